@@ -0,0 +1,38 @@
+//! Scan large generated source codes and print the throughput.
+//! Compare `cargo run --release --example bench`
+//! with `cargo run --release --example bench --features simd`
+use std::time::Instant;
+
+use uscan::{presets, Scanner, ScannerData};
+
+/// scan a source code several times and print the throughput
+fn bench(name: &str, source: &str) {
+    let config = presets::RUST.compile();
+    let mut scanner = Scanner::default().with_values(false);
+    let mut data = ScannerData::default();
+    let runs = 20;
+    let start = Instant::now();
+    for _ in 0..runs {
+        scanner.run(source, &config, &mut data).unwrap();
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+    println!(
+        "{:<12} {:>8} tokens, {:>7.1} MB/s",
+        name,
+        data.len(),
+        (source.len() * runs) as f64 / elapsed / 1_000_000.0
+    );
+}
+
+fn main() {
+    let line = "        let some_long_identifier = other_identifier + 42; // a comment explaining the line\n";
+    bench("code", &line.repeat(50_000));
+    // the long runs of spaces, identifier characters and comment text
+    // where the simd feature tests 8 bytes at a time
+    let indented = format!("{}x\n", " ".repeat(120));
+    bench("indentation", &indented.repeat(40_000));
+    let identifier = format!("{} ", "a_long_identifier_".repeat(8));
+    bench("identifiers", &identifier.repeat(30_000));
+    let comment = format!("// {}\n", "a long comment explaining the code ".repeat(4));
+    bench("comments", &comment.repeat(40_000));
+}
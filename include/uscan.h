@@ -0,0 +1,52 @@
+/* C interface of the uscan library, built with the ffi feature */
+#ifndef USCAN_H
+#define USCAN_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#define USCAN_SYMBOL 0
+#define USCAN_IDENTIFIER 1
+#define USCAN_STRING_LITERAL 2
+#define USCAN_CHAR_LITERAL 3
+#define USCAN_STRING_PART 4
+#define USCAN_INTERPOLATION_START 5
+#define USCAN_INTERPOLATION_END 6
+#define USCAN_NUMBER_LITERAL 7
+#define USCAN_KEYWORD 8
+#define USCAN_COMMENT 9
+#define USCAN_DIRECTIVE 10
+#define USCAN_IGNORE 11
+#define USCAN_NEW_LINE 12
+#define USCAN_EOF 13
+#define USCAN_UNKNOWN 14
+#define USCAN_CUSTOM 15
+#define USCAN_BOOL_LITERAL 16
+#define USCAN_NIL_LITERAL 17
+#define USCAN_REGEX_LITERAL 18
+#define USCAN_ATTRIBUTE 19
+#define USCAN_LIFETIME 20
+#define USCAN_PUNCTUATION 21
+
+/* start and len are in bytes, line starts at 1 */
+typedef struct UscanToken {
+    uint32_t kind;
+    size_t start;
+    size_t len;
+    size_t line;
+} UscanToken;
+
+typedef struct UscanResult UscanResult;
+
+/* scan a UTF-8 source code with a preset ("lua", "c", "rust", "python", "javascript", "json").
+   Returns NULL if the language is unknown or the source is not valid UTF-8 */
+UscanResult *uscan_scan(const uint8_t *source, size_t len, const char *language);
+size_t uscan_token_count(const UscanResult *result);
+/* returns false if the index is out of range */
+bool uscan_token_at(const UscanResult *result, size_t index, UscanToken *out);
+/* returns the number of the first error (1 for E0001, ...) or 0 */
+uint32_t uscan_error(const UscanResult *result, size_t *line, size_t *offset);
+void uscan_free(UscanResult *result);
+
+#endif
@@ -0,0 +1,127 @@
+//! Bracket matching for brace highlighting and balance checking
+use crate::{ScannerData, Span, TokenIndex};
+
+/// The brackets of most languages, for Brackets::new
+pub const DEFAULT_PAIRS: &[(&str, &str)] = &[("(", ")"), ("[", "]"), ("{", "}")];
+
+/// An unbalanced bracket found by Brackets::new
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BracketError {
+    /// an opening bracket without closing bracket
+    Unclosed { index: usize, span: Span },
+    /// a closing bracket without opening bracket
+    Unopened { index: usize, span: Span },
+    /// a closing bracket which does not match the innermost opening bracket
+    Mismatched { open: usize, close: usize, span: Span },
+}
+
+impl BracketError {
+    /// position of the faulty bracket in the source code, in bytes
+    pub fn span(&self) -> Span {
+        match self {
+            BracketError::Unclosed { span, .. }
+            | BracketError::Unopened { span, .. }
+            | BracketError::Mismatched { span, .. } => *span,
+        }
+    }
+}
+
+impl std::fmt::Display for BracketError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            BracketError::Unclosed { .. } => "unclosed bracket",
+            BracketError::Unopened { .. } => "unexpected closing bracket",
+            BracketError::Mismatched { .. } => "mismatched closing bracket",
+        };
+        write!(f, "{} at offset {}", message, self.span().start())
+    }
+}
+
+impl std::error::Error for BracketError {}
+
+/// The matching brackets of a scanned source code. Only symbol tokens are considered,
+/// so the brackets inside strings and comments are ignored.
+pub struct Brackets {
+    /// index of the matching bracket of each token, None for the other tokens
+    /// and the unbalanced brackets
+    matches: Vec<Option<TokenIndex>>,
+    /// opening brackets in the order of the source code, with their closing bracket
+    /// which may be a mismatched one
+    pairs: Vec<(TokenIndex, Option<TokenIndex>)>,
+    errors: Vec<BracketError>,
+}
+
+impl Brackets {
+    /// match the brackets of the tokens, with pairs of opening and closing symbols like DEFAULT_PAIRS
+    pub fn new(data: &ScannerData, pairs: &[(&str, &str)]) -> Self {
+        let mut result = Self {
+            matches: vec![None; data.len()],
+            pairs: Vec::new(),
+            errors: Vec::new(),
+        };
+        // opening brackets not closed yet, as positions in result.pairs, with their kind of bracket
+        let mut stack: Vec<(usize, usize)> = Vec::new();
+        for index in 0..data.len() {
+            if !data.token_kinds[index].is_symbol() {
+                continue;
+            }
+            let text = data.token_text(index);
+            let span = data.token_spans[index];
+            if let Some(kind) = pairs.iter().position(|(open, _)| *open == text) {
+                stack.push((result.pairs.len(), kind));
+                result.pairs.push((TokenIndex::new(index), None));
+            } else if let Some(kind) = pairs.iter().position(|(_, close)| *close == text) {
+                let Some((pos, open_kind)) = stack.pop() else {
+                    result.errors.push(BracketError::Unopened { index, span });
+                    continue;
+                };
+                let open = result.pairs[pos].0.index();
+                result.pairs[pos].1 = Some(TokenIndex::new(index));
+                if open_kind == kind {
+                    result.matches[open] = Some(TokenIndex::new(index));
+                    result.matches[index] = Some(TokenIndex::new(open));
+                } else {
+                    result.errors.push(BracketError::Mismatched {
+                        open,
+                        close: index,
+                        span,
+                    });
+                }
+            }
+        }
+        for (pos, _) in stack {
+            let index = result.pairs[pos].0.index();
+            result.errors.push(BracketError::Unclosed {
+                index,
+                span: data.token_spans[index],
+            });
+        }
+        result.errors.sort_by_key(|error| error.span().start());
+        result
+    }
+    /// index of the bracket matching the token at index, if it is a balanced bracket
+    pub fn matching(&self, index: usize) -> Option<usize> {
+        self.matches.get(index).copied().flatten().map(usize::from)
+    }
+    /// the unbalanced brackets, in the order of the source code
+    pub fn errors(&self) -> &[BracketError] {
+        &self.errors
+    }
+    pub fn is_balanced(&self) -> bool {
+        self.errors.is_empty()
+    }
+    /// the innermost brackets containing a byte offset, as the indices of the opening
+    /// and closing bracket tokens. The closing bracket is None if the bracket is not closed.
+    /// An offset is inside brackets when it is between the end of the opening bracket
+    /// and the start of the closing one.
+    pub fn enclosing(&self, data: &ScannerData, offset: usize) -> Option<(usize, Option<usize>)> {
+        self.pairs
+            .iter()
+            .filter(|(open, close)| {
+                data.token_spans[open.index()].end() <= offset
+                    && close.is_none_or(|close| offset <= data.token_spans[close.index()].start())
+            })
+            .max_by_key(|(open, _)| *open)
+            .map(|(open, close)| (open.index(), close.map(usize::from)))
+    }
+}
@@ -0,0 +1,190 @@
+use std::path::Path;
+
+use crate::value::{Value, ValueError};
+use crate::{
+    is_unicode_alpha, is_unicode_alphanum, ControlCharPolicy, OwnedScannerConfig,
+    UnterminatedStringPolicy,
+};
+
+/// Error while loading a language definition
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    /// the document is not valid JSON/TOML
+    Syntax(ValueError),
+    /// unknown key or value of the wrong type
+    InvalidField(String),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "{}", e),
+            ConfigError::Syntax(e) => write!(f, "syntax error at line {}", e),
+            ConfigError::InvalidField(field) => write!(f, "invalid field '{}'", field),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(e: std::io::Error) -> Self {
+        ConfigError::Io(e)
+    }
+}
+
+impl From<ValueError> for ConfigError {
+    fn from(e: ValueError) -> Self {
+        ConfigError::Syntax(e)
+    }
+}
+
+impl OwnedScannerConfig {
+    /// load a language definition from a JSON document. Example :
+    /// `{ "keywords": ["if", "else"], "symbols": ["==", "="], "single_line_cmt": "//" }`
+    pub fn from_json(text: &str) -> Result<Self, ConfigError> {
+        Self::from_value(&Value::from_json(text)?)
+    }
+    /// load a language definition from a TOML document, using the same keys as from_json
+    pub fn from_toml(text: &str) -> Result<Self, ConfigError> {
+        Self::from_value(&Value::from_toml(text)?)
+    }
+    /// load a language definition from a .json or .toml file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml(&text),
+            _ => Self::from_json(&text),
+        }
+    }
+    /// build a language definition from a parsed document
+    pub fn from_value(value: &Value) -> Result<Self, ConfigError> {
+        let fields = value
+            .as_object()
+            .ok_or_else(|| ConfigError::InvalidField(String::new()))?;
+        let mut config = Self::default();
+        for (key, value) in fields {
+            let invalid = || ConfigError::InvalidField(key.clone());
+            match key.as_str() {
+                "keywords" => config.keywords = strings(value).ok_or_else(invalid)?,
+                "symbols" => config.symbols = strings(value).ok_or_else(invalid)?,
+                "single_line_cmt" => config.single_line_cmt = string(value).ok_or_else(invalid)?,
+                "multi_line_cmt_start" => {
+                    config.multi_line_cmt_start = string(value).ok_or_else(invalid)?
+                }
+                "multi_line_cmt_end" => {
+                    config.multi_line_cmt_end = string(value).ok_or_else(invalid)?
+                }
+                "nested_comments" => {
+                    config.nested_comments = value.as_bool().ok_or_else(invalid)?
+                }
+                "punctuation" => config.punctuation = strings(value).ok_or_else(invalid)?,
+                "true_literals" => config.true_literals = strings(value).ok_or_else(invalid)?,
+                "false_literals" => config.false_literals = strings(value).ok_or_else(invalid)?,
+                "nil_literals" => config.nil_literals = strings(value).ok_or_else(invalid)?,
+                "attribute_prefixes" => {
+                    config.attribute_prefixes = strings(value).ok_or_else(invalid)?
+                }
+                "directive_prefixes" => {
+                    config.directive_prefixes = strings(value).ok_or_else(invalid)?
+                }
+                "line_continuation" => {
+                    config.line_continuation = char(value).ok_or_else(invalid)?
+                }
+                "string_delimiters" => {
+                    config.string_delimiters = strings(value).ok_or_else(invalid)?
+                }
+                "char_delimiter" => config.char_delimiter = char(value).ok_or_else(invalid)?,
+                "lifetimes" => config.lifetimes = value.as_bool().ok_or_else(invalid)?,
+                "raw_string_start" => config.raw_string_start = string(value).ok_or_else(invalid)?,
+                "raw_string_end" => config.raw_string_end = string(value).ok_or_else(invalid)?,
+                "raw_string_level" => config.raw_string_level = char(value).ok_or_else(invalid)?,
+                "heredoc_start" => config.heredoc_start = string(value).ok_or_else(invalid)?,
+                "interpolated_string" => {
+                    config.interpolated_string = char(value).ok_or_else(invalid)?
+                }
+                "interpolation_start" => {
+                    config.interpolation_start = string(value).ok_or_else(invalid)?
+                }
+                "interpolation_end" => config.interpolation_end = string(value).ok_or_else(invalid)?,
+                "strings_may_span_lines" => {
+                    config.strings_may_span_lines = value.as_bool().ok_or_else(invalid)?
+                }
+                "unterminated_strings" => {
+                    config.unterminated_strings = match value.as_str() {
+                        Some("error") => UnterminatedStringPolicy::Error,
+                        Some("line_end") => UnterminatedStringPolicy::RecoverAtLineEnd,
+                        Some("eof") => UnterminatedStringPolicy::RecoverAtEof,
+                        _ => return Err(invalid()),
+                    }
+                }
+                "control_chars" => {
+                    config.control_chars = match value.as_str() {
+                        Some("error") => ControlCharPolicy::Error,
+                        Some("warning") => ControlCharPolicy::Warning,
+                        Some("pass_through") => ControlCharPolicy::PassThrough,
+                        _ => return Err(invalid()),
+                    }
+                }
+                "scientific_notation" => {
+                    config.scientific_notation = value.as_bool().ok_or_else(invalid)?
+                }
+                "number_separator" => config.number_separator = char(value).ok_or_else(invalid)?,
+                "regex_literals" => {
+                    config.regex_literals = value.as_bool().ok_or_else(invalid)?
+                }
+                "decode_escapes" => {
+                    config.decode_escapes = value.as_bool().ok_or_else(invalid)?
+                }
+                "hex_float" => config.hex_float = value.as_bool().ok_or_else(invalid)?,
+                "legacy_octal" => config.legacy_octal = value.as_bool().ok_or_else(invalid)?,
+                "quoted_identifiers" => {
+                    config.quoted_identifiers = strings(value).ok_or_else(invalid)?
+                }
+                "string_prefixes" => {
+                    config.string_prefixes = strings(value).ok_or_else(invalid)?
+                }
+                "number_suffixes" => config.number_suffixes = strings(value).ok_or_else(invalid)?,
+                "unicode_identifiers" => {
+                    if value.as_bool().ok_or_else(invalid)? {
+                        config.identifier_start = is_unicode_alpha;
+                        config.identifier_char = is_unicode_alphanum;
+                    }
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(config)
+    }
+}
+
+fn strings(value: &Value) -> Option<Vec<String>> {
+    value
+        .as_array()?
+        .iter()
+        .map(|v| v.as_str().map(str::to_owned))
+        .collect()
+}
+
+/// an optional string. null is accepted in JSON documents
+fn string(value: &Value) -> Option<Option<String>> {
+    match value {
+        Value::Null => Some(None),
+        Value::String(s) => Some(Some(s.clone())),
+        _ => None,
+    }
+}
+
+/// an optional single character string
+fn char(value: &Value) -> Option<Option<char>> {
+    match string(value)? {
+        None => Some(None),
+        Some(s) => {
+            let mut chars = s.chars();
+            let c = chars.next()?;
+            chars.next().is_none().then_some(Some(c))
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Human readable rendering of scan errors
+//!
+//! ```text
+//! error[E0001]: unknown token
+//!  --> foo.lua:2:13
+//!   |
+//! 2 | local b = a ? 2
+//!   |             ^
+//! ```
+use crate::{suggest, ScanError};
+
+/// render an error with the file name, the offending source line and carets under the error span
+pub fn render(error: &ScanError, source: &str, file_name: &str) -> String {
+    let span = error.span();
+    let start = span.span.start();
+    let line_start = source[..start].rfind('\n').map_or(0, |pos| pos + 1);
+    let line_end = source[start..].find(['\n', '\r']).map_or(source.len(), |pos| start + pos);
+    let text = &source[line_start..line_end];
+    let line = source[..start].matches('\n').count() + 1;
+    let carets = source[start..line_end.max(start)]
+        .chars()
+        .take(span.span.len())
+        .count()
+        .min(span.span.len())
+        .max(1);
+    let margin = " ".repeat(line.to_string().len());
+    let mut out = format!("error[{}]: {}\n", error.code(), error.message());
+    out.push_str(&format!("{}--> {}:{}:{}\n", margin, file_name, line, span.column + 1));
+    out.push_str(&format!("{} |\n", margin));
+    out.push_str(&format!("{} | {}\n", line, text));
+    out.push_str(&format!(
+        "{} | {}{}\n",
+        margin,
+        caret_padding(&source[line_start..start]),
+        "^".repeat(carets)
+    ));
+    out
+}
+
+/// render an error like render, adding a "did you mean" line for an unknown token
+/// looking like one of the symbols, like `!=` for `~=` in Lua
+///
+/// ```text
+/// error[E0001]: unknown token
+///  --> foo.lua:1:6
+///   |
+/// 1 | if a != 2 then
+///   |      ^
+///   = help: did you mean `~=`?
+/// ```
+pub fn render_with_suggestions<S: AsRef<str>>(
+    error: &ScanError,
+    source: &str,
+    file_name: &str,
+    symbols: &[S],
+) -> String {
+    let mut out = render(error, source, file_name);
+    let candidates = symbol_suggestions(error, source, symbols);
+    if !candidates.is_empty() {
+        let line = source[..error.span().span.start()].matches('\n').count() + 1;
+        let margin = " ".repeat(line.to_string().len());
+        out.push_str(&format!("{} = help: did you mean {}?\n", margin, or_list(&candidates)));
+    }
+    out
+}
+
+/// the symbols differing by one character from the punctuation starting at an unknown token
+pub fn symbol_suggestions<'a, S: AsRef<str>>(
+    error: &ScanError,
+    source: &str,
+    symbols: &'a [S],
+) -> Vec<&'a str> {
+    let ScanError::UnknownToken(_) = error else {
+        return Vec::new();
+    };
+    let start = error.span().span.start();
+    let max_len = symbols.iter().map(|s| s.as_ref().chars().count()).max().unwrap_or(0);
+    let Some(rest) = source.get(start..) else {
+        return Vec::new();
+    };
+    let word_len: usize = rest
+        .chars()
+        .take_while(|c| !c.is_whitespace() && !c.is_alphanumeric() && !matches!(c, '"' | '\''))
+        .take(max_len)
+        .map(char::len_utf8)
+        .sum();
+    let word = &rest[..word_len];
+    let len = word.chars().count();
+    if len < 2 {
+        return Vec::new();
+    }
+    suggest::suggestions(word, symbols, 1)
+        .into_iter()
+        .filter(|symbol| symbol.chars().count() == len)
+        .collect()
+}
+
+/// `a`, `b` or `c`
+fn or_list(words: &[&str]) -> String {
+    let quoted: Vec<String> = words.iter().map(|word| format!("`{}`", word)).collect();
+    match quoted.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} or {}", rest.join(", "), last),
+        _ => quoted.concat(),
+    }
+}
+
+/// spaces to put the caret under a position, keeping the tabulations to stay aligned
+fn caret_padding(before: &str) -> String {
+    before
+        .chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect()
+}
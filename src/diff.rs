@@ -0,0 +1,188 @@
+//! Differences between two scans of a source code, at the token level
+use std::ops::Range;
+
+use crate::ScannerData;
+
+/// A change turning the tokens of an old scan into the tokens of a new scan.
+/// The ranges are token indices in ScannerData
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenEdit {
+    /// new tokens inserted before the old token at index old
+    Insert { old: usize, new: Range<usize> },
+    /// old tokens removed. new is the index of the new token following them
+    Delete { old: Range<usize>, new: usize },
+    /// old tokens replaced by new ones
+    Replace { old: Range<usize>, new: Range<usize> },
+}
+
+/// the edits turning the tokens of old into the tokens of new, in the order of the source code.
+/// Two tokens are equal when they have the same kind and the same text, whatever their position.
+/// Computes a minimal diff with the Myers algorithm, fast when the scans are similar,
+/// for example before and after a modification in an editor
+pub fn diff(old: &ScannerData, new: &ScannerData) -> Vec<TokenEdit> {
+    let same = |i: usize, j: usize| {
+        old.token_kinds[i] == new.token_kinds[j] && old.token_text(i) == new.token_text(j)
+    };
+    // skip the common prefix and suffix
+    let prefix = (0..old.len().min(new.len()))
+        .take_while(|i| same(*i, *i))
+        .count();
+    let suffix = (0..old.len().min(new.len()) - prefix)
+        .take_while(|i| same(old.len() - 1 - i, new.len() - 1 - i))
+        .count();
+    let old_range = prefix..old.len() - suffix;
+    let new_range = prefix..new.len() - suffix;
+    let mut pairs = common_tokens(old_range.len(), new_range.len(), |i, j| {
+        same(prefix + i, prefix + j)
+    });
+    pairs.push((old_range.len(), new_range.len()));
+    let mut edits = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    for (next_i, next_j) in pairs {
+        let old = prefix + i..prefix + next_i;
+        let new = prefix + j..prefix + next_j;
+        match (old.is_empty(), new.is_empty()) {
+            (true, true) => (),
+            (true, false) => edits.push(TokenEdit::Insert { old: old.start, new }),
+            (false, true) => edits.push(TokenEdit::Delete { old, new: new.start }),
+            (false, false) => edits.push(TokenEdit::Replace { old, new }),
+        }
+        (i, j) = (next_i + 1, next_j + 1);
+    }
+    edits
+}
+
+/// the pairs of equal elements of a longest common subsequence of two sequences of lengths n and m.
+/// Uses the linear space variant of the Myers algorithm : the middle of an optimal edit path
+/// splits the sequences in two smaller problems
+fn common_tokens(n: usize, m: usize, same: impl Fn(usize, usize) -> bool) -> Vec<(usize, usize)> {
+    let mut diagonals = Diagonals::new(n + m);
+    let mut pairs = Vec::new();
+    split(0..n, 0..m, &same, &mut diagonals, &mut pairs);
+    pairs
+}
+
+/// the furthest x reached on each diagonal k = x - y, forward and backward
+struct Diagonals {
+    forward: Vec<usize>,
+    backward: Vec<usize>,
+    /// index of the diagonal 0
+    offset: isize,
+}
+
+impl Diagonals {
+    fn new(max: usize) -> Self {
+        Self {
+            forward: vec![0; 2 * max + 3],
+            backward: vec![0; 2 * max + 3],
+            offset: max as isize + 1,
+        }
+    }
+    fn at(&self, k: isize) -> usize {
+        (k + self.offset) as usize
+    }
+}
+
+/// add to pairs the common elements of old[old_range] and new[new_range]
+fn split(
+    old: Range<usize>,
+    new: Range<usize>,
+    same: &impl Fn(usize, usize) -> bool,
+    diagonals: &mut Diagonals,
+    pairs: &mut Vec<(usize, usize)>,
+) {
+    let prefix = (0..old.len().min(new.len()))
+        .take_while(|i| same(old.start + i, new.start + i))
+        .count();
+    pairs.extend((0..prefix).map(|i| (old.start + i, new.start + i)));
+    let (old, new) = (old.start + prefix..old.end, new.start + prefix..new.end);
+    let suffix = (0..old.len().min(new.len()))
+        .take_while(|i| same(old.end - 1 - i, new.end - 1 - i))
+        .count();
+    let (old, new) = (old.start..old.end - suffix, new.start..new.end - suffix);
+    if !old.is_empty() && !new.is_empty() {
+        if let Some((x, y)) = middle(old.clone(), new.clone(), same, diagonals) {
+            split(old.start..x, new.start..y, same, diagonals, pairs);
+            split(x..old.end, y..new.end, same, diagonals, pairs);
+        }
+    }
+    pairs.extend((0..suffix).map(|i| (old.end + i, new.end + i)));
+}
+
+/// a point of an optimal edit path between old and new, strictly between their starts and ends,
+/// found by searching from both ends until the paths meet
+fn middle(
+    old: Range<usize>,
+    new: Range<usize>,
+    same: &impl Fn(usize, usize) -> bool,
+    v: &mut Diagonals,
+) -> Option<(usize, usize)> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let delta = n - m;
+    let odd = delta & 1 == 1;
+    let at_one = v.at(1);
+    v.forward[at_one] = 0;
+    v.backward[at_one] = 0;
+    for d in 0..=(n + m + 1) / 2 {
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && v.forward[v.at(k - 1)] < v.forward[v.at(k + 1)]) {
+                v.forward[v.at(k + 1)] as isize
+            } else {
+                v.forward[v.at(k - 1)] as isize + 1
+            };
+            let (x0, y0) = (x, x - k);
+            let mut y = y0;
+            while x < n && y < m && same(old.start + x as usize, new.start + y as usize) {
+                x += 1;
+                y += 1;
+            }
+            let at = v.at(k);
+            v.forward[at] = x as usize;
+            if odd && (k - delta).abs() < d {
+                let back = v.backward[v.at(delta - k)] as isize;
+                if x + back >= n {
+                    return point(&old, &new, x0, y0);
+                }
+            }
+        }
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && v.backward[v.at(k - 1)] < v.backward[v.at(k + 1)]) {
+                v.backward[v.at(k + 1)] as isize
+            } else {
+                v.backward[v.at(k - 1)] as isize + 1
+            };
+            let mut y = x - k;
+            while x < n
+                && y < m
+                && same(old.end - 1 - x as usize, new.end - 1 - y as usize)
+            {
+                x += 1;
+                y += 1;
+            }
+            let at = v.at(k);
+            v.backward[at] = x as usize;
+            if !odd && (k - delta).abs() <= d {
+                let forward = v.forward[v.at(delta - k)] as isize;
+                if x + forward >= n {
+                    return point(&old, &new, n - x, m - y);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// the absolute position of a point of the edit path, None at the start or the end
+fn point(
+    old: &Range<usize>,
+    new: &Range<usize>,
+    x: isize,
+    y: isize,
+) -> Option<(usize, usize)> {
+    if x < 0 || y < 0 || x as usize > old.len() || y as usize > new.len() {
+        return None;
+    }
+    let (x, y) = (old.start + x as usize, new.start + y as usize);
+    let inside = (x, y) != (old.start, new.start) && (x, y) != (old.end, new.end);
+    inside.then_some((x, y))
+}
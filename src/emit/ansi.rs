@@ -0,0 +1,57 @@
+//! Terminal syntax highlighting with ANSI escape sequences
+use super::theme::Theme;
+use crate::{ScannerData, TokenKind};
+
+/// the default SGR parameters of each token kind, for example "35" for magenta.
+/// Symbols, identifiers and spaces are not colored
+pub fn default_color(kind: TokenKind) -> Option<&'static str> {
+    match kind {
+        TokenKind::Keyword | TokenKind::Lifetime => Some("35"),
+        TokenKind::StringLiteral | TokenKind::StringPart | TokenKind::CharLiteral => Some("32"),
+        TokenKind::InterpolationStart | TokenKind::InterpolationEnd => Some("36"),
+        TokenKind::NumberLiteral | TokenKind::BoolLiteral | TokenKind::NilLiteral => Some("33"),
+        TokenKind::Comment => Some("90"),
+        TokenKind::Directive | TokenKind::RegexLiteral | TokenKind::Attribute => Some("36"),
+        TokenKind::Unknown => Some("31;4"),
+        TokenKind::Custom(_) => Some("34"),
+        _ => None,
+    }
+}
+
+/// render the source code of a ScannerData with the default colors
+pub fn render(data: &ScannerData) -> String {
+    render_with(data, default_color)
+}
+
+/// render the source code of a ScannerData with a custom palette giving the SGR parameters
+/// of each token kind, for example "1;34" for bold blue. Tokens with no color are left as is
+pub fn render_with<'a>(data: &ScannerData, color: impl Fn(TokenKind) -> Option<&'a str>) -> String {
+    let mut out = String::with_capacity(data.source.len() * 2);
+    for (kind, text) in super::segments(data) {
+        push_colored(kind.and_then(&color), text, &mut out);
+    }
+    out
+}
+
+/// render the source code of a ScannerData with the 24 bits colors of a theme
+pub fn render_themed(data: &ScannerData, theme: &Theme) -> String {
+    let mut out = String::with_capacity(data.source.len() * 2);
+    for (kind, text) in super::segments(data) {
+        let sgr = theme.style(kind, text).to_ansi();
+        push_colored((!sgr.is_empty()).then_some(sgr.as_str()), text, &mut out);
+    }
+    out
+}
+
+fn push_colored(color: Option<&str>, text: &str, out: &mut String) {
+    match color {
+        Some(color) => {
+            out.push_str("\x1b[");
+            out.push_str(color);
+            out.push('m');
+            out.push_str(text);
+            out.push_str("\x1b[0m");
+        }
+        None => out.push_str(text),
+    }
+}
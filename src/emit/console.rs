@@ -0,0 +1,62 @@
+//! Highlighting for roguelike consoles, like doryen-rs, where each cell has
+//! a character, a foreground and a background color. Example with doryen-rs :
+//!
+//! ```text
+//! let line = console::render_line(&data, &theme, line_number);
+//! for (x, c) in line.chars.iter().enumerate() {
+//!     con.cell(x as i32, y, Some(*c as u16), Some(line.fore[x]), Some(line.back[x]));
+//! }
+//! ```
+use super::theme::{Color, Theme};
+use crate::ScannerData;
+
+/// an RGBA color, like doryen-rs Color
+pub type ConsoleColor = (u8, u8, u8, u8);
+
+/// color used when the theme has no default foreground color
+pub const DEFAULT_FORE: ConsoleColor = (255, 255, 255, 255);
+/// color used when the theme has no default background color
+pub const DEFAULT_BACK: ConsoleColor = (0, 0, 0, 255);
+
+impl From<Color> for ConsoleColor {
+    fn from(color: Color) -> Self {
+        (color.r, color.g, color.b, 255)
+    }
+}
+
+/// The cells of a line of source code : one character and two colors per cell
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ConsoleLine {
+    pub chars: Vec<char>,
+    pub fore: Vec<ConsoleColor>,
+    pub back: Vec<ConsoleColor>,
+}
+
+/// the cells of a line (in the position convention of the ScannerData) of its source code,
+/// without its end of line characters. Empty if the line does not exist
+pub fn render_line(data: &ScannerData, theme: &Theme, line: usize) -> ConsoleLine {
+    let mut out = ConsoleLine::default();
+    let Some(start) = data.index_line(line).and_then(|line| data.line_index.line_start(line)) else {
+        return out;
+    };
+    let end = data.source[start..]
+        .find('\n')
+        .map_or(data.source.len(), |pos| start + pos);
+    let text = data.source[start..end].trim_end_matches('\r');
+    // first token ending after the line start
+    let mut index = data.token_spans.partition_point(|span| span.end() <= start);
+    for (offset, c) in text.char_indices() {
+        let offset = start + offset;
+        while data.token_spans.get(index).is_some_and(|span| span.end() <= offset) {
+            index += 1;
+        }
+        let style = match data.get(index).filter(|token| token.span.start() <= offset) {
+            Some(token) => theme.style(Some(token.kind), token.text(data.source)),
+            None => theme.style(None, ""),
+        };
+        out.chars.push(c);
+        out.fore.push(style.fg.map_or(DEFAULT_FORE, Into::into));
+        out.back.push(style.bg.map_or(DEFAULT_BACK, Into::into));
+    }
+    out
+}
@@ -0,0 +1,88 @@
+//! HTML syntax highlighting. Each token is wrapped in a `<span class="...">`
+//! which can be styled with CSS :
+//!
+//! ```text
+//! <pre><code><span class="keyword">local</span> a = <span class="number">1</span></code></pre>
+//! ```
+use super::theme::Theme;
+use crate::{ScannerData, TokenKind};
+
+/// the default CSS class of each token kind. Custom tokens use their class name.
+/// Symbols other than punctuation, identifiers and spaces have no class
+pub fn default_class(kind: TokenKind) -> Option<&'static str> {
+    match kind {
+        TokenKind::Keyword => Some("keyword"),
+        TokenKind::Punctuation => Some("punctuation"),
+        TokenKind::Lifetime => Some("lifetime"),
+        TokenKind::StringLiteral | TokenKind::StringPart | TokenKind::CharLiteral => Some("string"),
+        TokenKind::InterpolationStart | TokenKind::InterpolationEnd => Some("interpolation"),
+        TokenKind::NumberLiteral => Some("number"),
+        TokenKind::BoolLiteral | TokenKind::NilLiteral => Some("constant"),
+        TokenKind::RegexLiteral => Some("regex"),
+        TokenKind::Comment => Some("comment"),
+        TokenKind::Directive => Some("directive"),
+        TokenKind::Attribute => Some("attribute"),
+        TokenKind::Unknown => Some("error"),
+        TokenKind::Custom(_) => kind.class(),
+        _ => None,
+    }
+}
+
+/// render the source code of a ScannerData as HTML, using default_class for the class names.
+/// The result should be placed in a `<pre>` element
+pub fn render(data: &ScannerData) -> String {
+    render_with(data, default_class)
+}
+
+/// render the source code of a ScannerData as HTML with a custom class name mapping.
+/// Tokens with no class are not wrapped in a span
+pub fn render_with<'a>(data: &ScannerData, class: impl Fn(TokenKind) -> Option<&'a str>) -> String {
+    let mut out = String::with_capacity(data.source.len() * 2);
+    for (kind, text) in super::segments(data) {
+        match kind.and_then(&class) {
+            Some(class) => {
+                out.push_str("<span class=\"");
+                escape(class, &mut out);
+                out.push_str("\">");
+                escape(text, &mut out);
+                out.push_str("</span>");
+            }
+            None => escape(text, &mut out),
+        }
+    }
+    out
+}
+
+/// render the source code of a ScannerData as HTML with inline styles from a theme.
+/// The default style of the theme should be applied to the enclosing `<pre>` element
+/// with `theme.default.to_css()`
+pub fn render_themed(data: &ScannerData, theme: &Theme) -> String {
+    let mut out = String::with_capacity(data.source.len() * 2);
+    for (kind, text) in super::segments(data) {
+        let style = theme.style(kind, text);
+        if style == theme.default {
+            escape(text, &mut out);
+        } else {
+            out.push_str("<span style=\"");
+            out.push_str(&style.to_css());
+            out.push_str("\">");
+            escape(text, &mut out);
+            out.push_str("</span>");
+        }
+    }
+    out
+}
+
+/// append a text to out, escaping the HTML special characters
+pub fn escape(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! Language Server Protocol semantic tokens.
+//! encode converts the tokens of a ScannerData into the integer array of a
+//! `SemanticTokens` response, and Legend gives the matching `SemanticTokensLegend`
+use crate::{ScannerData, TokenKind};
+
+/// The token types and modifiers announced by the server, and the type of each token kind
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Legend {
+    /// names of the token types, in the order of their indices
+    pub token_types: Vec<String>,
+    /// names of the token modifiers, in the order of their bits
+    pub token_modifiers: Vec<String>,
+    /// type index and modifier bits of each token kind
+    kinds: Vec<(TokenKind, u32, u32)>,
+}
+
+impl Default for Legend {
+    /// keyword, string, number, comment, macro (directives), operator (symbols), variable (identifiers),
+    /// regexp, decorator (attributes) and typeParameter (lifetimes)
+    fn default() -> Self {
+        Self::new(&[
+            (TokenKind::Keyword, "keyword"),
+            (TokenKind::StringLiteral, "string"),
+            (TokenKind::StringPart, "string"),
+            (TokenKind::CharLiteral, "string"),
+            (TokenKind::NumberLiteral, "number"),
+            (TokenKind::BoolLiteral, "keyword"),
+            (TokenKind::NilLiteral, "keyword"),
+            (TokenKind::Comment, "comment"),
+            (TokenKind::Directive, "macro"),
+            (TokenKind::Symbol, "operator"),
+            (TokenKind::Identifier, "variable"),
+            (TokenKind::RegexLiteral, "regexp"),
+            (TokenKind::Attribute, "decorator"),
+            (TokenKind::Lifetime, "typeParameter"),
+        ])
+    }
+}
+
+impl Legend {
+    /// a legend from the type name of each token kind. The kinds not listed are not encoded
+    pub fn new(types: &[(TokenKind, &str)]) -> Self {
+        let mut legend = Self {
+            token_types: Vec::new(),
+            token_modifiers: Vec::new(),
+            kinds: Vec::new(),
+        };
+        for (kind, name) in types {
+            let index = match legend.token_types.iter().position(|t| t == name) {
+                Some(index) => index,
+                None => {
+                    legend.token_types.push(name.to_string());
+                    legend.token_types.len() - 1
+                }
+            };
+            legend.kinds.retain(|(k, _, _)| k != kind);
+            legend.kinds.push((*kind, index as u32, 0));
+        }
+        legend
+    }
+    /// add modifiers to the tokens of a kind, for example "readonly"
+    pub fn with_modifiers(mut self, kind: TokenKind, modifiers: &[&str]) -> Self {
+        for modifier in modifiers {
+            let bit = match self.token_modifiers.iter().position(|m| m == modifier) {
+                Some(bit) => bit,
+                None => {
+                    self.token_modifiers.push(modifier.to_string());
+                    self.token_modifiers.len() - 1
+                }
+            };
+            for entry in self.kinds.iter_mut().filter(|(k, _, _)| *k == kind) {
+                entry.2 |= 1 << bit;
+            }
+        }
+        self
+    }
+    /// the type index and modifier bits of a token kind, or None if it is not encoded
+    pub fn get(&self, kind: TokenKind) -> Option<(u32, u32)> {
+        self.kinds
+            .iter()
+            .find(|(k, _, _)| *k == kind)
+            .map(|(_, index, modifiers)| (*index, *modifiers))
+    }
+}
+
+/// the semantic tokens of a ScannerData, 5 integers per token : line delta, start delta,
+/// length, type index and modifier bits. Positions and lengths are in UTF-16 code units.
+/// Tokens spanning several lines, like multi-line comments, are split in one token per line
+pub fn encode(data: &ScannerData, legend: &Legend) -> Vec<u32> {
+    let mut out = Vec::new();
+    let (mut previous_line, mut previous_start) = (0, 0);
+    for token in data.iter() {
+        let Some((index, modifiers)) = legend.get(token.kind) else {
+            continue;
+        };
+        let mut offset = token.span.start();
+        for part in token.text(data.source).split('\n') {
+            let text = part.strip_suffix('\r').unwrap_or(part);
+            if !text.is_empty() {
+                let (line, start) = data.utf16_position(offset);
+                let delta_start = match line == previous_line {
+                    true => start - previous_start,
+                    false => start,
+                };
+                out.extend([
+                    (line - previous_line) as u32,
+                    delta_start as u32,
+                    text.encode_utf16().count() as u32,
+                    index,
+                    modifiers,
+                ]);
+                (previous_line, previous_start) = (line, start);
+            }
+            offset += part.len() + 1;
+        }
+    }
+    out
+}
@@ -0,0 +1,36 @@
+//! Rendering of a scanned source code with syntax highlighting
+use crate::{ScannerData, TokenKind};
+
+pub mod ansi;
+pub mod console;
+pub mod html;
+pub mod lsp;
+pub mod theme;
+
+/// Split the source code of a ScannerData into consecutive parts covering it entirely :
+/// the tokens with their kind, and the text between them (spaces, skipped comments) with None
+pub fn segments<'a>(
+    data: &'a ScannerData,
+) -> impl Iterator<Item = (Option<TokenKind>, &'a str)> + 'a {
+    let mut offset = 0;
+    let mut tokens = data.iter().peekable();
+    std::iter::from_fn(move || {
+        let source = data.source;
+        if offset >= source.len() {
+            return None;
+        }
+        match tokens.peek() {
+            Some(token) if token.span.start() <= offset => {
+                let token = tokens.next()?;
+                offset = token.span.end();
+                Some((Some(token.kind), token.text(source)))
+            }
+            next => {
+                let end = next.map_or(source.len(), |token| token.span.start());
+                let gap = &source[offset..end];
+                offset = end;
+                Some((None, gap))
+            }
+        }
+    })
+}
@@ -0,0 +1,357 @@
+//! Colors and text attributes of the tokens, shared by the emitters
+#[cfg(feature = "config")]
+use crate::value::Value;
+#[cfg(feature = "config")]
+use crate::ConfigError;
+use crate::TokenKind;
+
+/// A 24 bits RGB color
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+    /// parse a "#rrggbb" color
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#')?;
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+        let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        Some(Self::new(byte(0)?, byte(2)?, byte(4)?))
+    }
+    /// the "#rrggbb" representation of the color
+    pub fn to_hex(&self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+/// How a token is displayed. Colors left to None use the theme default ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Style {
+    /// text color
+    pub fg: Option<Color>,
+    /// background color
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+impl Style {
+    pub const fn fg(color: Color) -> Self {
+        Self {
+            fg: Some(color),
+            bg: None,
+            bold: false,
+            italic: false,
+            underline: false,
+        }
+    }
+    /// the CSS properties of the style, for example "color:#c678dd;font-weight:bold"
+    pub fn to_css(&self) -> String {
+        let mut css = Vec::new();
+        if let Some(fg) = self.fg {
+            css.push(format!("color:{}", fg.to_hex()));
+        }
+        if let Some(bg) = self.bg {
+            css.push(format!("background-color:{}", bg.to_hex()));
+        }
+        if self.bold {
+            css.push("font-weight:bold".to_owned());
+        }
+        if self.italic {
+            css.push("font-style:italic".to_owned());
+        }
+        if self.underline {
+            css.push("text-decoration:underline".to_owned());
+        }
+        css.join(";")
+    }
+    /// the SGR parameters of the style, using 24 bits colors, for example "1;38;2;198;120;221"
+    pub fn to_ansi(&self) -> String {
+        let mut sgr = Vec::new();
+        if self.bold {
+            sgr.push("1".to_owned());
+        }
+        if self.italic {
+            sgr.push("3".to_owned());
+        }
+        if self.underline {
+            sgr.push("4".to_owned());
+        }
+        if let Some(c) = self.fg {
+            sgr.push(format!("38;2;{};{};{}", c.r, c.g, c.b));
+        }
+        if let Some(c) = self.bg {
+            sgr.push(format!("48;2;{};{};{}", c.r, c.g, c.b));
+        }
+        sgr.join(";")
+    }
+    #[cfg(feature = "config")]
+    /// parse a style. The keywords key is allowed for keyword categories
+    fn from_value(value: &Value, category: bool) -> Option<Self> {
+        let color = |key| match value.get(key) {
+            None => Some(None),
+            Some(color) => Color::from_hex(color.as_str()?).map(Some),
+        };
+        let flag = |key| match value.get(key) {
+            None => Some(false),
+            Some(flag) => flag.as_bool(),
+        };
+        let known = ["fg", "bg", "bold", "italic", "underline"];
+        let known = |key: &str| known.contains(&key) || (category && key == "keywords");
+        if value.as_object()?.iter().any(|(key, _)| !known(key)) {
+            return None;
+        }
+        Some(Self {
+            fg: color("fg")?,
+            bg: color("bg")?,
+            bold: flag("bold")?,
+            italic: flag("italic")?,
+            underline: flag("underline")?,
+        })
+    }
+}
+
+/// A group of keywords displayed differently from the other keywords,
+/// for example the control flow keywords
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeywordCategory {
+    pub name: String,
+    pub keywords: Vec<String>,
+    pub style: Style,
+}
+
+/// The style of each token kind
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    pub name: String,
+    /// style of the text without a specific style, spaces included
+    pub default: Style,
+    /// style of each token kind
+    pub styles: Vec<(TokenKind, Style)>,
+    /// styles of some keywords, taking precedence over the keyword style
+    pub keyword_categories: Vec<KeywordCategory>,
+}
+
+/// the names of the token kinds in theme files
+#[cfg(feature = "config")]
+const KIND_KEYS: [(TokenKind, &str); 21] = [
+    (TokenKind::Symbol, "symbol"),
+    (TokenKind::Punctuation, "punctuation"),
+    (TokenKind::Identifier, "identifier"),
+    (TokenKind::StringLiteral, "string_literal"),
+    (TokenKind::CharLiteral, "char_literal"),
+    (TokenKind::Lifetime, "lifetime"),
+    (TokenKind::StringPart, "string_part"),
+    (TokenKind::InterpolationStart, "interpolation_start"),
+    (TokenKind::InterpolationEnd, "interpolation_end"),
+    (TokenKind::NumberLiteral, "number_literal"),
+    (TokenKind::BoolLiteral, "bool_literal"),
+    (TokenKind::NilLiteral, "nil_literal"),
+    (TokenKind::RegexLiteral, "regex_literal"),
+    (TokenKind::Keyword, "keyword"),
+    (TokenKind::Comment, "comment"),
+    (TokenKind::Directive, "directive"),
+    (TokenKind::Attribute, "attribute"),
+    (TokenKind::Ignore, "ignore"),
+    (TokenKind::NewLine, "new_line"),
+    (TokenKind::Eof, "eof"),
+    (TokenKind::Unknown, "unknown"),
+];
+
+impl Theme {
+    /// light text on a dark background
+    pub fn dark() -> Self {
+        let string = Style::fg(Color::new(0x98, 0xc3, 0x79));
+        let constant = Style::fg(Color::new(0xd1, 0x9a, 0x66));
+        Self {
+            name: "dark".to_owned(),
+            default: Style {
+                bg: Some(Color::new(0x28, 0x2c, 0x34)),
+                ..Style::fg(Color::new(0xab, 0xb2, 0xbf))
+            },
+            styles: vec![
+                (TokenKind::Keyword, Style::fg(Color::new(0xc6, 0x78, 0xdd))),
+                (TokenKind::Lifetime, Style::fg(Color::new(0xe0, 0x6c, 0x75))),
+                (TokenKind::StringLiteral, string),
+                (TokenKind::CharLiteral, string),
+                (TokenKind::StringPart, string),
+                (TokenKind::NumberLiteral, constant),
+                (TokenKind::BoolLiteral, constant),
+                (TokenKind::NilLiteral, constant),
+                (
+                    TokenKind::Comment,
+                    Style {
+                        italic: true,
+                        ..Style::fg(Color::new(0x7f, 0x84, 0x8e))
+                    },
+                ),
+                (TokenKind::RegexLiteral, Style::fg(Color::new(0x56, 0xb6, 0xc2))),
+                (TokenKind::Directive, Style::fg(Color::new(0x56, 0xb6, 0xc2))),
+                (TokenKind::Attribute, Style::fg(Color::new(0xe5, 0xc0, 0x7b))),
+                (
+                    TokenKind::Unknown,
+                    Style {
+                        underline: true,
+                        ..Style::fg(Color::new(0xe0, 0x6c, 0x75))
+                    },
+                ),
+            ],
+            keyword_categories: Vec::new(),
+        }
+    }
+    /// dark text on a light background
+    pub fn light() -> Self {
+        let string = Style::fg(Color::new(0x50, 0xa1, 0x4f));
+        let constant = Style::fg(Color::new(0x98, 0x68, 0x01));
+        Self {
+            name: "light".to_owned(),
+            default: Style {
+                bg: Some(Color::new(0xfa, 0xfa, 0xfa)),
+                ..Style::fg(Color::new(0x38, 0x3a, 0x42))
+            },
+            styles: vec![
+                (TokenKind::Keyword, Style::fg(Color::new(0xa6, 0x26, 0xa4))),
+                (TokenKind::Lifetime, Style::fg(Color::new(0xe4, 0x56, 0x49))),
+                (TokenKind::StringLiteral, string),
+                (TokenKind::CharLiteral, string),
+                (TokenKind::StringPart, string),
+                (TokenKind::NumberLiteral, constant),
+                (TokenKind::BoolLiteral, constant),
+                (TokenKind::NilLiteral, constant),
+                (
+                    TokenKind::Comment,
+                    Style {
+                        italic: true,
+                        ..Style::fg(Color::new(0xa0, 0xa1, 0xa7))
+                    },
+                ),
+                (TokenKind::RegexLiteral, Style::fg(Color::new(0x01, 0x84, 0xbc))),
+                (TokenKind::Directive, Style::fg(Color::new(0x01, 0x84, 0xbc))),
+                (TokenKind::Attribute, Style::fg(Color::new(0xc1, 0x84, 0x01))),
+                (
+                    TokenKind::Unknown,
+                    Style {
+                        underline: true,
+                        ..Style::fg(Color::new(0xe4, 0x56, 0x49))
+                    },
+                ),
+            ],
+            keyword_categories: Vec::new(),
+        }
+    }
+    /// the style of a text, None being the text between tokens.
+    /// The missing colors are taken from the default style
+    pub fn style(&self, kind: Option<TokenKind>, text: &str) -> Style {
+        let category = match kind {
+            Some(TokenKind::Keyword) => self
+                .keyword_categories
+                .iter()
+                .find(|category| category.keywords.iter().any(|k| k == text))
+                .map(|category| category.style),
+            _ => None,
+        };
+        let style = category.or_else(|| {
+            let kind = kind?;
+            self.styles.iter().find(|(k, _)| *k == kind).map(|(_, style)| *style)
+        });
+        match style {
+            Some(style) => Style {
+                fg: style.fg.or(self.default.fg),
+                bg: style.bg.or(self.default.bg),
+                ..style
+            },
+            None => self.default,
+        }
+    }
+    #[cfg(feature = "config")]
+    /// load a theme from a TOML document. Example :
+    /// ```toml
+    /// name = "mine"
+    /// [default]
+    /// fg = "#d0d0d0"
+    /// [styles]
+    /// keyword = { fg = "#c678dd", bold = true }
+    /// comment = { fg = "#7f848e", italic = true }
+    /// [styles.custom]
+    /// lifetime = { fg = "#e5c07b" }
+    /// [keyword_categories.control]
+    /// keywords = ["if", "else", "return"]
+    /// fg = "#e06c75"
+    /// ```
+    pub fn from_toml(text: &str) -> Result<Self, ConfigError> {
+        Self::from_value(&Value::from_toml(text)?)
+    }
+    #[cfg(feature = "config")]
+    /// load a theme from a JSON document, using the same keys as from_toml
+    pub fn from_json(text: &str) -> Result<Self, ConfigError> {
+        Self::from_value(&Value::from_json(text)?)
+    }
+    #[cfg(feature = "config")]
+    pub fn from_value(value: &Value) -> Result<Self, ConfigError> {
+        let fields = value
+            .as_object()
+            .ok_or_else(|| ConfigError::InvalidField(String::new()))?;
+        let mut theme = Self {
+            name: String::new(),
+            default: Style::default(),
+            styles: Vec::new(),
+            keyword_categories: Vec::new(),
+        };
+        for (key, value) in fields {
+            let invalid = |field: &str| ConfigError::InvalidField(field.to_owned());
+            match key.as_str() {
+                "name" => theme.name = value.as_str().ok_or_else(|| invalid(key))?.to_owned(),
+                "default" => theme.default = Style::from_value(value, false).ok_or_else(|| invalid(key))?,
+                "styles" => {
+                    for (name, style) in value.as_object().ok_or_else(|| invalid(key))? {
+                        let field = format!("styles.{}", name);
+                        if name == "custom" {
+                            // styles of the custom token classes
+                            for (class, style) in style.as_object().ok_or_else(|| invalid(&field))? {
+                                let field = format!("styles.custom.{}", class);
+                                let style = Style::from_value(style, false).ok_or_else(|| invalid(&field))?;
+                                theme.styles.push((TokenKind::custom(class), style));
+                            }
+                            continue;
+                        }
+                        let kind = KIND_KEYS
+                            .iter()
+                            .find(|(_, key)| key == name)
+                            .ok_or_else(|| invalid(&field))?
+                            .0;
+                        let style = Style::from_value(style, false).ok_or_else(|| invalid(&field))?;
+                        theme.styles.push((kind, style));
+                    }
+                }
+                "keyword_categories" => {
+                    for (name, category) in value.as_object().ok_or_else(|| invalid(key))? {
+                        let field = format!("keyword_categories.{}", name);
+                        let keywords = category
+                            .get("keywords")
+                            .and_then(Value::as_array)
+                            .and_then(|list| {
+                                list.iter().map(|k| k.as_str().map(str::to_owned)).collect()
+                            })
+                            .ok_or_else(|| invalid(&field))?;
+                        theme.keyword_categories.push(KeywordCategory {
+                            name: name.clone(),
+                            keywords,
+                            style: Style::from_value(category, true).ok_or_else(|| invalid(&field))?,
+                        });
+                    }
+                }
+                _ => return Err(invalid(key)),
+            }
+        }
+        Ok(theme)
+    }
+}
@@ -0,0 +1,179 @@
+//! C interface, enabled by the `ffi` feature. See include/uscan.h for the C declarations.
+//! Build the library with `cargo rustc --release --features ffi --crate-type cdylib`.
+use std::os::raw::c_char;
+
+use crate::{presets, ScanError, Scanner, TokenKind};
+
+pub const USCAN_SYMBOL: u32 = 0;
+pub const USCAN_IDENTIFIER: u32 = 1;
+pub const USCAN_STRING_LITERAL: u32 = 2;
+pub const USCAN_CHAR_LITERAL: u32 = 3;
+pub const USCAN_STRING_PART: u32 = 4;
+pub const USCAN_INTERPOLATION_START: u32 = 5;
+pub const USCAN_INTERPOLATION_END: u32 = 6;
+pub const USCAN_NUMBER_LITERAL: u32 = 7;
+pub const USCAN_KEYWORD: u32 = 8;
+pub const USCAN_COMMENT: u32 = 9;
+pub const USCAN_DIRECTIVE: u32 = 10;
+pub const USCAN_IGNORE: u32 = 11;
+pub const USCAN_NEW_LINE: u32 = 12;
+pub const USCAN_EOF: u32 = 13;
+pub const USCAN_UNKNOWN: u32 = 14;
+/// a token produced by a custom rule
+pub const USCAN_CUSTOM: u32 = 15;
+pub const USCAN_BOOL_LITERAL: u32 = 16;
+pub const USCAN_NIL_LITERAL: u32 = 17;
+pub const USCAN_REGEX_LITERAL: u32 = 18;
+pub const USCAN_ATTRIBUTE: u32 = 19;
+pub const USCAN_LIFETIME: u32 = 20;
+pub const USCAN_PUNCTUATION: u32 = 21;
+
+/// the stable numeric id of a token kind
+pub fn kind_id(kind: TokenKind) -> u32 {
+    match kind {
+        TokenKind::Symbol => USCAN_SYMBOL,
+        TokenKind::Identifier => USCAN_IDENTIFIER,
+        TokenKind::StringLiteral => USCAN_STRING_LITERAL,
+        TokenKind::CharLiteral => USCAN_CHAR_LITERAL,
+        TokenKind::StringPart => USCAN_STRING_PART,
+        TokenKind::InterpolationStart => USCAN_INTERPOLATION_START,
+        TokenKind::InterpolationEnd => USCAN_INTERPOLATION_END,
+        TokenKind::NumberLiteral => USCAN_NUMBER_LITERAL,
+        TokenKind::Keyword => USCAN_KEYWORD,
+        TokenKind::Comment => USCAN_COMMENT,
+        TokenKind::Directive => USCAN_DIRECTIVE,
+        TokenKind::Ignore => USCAN_IGNORE,
+        TokenKind::NewLine => USCAN_NEW_LINE,
+        TokenKind::Eof => USCAN_EOF,
+        TokenKind::Unknown => USCAN_UNKNOWN,
+        TokenKind::Custom(_) => USCAN_CUSTOM,
+        TokenKind::BoolLiteral => USCAN_BOOL_LITERAL,
+        TokenKind::NilLiteral => USCAN_NIL_LITERAL,
+        TokenKind::RegexLiteral => USCAN_REGEX_LITERAL,
+        TokenKind::Attribute => USCAN_ATTRIBUTE,
+        TokenKind::Lifetime => USCAN_LIFETIME,
+        TokenKind::Punctuation => USCAN_PUNCTUATION,
+    }
+}
+
+/// a token as seen from C. start and len are in bytes
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UscanToken {
+    pub kind: u32,
+    pub start: usize,
+    pub len: usize,
+    pub line: usize,
+}
+
+/// the tokens of a source code and its first scan error. Opaque for C
+pub struct UscanResult {
+    tokens: Vec<UscanToken>,
+    error: Option<ScanError>,
+}
+
+/// scan a UTF-8 source code of len bytes with a preset (for example "lua").
+/// The scan does not stop at errors, see uscan_error.
+/// Returns NULL if the language is unknown or the source code is not valid UTF-8.
+/// The result must be released with uscan_free.
+///
+/// # Safety
+/// source must point to len readable bytes and language to a null terminated string
+#[no_mangle]
+pub unsafe extern "C" fn uscan_scan(
+    source: *const u8,
+    len: usize,
+    language: *const c_char,
+) -> *mut UscanResult {
+    if source.is_null() || language.is_null() {
+        return std::ptr::null_mut();
+    }
+    let bytes = std::slice::from_raw_parts(source, len);
+    let language = std::ffi::CStr::from_ptr(language);
+    let (Ok(source), Some(config)) = (
+        std::str::from_utf8(bytes),
+        language.to_str().ok().and_then(presets::by_name),
+    ) else {
+        return std::ptr::null_mut();
+    };
+    let (data, errors) = Scanner::default()
+        .with_values(false)
+        .run_lossy(source, config);
+    let tokens = data
+        .iter()
+        .map(|token| UscanToken {
+            kind: kind_id(token.kind),
+            start: token.span.start(),
+            len: token.span.len(),
+            line: token.line,
+        })
+        .collect();
+    Box::into_raw(Box::new(UscanResult {
+        tokens,
+        error: errors.into_iter().next(),
+    }))
+}
+
+/// number of tokens in a result
+///
+/// # Safety
+/// result must come from uscan_scan and not be freed
+#[no_mangle]
+pub unsafe extern "C" fn uscan_token_count(result: *const UscanResult) -> usize {
+    result.as_ref().map_or(0, |result| result.tokens.len())
+}
+
+/// copy a token into out. Returns false if the index is out of range
+///
+/// # Safety
+/// result must come from uscan_scan and not be freed, out must be writable
+#[no_mangle]
+pub unsafe extern "C" fn uscan_token_at(
+    result: *const UscanResult,
+    index: usize,
+    out: *mut UscanToken,
+) -> bool {
+    match (result.as_ref().and_then(|r| r.tokens.get(index)), out.is_null()) {
+        (Some(token), false) => {
+            *out = *token;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// the first scan error : returns its number (1 for E0001, ...) and writes its line
+/// and its offset in characters into line and offset if they are not NULL.
+/// Returns 0 if there is no error
+///
+/// # Safety
+/// result must come from uscan_scan and not be freed, line and offset must be writable or NULL
+#[no_mangle]
+pub unsafe extern "C" fn uscan_error(
+    result: *const UscanResult,
+    line: *mut usize,
+    offset: *mut usize,
+) -> u32 {
+    let Some(error) = result.as_ref().and_then(|r| r.error.as_ref()) else {
+        return 0;
+    };
+    let (error_line, error_offset) = error.position();
+    if !line.is_null() {
+        *line = error_line;
+    }
+    if !offset.is_null() {
+        *offset = error_offset;
+    }
+    error.code()[1..].parse().unwrap_or(0)
+}
+
+/// release a result. Does nothing on NULL
+///
+/// # Safety
+/// result must come from uscan_scan and not be already freed
+#[no_mangle]
+pub unsafe extern "C" fn uscan_free(result: *mut UscanResult) {
+    if !result.is_null() {
+        drop(Box::from_raw(result));
+    }
+}
@@ -0,0 +1,121 @@
+//! Indentation hints for editors, computed from the block openers and closers of the tokens
+use crate::brackets::{BracketError, Brackets, DEFAULT_PAIRS};
+use crate::{Compile, IncompleteKind, Scanner, ScannerData, TokenKind};
+
+/// Whether the input of an interactive console can be run, see IndentRules::is_input_complete
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Completeness {
+    /// the input can be run
+    Complete,
+    /// a string, comment, bracket or block is still open, the console should ask for more input
+    Incomplete(IncompleteKind),
+    /// the input contains an error that more input cannot fix, like an unknown token
+    /// or an unexpected closing bracket
+    Invalid,
+}
+
+/// The symbols and keywords opening and closing indented blocks.
+/// A word in both lists closes the current block and opens a new one, like "else" in Lua.
+/// A line starting with closing words is indented less than its content
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndentRules<'a> {
+    /// symbols and keywords opening a block, like "{" or "do"
+    pub open: &'a [&'a str],
+    /// symbols and keywords closing a block, like "}" or "end"
+    pub close: &'a [&'a str],
+}
+
+/// blocks delimited by brackets, for C-like languages
+pub const BRACES: IndentRules = IndentRules {
+    open: &["(", "[", "{"],
+    close: &[")", "]", "}"],
+};
+
+/// blocks of the Lua language
+pub const LUA: IndentRules = IndentRules {
+    open: &["(", "[", "{", "do", "then", "function", "repeat", "else"],
+    close: &[")", "]", "}", "end", "until", "elseif", "else"],
+};
+
+impl IndentRules<'_> {
+    /// the indentation level of a line (starting at 1), in number of open blocks
+    pub fn line_level(&self, data: &ScannerData, line: usize) -> usize {
+        let mut leading_closers = 0;
+        for index in self.line_tokens(data, line) {
+            let (closes, opens) = self.word(data, index);
+            if !closes {
+                break;
+            }
+            leading_closers += 1;
+            if opens {
+                break;
+            }
+        }
+        self.depth_before(data, line).saturating_sub(leading_closers)
+    }
+    /// the indentation of a new line inserted after a line, in number of levels
+    /// relative to the indentation of that line, for example 1 after "if a then" in Lua
+    /// and 0 after "x = 1". Use line_level to reindent a line when a closing word is typed
+    pub fn indent_delta(&self, data: &ScannerData, line: usize) -> isize {
+        self.depth_before(data, line + 1) as isize - self.line_level(data, line) as isize
+    }
+    /// whether a source code typed in an interactive console is complete, is waiting
+    /// for the end of a string, comment, bracket or block, or contains an error.
+    /// The blocks are those of these rules, for example a Lua function without its "end"
+    pub fn is_input_complete<C: Compile + ?Sized>(&self, source: &str, config: &C) -> Completeness {
+        let Ok(scan) = Scanner::default().run_partial(source, config) else {
+            return Completeness::Invalid;
+        };
+        let data = &scan.data;
+        let misplaced = Brackets::new(data, DEFAULT_PAIRS)
+            .errors()
+            .iter()
+            .any(|error| !matches!(error, BracketError::Unclosed { .. }));
+        if misplaced {
+            return Completeness::Invalid;
+        }
+        let mut depth = 0;
+        for index in 0..data.len() {
+            let (closes, opens) = self.word(data, index);
+            if closes {
+                if depth == 0 {
+                    return Completeness::Invalid;
+                }
+                depth -= 1;
+            }
+            if opens {
+                depth += 1;
+            }
+        }
+        match scan.incomplete {
+            Some(kind) => Completeness::Incomplete(kind),
+            None if depth > 0 => Completeness::Incomplete(IncompleteKind::Block),
+            None => Completeness::Complete,
+        }
+    }
+    /// the number of blocks open at the start of a line
+    fn depth_before(&self, data: &ScannerData, line: usize) -> usize {
+        let end = data.token_lines.partition_point(|l| (*l as usize) < line);
+        (0..end).fold(0usize, |depth, index| match self.word(data, index) {
+            (true, true) => depth.saturating_sub(1) + 1,
+            (true, false) => depth.saturating_sub(1),
+            (false, true) => depth + 1,
+            (false, false) => depth,
+        })
+    }
+    /// the indices of the tokens starting on a line
+    fn line_tokens(&self, data: &ScannerData, line: usize) -> std::ops::Range<usize> {
+        let lines = &data.token_lines;
+        lines.partition_point(|l| (*l as usize) < line)..lines.partition_point(|l| *l as usize <= line)
+    }
+    /// whether a token closes and opens a block
+    fn word(&self, data: &ScannerData, index: usize) -> (bool, bool) {
+        match data.token_kinds[index] {
+            TokenKind::Symbol | TokenKind::Punctuation | TokenKind::Keyword => {
+                let text = data.token_text(index);
+                (self.close.contains(&text), self.open.contains(&text))
+            }
+            _ => (false, false),
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+
+/// A cheap id standing for an identifier or keyword text. See Interner
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    pub(crate) fn new(index: usize) -> Self {
+        Symbol(index as u32)
+    }
+    /// index of the symbol in its interner
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Stores each distinct identifier and keyword text once.
+/// Two tokens with the same text get the same Symbol.
+#[derive(Debug, Clone, Default)]
+pub struct Interner {
+    symbols: HashMap<String, Symbol>,
+    strings: Vec<String>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// the symbol of a text, allocating it on first use
+    pub fn intern(&mut self, text: &str) -> Symbol {
+        if let Some(symbol) = self.symbols.get(text) {
+            return *symbol;
+        }
+        let symbol = Symbol(self.strings.len() as u32);
+        self.strings.push(text.to_owned());
+        self.symbols.insert(text.to_owned(), symbol);
+        symbol
+    }
+    /// the symbol of a text if it was already interned
+    pub fn get(&self, text: &str) -> Option<Symbol> {
+        self.symbols.get(text).copied()
+    }
+    /// the symbol at an index, if it exists. See Symbol::index
+    pub fn symbol(&self, index: usize) -> Option<Symbol> {
+        (index < self.strings.len()).then_some(Symbol(index as u32))
+    }
+    /// the text of a symbol. Panics if the symbol comes from another interner
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        &self.strings[symbol.index()]
+    }
+    /// the texts in the order of their symbols
+    pub fn iter(&self) -> impl Iterator<Item = &str> + '_ {
+        self.strings.iter().map(String::as_str)
+    }
+    /// number of distinct texts
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+    /// approximate memory used by the texts and the hash table, in bytes
+    pub(crate) fn memory_usage(&self) -> usize {
+        let texts: usize = self.strings.iter().map(String::capacity).sum();
+        self.strings.capacity() * std::mem::size_of::<String>()
+            + self.symbols.capacity() * (std::mem::size_of::<(String, Symbol)>() + 1)
+            + 2 * texts
+    }
+}
@@ -1,10 +1,50 @@
+pub mod brackets;
+#[cfg(feature = "config")]
+mod config_file;
+pub mod diagnostics;
+pub mod diff;
+pub mod emit;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod interner;
+pub mod indent;
+pub mod lint;
+mod line_index;
+pub mod metrics;
+pub mod minify;
+pub mod presets;
+#[cfg(feature = "regex")]
+pub mod regex;
+mod rule;
 mod scanner;
+pub mod security;
+#[cfg(feature = "simd")]
+mod simd;
+mod source_file;
+pub mod suggest;
+pub mod tasks;
+mod text_source;
+mod token_cursor;
+mod trie;
+#[cfg(any(feature = "config", feature = "json"))]
+pub mod value;
+mod visitor;
 
+#[cfg(feature = "config")]
+pub use config_file::ConfigError;
+pub use interner::{Interner, Symbol};
+pub use line_index::LineIndex;
+pub use rule::{Cursor, TokenRule};
 pub use scanner::*;
+pub use source_file::{FileError, SourceFile};
+pub use text_source::TextSource;
+pub use token_cursor::{Checkpoint, TokenCursor, UnexpectedToken};
+pub use visitor::{TokenVisitor, VisitedToken};
 
 #[cfg(test)]
 mod tests {
-    use crate::{ScannerConfig, ScannerData, Scanner, TokenType, ScanError};
+    use crate::presets;
+    use crate::{ScannerConfig, ScannerData, Scanner, TokenType, ScanError, TokenKind, OwnedScannerConfig, NumberValue, LineIndex, ErrorSpan, Span, TokenIndex};
     const LUA_CONFIG: ScannerConfig = ScannerConfig {
         keywords: &[
             "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "if", "in",
@@ -17,6 +57,7 @@ mod tests {
         single_line_cmt: Some("--"),
         multi_line_cmt_start: Some("--[["),
         multi_line_cmt_end: Some("]]"),
+        ..ScannerConfig::DEFAULT
     };
 
     #[test]
@@ -43,7 +84,7 @@ mod tests {
             TokenType::Identifier("p2".to_string()),
             TokenType::Keyword("end".to_string()),
         ]);
-        assert_eq!(scanner_data.token_len,&[
+        assert_eq!(scanner_data.token_lens_chars(),&[
             8,4,1,2,1,2,1,6,2,1,2,3
         ]);
 
@@ -62,16 +103,16 @@ mod tests {
             TokenType::StringLiteral("à".to_string()),
             TokenType::Comment("-- comment".to_string()),
         ]);
-        assert_eq!(scanner_data.token_len,&[
+        assert_eq!(scanner_data.token_lens_chars(),&[
             5,1,1,3,10
         ]);
-        assert_eq!(scanner_data.token_start,&[
+        assert_eq!(scanner_data.token_starts_chars(),&[
             0,6,7,8,12
         ]);
         let mut st=String::new();
         for i in 0..5 {
-            let s=scanner_data.token_start[i];
-            let e = s + scanner_data.token_len[i];
+            let s=scanner_data.token_start_char(i);
+            let e = s + scanner_data.token_len_chars(i);
             let text: String = source_code.chars().skip(s).take(e-s).collect();
             st.push_str(&text);
         }
@@ -85,29 +126,328 @@ mod tests {
 
         let mut scanner_data = ScannerData::default();
         let res = Scanner::default().run(source_code, &LUA_CONFIG, &mut scanner_data);
-        assert_eq!(res,Err(ScanError::UnexpectedEof(1,8)));
+        assert!(matches!(res, Err(ScanError::UnexpectedEof(e)) if (e.line, e.offset) == (1, 8)));
         assert_eq!(scanner_data.token_types,&[
             TokenType::Keyword("local".to_string()),
             TokenType::Identifier("s".to_string()),
             TokenType::Symbol("=".to_string()),
             TokenType::StringLiteral("à".to_string()),
         ]);
-        assert_eq!(scanner_data.token_len,&[
+        assert_eq!(scanner_data.token_lens_chars(),&[
             5,1,1,3
         ]);
-        assert_eq!(scanner_data.token_start,&[
+        assert_eq!(scanner_data.token_starts_chars(),&[
             0,6,7,8
         ]);
         let mut st=String::new();
         for i in 0..4 {
-            let s=scanner_data.token_start[i];
-            let e = s + scanner_data.token_len[i];
+            let s=scanner_data.token_start_char(i);
+            let e = s + scanner_data.token_len_chars(i);
             st.push_str(&source_code[s..e]);
         }
         assert_eq!(&st, "locals=\"à");
 
     }
 
+    #[test]
+    fn partial_scan() {
+        use crate::IncompleteKind;
+        let mut scanner = Scanner::default();
+        let partial = |scanner: &mut Scanner, source| {
+            scanner.run_partial(source, &LUA_CONFIG).map(|scan| scan.incomplete)
+        };
+        assert_eq!(partial(&mut scanner, "local s=\"à"), Ok(Some(IncompleteKind::String)));
+        assert_eq!(partial(&mut scanner, "local s --[[ comment"), Ok(Some(IncompleteKind::Comment)));
+        assert_eq!(partial(&mut scanner, "f(a, {b"), Ok(Some(IncompleteKind::Bracket)));
+        assert_eq!(partial(&mut scanner, "f(a, {b}) --[[ ]]"), Ok(None));
+        assert!(matches!(partial(&mut scanner, "f(a) $"), Err(ScanError::UnknownToken(_))));
+        let scan = scanner.run_partial("local s=\"à", &LUA_CONFIG).unwrap();
+        assert_eq!(scan.data.token_types[3], TokenType::StringLiteral("à".to_string()));
+        let config = ScannerConfig {
+            interpolated_string: Some('`'),
+            interpolation_start: Some("${"),
+            interpolation_end: Some("}"),
+            ..LUA_CONFIG
+        };
+        let scan = scanner.run_partial("`a ${b", &config).unwrap();
+        assert_eq!(scan.incomplete, Some(IncompleteKind::String));
+    }
+
+    #[test]
+    fn unterminated_string_policy() {
+        use crate::UnterminatedStringPolicy;
+        let scan = |source, policy, may_span| {
+            let config = ScannerConfig {
+                strings_may_span_lines: may_span,
+                unterminated_strings: policy,
+                ..LUA_CONFIG
+            };
+            let mut scanner_data = ScannerData::default();
+            let res = Scanner::default().run(source, &config, &mut scanner_data);
+            (res.is_ok(), scanner_data.token_types)
+        };
+        let source = "a = \"b\nc = d";
+        let (ok, _) = scan(source, UnterminatedStringPolicy::Error, false);
+        assert!(!ok);
+        let (ok, _) = scan(source, UnterminatedStringPolicy::Error, true);
+        assert!(!ok);
+        let expected = vec![
+            TokenType::Identifier("a".to_string()),
+            TokenType::Symbol("=".to_string()),
+            TokenType::StringLiteral("b".to_string()),
+            TokenType::Identifier("c".to_string()),
+            TokenType::Symbol("=".to_string()),
+            TokenType::Identifier("d".to_string()),
+        ];
+        assert_eq!(scan(source, UnterminatedStringPolicy::RecoverAtLineEnd, false), (true, expected.clone()));
+        assert_eq!(scan(source, UnterminatedStringPolicy::RecoverAtLineEnd, true), (true, expected));
+        let (ok, tokens) = scan(source, UnterminatedStringPolicy::RecoverAtEof, false);
+        assert!(ok);
+        assert_eq!(tokens[2], TokenType::StringLiteral("b\nc = d".to_string()));
+        #[cfg(feature = "config")]
+        {
+            let config = OwnedScannerConfig::from_json(r#"{"unterminated_strings": "line_end"}"#).unwrap();
+            assert_eq!(config.unterminated_strings, UnterminatedStringPolicy::RecoverAtLineEnd);
+        }
+        // heredocs and quoted identifiers follow the same policy
+        let scan = |source, policy| {
+            let config = ScannerConfig {
+                heredoc_start: Some("<<"),
+                quoted_identifiers: &["`"][..],
+                unterminated_strings: policy,
+                ..LUA_CONFIG
+            };
+            let mut scanner_data = ScannerData::default();
+            let res = Scanner::default().run(source, &config, &mut scanner_data);
+            (res.is_ok(), scanner_data.token_types)
+        };
+        let heredoc = "cat <<END\na\nb";
+        let quoted = "select `a\nb";
+        assert!(!scan(heredoc, UnterminatedStringPolicy::Error).0);
+        assert!(!scan(quoted, UnterminatedStringPolicy::Error).0);
+        assert_eq!(
+            scan(heredoc, UnterminatedStringPolicy::RecoverAtLineEnd),
+            (true, vec![
+                TokenType::Identifier("cat".to_string()),
+                TokenType::StringLiteral(String::new()),
+                TokenType::Identifier("a".to_string()),
+                TokenType::Identifier("b".to_string()),
+            ])
+        );
+        assert_eq!(
+            scan(quoted, UnterminatedStringPolicy::RecoverAtLineEnd),
+            (true, vec![
+                TokenType::Identifier("select".to_string()),
+                TokenType::Identifier("a".to_string()),
+                TokenType::Identifier("b".to_string()),
+            ])
+        );
+        assert_eq!(
+            scan(heredoc, UnterminatedStringPolicy::RecoverAtEof),
+            (true, vec![
+                TokenType::Identifier("cat".to_string()),
+                TokenType::StringLiteral("a\nb".to_string()),
+            ])
+        );
+        assert_eq!(
+            scan(quoted, UnterminatedStringPolicy::RecoverAtEof),
+            (true, vec![
+                TokenType::Identifier("select".to_string()),
+                TokenType::Identifier("a\nb".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn control_char_policy() {
+        use crate::ControlCharPolicy;
+        let source = "a\u{0}b\n \u{1b}c";
+        let config = |control_chars| ScannerConfig { control_chars, ..LUA_CONFIG };
+        let mut scanner_data = ScannerData::default();
+        let res = Scanner::default().run(source, &config(ControlCharPolicy::Error), &mut scanner_data);
+        assert!(matches!(res, Err(ScanError::UnknownToken(e)) if e.span == Span::new(1, 1)));
+        let names = vec![
+            TokenType::Identifier("a".to_string()),
+            TokenType::Identifier("b".to_string()),
+            TokenType::Identifier("c".to_string()),
+        ];
+        Scanner::default()
+            .run(source, &config(ControlCharPolicy::PassThrough), &mut scanner_data)
+            .unwrap();
+        assert_eq!(scanner_data.token_types, names);
+        assert!(scanner_data.warnings.is_empty());
+        Scanner::default()
+            .run(source, &config(ControlCharPolicy::Warning), &mut scanner_data)
+            .unwrap();
+        assert_eq!(scanner_data.token_types, names);
+        let positions: Vec<_> = scanner_data.warnings.iter().map(ScanError::position).collect();
+        assert_eq!(positions, vec![(1, 1), (2, 5)]);
+        assert_eq!(scanner_data.warnings[1].span().column, 1);
+        assert_eq!(scanner_data.warnings[1].code(), "E0004");
+        #[cfg(feature = "json")]
+        {
+            let json = scanner_data.to_json();
+            assert_eq!(ScannerData::from_json(&json, source).unwrap().warnings, scanner_data.warnings);
+        }
+    }
+
+    #[test]
+    fn scan_limits() {
+        use crate::ScanLimits;
+        let config = ScannerConfig { nested_comments: true, ..LUA_CONFIG };
+        let limited = |limits: ScanLimits, source| {
+            let mut scanner_data = ScannerData::default();
+            let res = Scanner::default().with_limits(limits).run(source, &config, &mut scanner_data);
+            (res.err().map(|e| (e.code(), e.span().span)), scanner_data.len())
+        };
+        let source = "a = \"text\" --[[ --[[ ]] ]] b";
+        assert_eq!(limited(ScanLimits::NONE, source), (None, 5));
+        let limits = ScanLimits { max_input_len: 10, ..ScanLimits::NONE };
+        assert_eq!(limited(limits, source), (Some(("E0005", Span::new(0, 28))), 0));
+        let limits = ScanLimits { max_tokens: 2, ..ScanLimits::NONE };
+        assert_eq!(limited(limits, source), (Some(("E0005", Span::new(4, 6))), 2));
+        let limits = ScanLimits { max_literal_len: 5, ..ScanLimits::NONE };
+        assert_eq!(limited(limits, source), (Some(("E0005", Span::new(4, 6))), 2));
+        let limits = ScanLimits { max_comment_nesting: 1, ..ScanLimits::NONE };
+        assert_eq!(limited(limits, source), (Some(("E0005", Span::new(11, 9))), 3));
+        let (_, errors) = Scanner::default().with_limits(limits).run_lossy(source, &config);
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn cancellation() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+        let source = "a = b\n".repeat(2000);
+        let cancel = Arc::new(AtomicBool::new(false));
+        let mut scanner = Scanner::default().with_cancellation(cancel.clone());
+        let mut scanner_data = ScannerData::default();
+        scanner.run(&source, &LUA_CONFIG, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.len(), 6000);
+        cancel.store(true, Ordering::Relaxed);
+        let res = scanner.run(&source, &LUA_CONFIG, &mut scanner_data);
+        assert!(matches!(res, Err(ScanError::Cancelled(e)) if e.span == Span::new(0, 0)));
+        assert!(scanner_data.is_empty());
+        let res = scanner.run_parallel(&source, &LUA_CONFIG, &mut scanner_data, 4);
+        assert_eq!(res.map_err(|e| e.code()), Err("E0006"));
+    }
+
+    #[test]
+    fn position_convention() {
+        use crate::PositionConvention;
+        let source = "a\nb\n\"c";
+        let mut scanner = Scanner::default().with_positions(PositionConvention::ZeroBased);
+        let mut scanner_data = ScannerData::default();
+        let res = scanner.run(source, &LUA_CONFIG, &mut scanner_data);
+        assert!(matches!(res, Err(ScanError::UnexpectedEof(e)) if (e.line, e.column) == (2, 0)));
+        assert_eq!(scanner_data.token_lines, &[0, 1, 2]);
+        assert_eq!(scanner_data.token_line(1), 1);
+        assert_eq!(scanner_data.line_text(0), Some("a"));
+        assert_eq!(scanner_data.line_text(2), Some("\"c"));
+        assert_eq!(scanner_data.line_text(3), None);
+        assert_eq!(scanner_data.token_at_line_col(1, 0), Some(1));
+        assert_eq!(scanner_data.offset_line(2), 1);
+        #[cfg(feature = "json")]
+        {
+            let data = ScannerData::from_json(&scanner_data.to_json(), source).unwrap();
+            assert_eq!(data.positions, PositionConvention::ZeroBased);
+            assert_eq!(data.line_text(0), Some("a"));
+        }
+        let mut registry = crate::lint::LintRegistry::new();
+        registry.register(crate::lint::TrailingWhitespace);
+        let (data, _) = Scanner::default().with_positions(PositionConvention::ZeroBased).run_lossy("a \nb ", &LUA_CONFIG);
+        let diagnostics = registry.run(&data);
+        assert_eq!(diagnostics.iter().map(|d| d.line).collect::<Vec<_>>(), vec![0, 1]);
+        let lines: Vec<_> = Scanner::default()
+            .with_positions(PositionConvention::ZeroBased)
+            .tokens("a\nb", &LUA_CONFIG)
+            .map(|token| token.unwrap().line)
+            .collect();
+        assert_eq!(lines, vec![0, 1]);
+    }
+
+    #[test]
+    fn token_columns() {
+        let source = "a = 1\n\tb\t= \"é\" c\n  \"d";
+        let mut scanner_data = ScannerData::default();
+        let res = Scanner::default().run(source, &LUA_CONFIG, &mut scanner_data);
+        assert!(matches!(res, Err(ScanError::UnexpectedEof(e)) if e.column == 2));
+        assert_eq!(scanner_data.token_cols, &[0, 2, 4, 1, 3, 5, 9, 2]);
+        let res = Scanner::default().with_tab_width(4).run(source, &LUA_CONFIG, &mut scanner_data);
+        assert!(res.is_err());
+        assert_eq!(scanner_data.token_cols, &[0, 2, 4, 4, 8, 10, 14, 2]);
+        assert_eq!(scanner_data.token_col(4), 8);
+        #[cfg(feature = "json")]
+        {
+            let json = scanner_data.to_json();
+            assert_eq!(ScannerData::from_json(&json, source).unwrap().token_cols, scanner_data.token_cols);
+        }
+    }
+
+    #[test]
+    fn token_end_lines() {
+        let source = "a --[[ one\ntwo\nthree ]] b\n\"x\ny\"";
+        let mut scanner_data = ScannerData::default();
+        Scanner::default()
+            .with_newlines(true)
+            .run(source, &LUA_CONFIG, &mut scanner_data)
+            .unwrap();
+        assert_eq!(scanner_data.token_lines, &[1, 1, 3, 3, 4]);
+        assert_eq!(scanner_data.token_end_lines, &[1, 3, 3, 3, 5]);
+        assert_eq!(scanner_data.token_end_line(1), 3);
+        #[cfg(feature = "json")]
+        {
+            let json = scanner_data.to_json().replace("token_end_lines", "unknown");
+            let data = ScannerData::from_json(&json, source).unwrap();
+            assert_eq!(data.token_end_lines, scanner_data.token_end_lines);
+        }
+    }
+
+    #[test]
+    fn line_tokens() {
+        use crate::TokenSlice;
+        let source = "a --[[ one\r\n\r\nthree ]] b\nc";
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(source, &LUA_CONFIG, &mut scanner_data).unwrap();
+        let slices = |line| scanner_data.line_tokens(line).collect::<Vec<_>>();
+        assert_eq!(slices(1), vec![
+            TokenSlice { index: 0, kind: TokenKind::Identifier, span: Span::new(0, 1), column: 0, text: "a" },
+            TokenSlice { index: 1, kind: TokenKind::Comment, span: Span::new(2, 8), column: 2, text: "--[[ one" },
+        ]);
+        assert_eq!(slices(2), vec![]);
+        assert_eq!(slices(3), vec![
+            TokenSlice { index: 1, kind: TokenKind::Comment, span: Span::new(14, 8), column: 0, text: "three ]]" },
+            TokenSlice { index: 2, kind: TokenKind::Identifier, span: Span::new(23, 1), column: 9, text: "b" },
+        ]);
+        assert_eq!(slices(4).iter().map(|s| s.index).collect::<Vec<_>>(), vec![3]);
+        assert_eq!(slices(5), vec![]);
+    }
+
+    #[test]
+    fn line_states() {
+        let source = "a = 1\n--[[ x\ny ]] b\n\nc";
+        let mut scanner = Scanner::default().with_line_states(true);
+        let mut scanner_data = ScannerData::default();
+        scanner.run(source, &LUA_CONFIG, &mut scanner_data).unwrap();
+        let offsets: Vec<_> = scanner_data.line_states.iter().map(|s| s.offset).collect();
+        // the third line starts inside the comment
+        assert_eq!(offsets, vec![0, 6, 6, 20, 21]);
+        // scan the third line again
+        let mut line_data = ScannerData::default();
+        let state = scanner_data.line_states[2].clone();
+        let next = scanner.run_range(source, &LUA_CONFIG, &mut line_data, &state, 20).unwrap();
+        assert_eq!(line_data.token_types, &[
+            TokenType::Comment("--[[ x\ny ]]".to_string()),
+            TokenType::Identifier("b".to_string()),
+        ]);
+        assert_eq!(next.offset, 19);
+        #[cfg(feature = "json")]
+        {
+            let json = scanner_data.to_json();
+            assert_eq!(ScannerData::from_json(&json, source).unwrap().line_states, scanner_data.line_states);
+        }
+    }
+
     #[test]
     fn multi_comments() {
         let source_code=r#"local s="" --[[comment]]"#;
@@ -121,20 +461,2221 @@ mod tests {
             TokenType::StringLiteral("".to_string()),
             TokenType::Comment("--[[comment]]".to_string()),
         ]);
-        assert_eq!(scanner_data.token_len,&[
+        assert_eq!(scanner_data.token_lens_chars(),&[
             5,1,1,2,13
         ]);
-        assert_eq!(scanner_data.token_start,&[
+        assert_eq!(scanner_data.token_starts_chars(),&[
             0,6,7,8,11
         ]);
         let mut st=String::new();
         for i in 0..5 {
-            let s=scanner_data.token_start[i];
-            let e = s + scanner_data.token_len[i];
+            let s=scanner_data.token_start_char(i);
+            let e = s + scanner_data.token_len_chars(i);
             st.push_str(&source_code[s..e]);
         }
         assert_eq!(&st, "locals=\"\"--[[comment]]");
 
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn spans_without_values() {
+        let source_code="local s=\"à\" -- comment\nx=0x1f";
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().with_values(false).run(source_code, &LUA_CONFIG, &mut scanner_data).unwrap();
+        assert!(scanner_data.token_types.is_empty());
+        assert_eq!(scanner_data.token_kinds,&[
+            TokenKind::Keyword,
+            TokenKind::Identifier,
+            TokenKind::Symbol,
+            TokenKind::StringLiteral,
+            TokenKind::Comment,
+            TokenKind::Identifier,
+            TokenKind::Symbol,
+            TokenKind::NumberLiteral,
+        ]);
+        let texts: Vec<&str> = scanner_data.token_spans.iter().map(|span| &source_code[span.range()]).collect();
+        assert_eq!(texts, &["local", "s", "=", "\"à\"", "-- comment", "x", "=", "0x1f"]);
+        assert_eq!(scanner_data.token_lines,&[1,1,1,1,1,2,2,2]);
+        assert_eq!(scanner_data.token_starts_chars(),&[0,6,7,8,12,23,24,25]);
+    }
+
+    #[test]
+    fn lazy_tokens() {
+        let source_code="function f() return 1 end @";
+
+        let mut tokens = Scanner::default().tokens(source_code, &LUA_CONFIG);
+        let first = tokens.next().unwrap().unwrap();
+        assert_eq!(first.kind, TokenKind::Keyword);
+        assert_eq!(first.text(source_code), "function");
+        assert_eq!(tokens.value(&first), TokenType::Keyword("function".to_string()));
+        let rest: Vec<_> = tokens.by_ref().collect();
+        assert_eq!(rest.len(), 7);
+        assert!(matches!(rest[6], Err(ScanError::UnknownToken(e)) if (e.line, e.offset) == (1, 26)));
+        assert_eq!(tokens.value(rest[4].as_ref().unwrap()), TokenType::NumberLiteral("1".to_string(), NumberValue::Int(1), None, 10));
+    }
+
+    #[test]
+    fn owned_config() {
+        let keywords = "let in".split(' ').collect::<Vec<_>>();
+        let config = OwnedScannerConfig::new()
+            .with_keywords(keywords)
+            .with_symbols(["=", "+"])
+            .with_single_line_cmt("#");
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run("let x = 1 + y # sum", &config, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_types,&[
+            TokenType::Keyword("let".to_string()),
+            TokenType::Identifier("x".to_string()),
+            TokenType::Symbol("=".to_string()),
+            TokenType::NumberLiteral("1".to_string(), NumberValue::Int(1), None, 10),
+            TokenType::Symbol("+".to_string()),
+            TokenType::Identifier("y".to_string()),
+            TokenType::Comment("# sum".to_string()),
+        ]);
+
+        let lua = OwnedScannerConfig::from(&LUA_CONFIG);
+        assert_eq!(lua.keywords.len(), LUA_CONFIG.keywords.len());
+        assert_eq!(lua.multi_line_cmt_end.as_deref(), Some("]]"));
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn config_from_file_formats() {
+        let json = r#"{
+            "keywords": ["if", "then", "end"],
+            "symbols": ["==", "="],
+            "single_line_cmt": "//",
+            "multi_line_cmt_start": null
+        }"#;
+        let toml = r#"
+            # same language in TOML
+            keywords = ["if", "then",
+                "end"]
+            symbols = ['==', '=']
+            single_line_cmt = "//"
+        "#;
+        let from_json = OwnedScannerConfig::from_json(json).unwrap();
+        let from_toml = OwnedScannerConfig::from_toml(toml).unwrap();
+        assert_eq!(from_json, from_toml);
+        assert_eq!(from_json.symbols, &["==", "="]);
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run("if a == b then end // x", &from_toml, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_kinds.len(), 7);
+        assert!(OwnedScannerConfig::from_json(r#"{"keyword": []}"#).is_err());
+        assert!(OwnedScannerConfig::from_toml("keywords = [1]").is_err());
+        assert!(crate::value::Value::from_json(&"[".repeat(200_000)).is_err());
+        assert!(crate::value::Value::from_toml(&format!("a = {}", "[".repeat(200_000))).is_err());
+        assert!(crate::value::Value::from_json(&format!("{}1{}", "[".repeat(100), "]".repeat(100))).is_ok());
+        assert!(crate::value::Value::from_json("[nan, inf]").is_err());
+        #[cfg(feature = "json")]
+        assert!(ScannerData::from_json(&format!("{{\"tokens\": {}", "[".repeat(200_000)), "").is_none());
+    }
+
+    #[test]
+    fn presets() {
+        let source_code = "int main(void) { return a->b >= 0x10; } /* done */";
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(source_code, &presets::C, &mut scanner_data).unwrap();
+        let texts: Vec<&str> = scanner_data.token_spans.iter().map(|span| &source_code[span.range()]).collect();
+        assert_eq!(texts, &["int", "main", "(", "void", ")", "{", "return", "a", "->", "b", ">=", "0x10", ";", "}", "/* done */"]);
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(r#"{"a": [1, true, null]}"#, presets::by_name("JSON").unwrap(), &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_kinds.len(), 11);
+        assert!(presets::by_name("cobol").is_none());
+
+        let snippets = [
+            ("lua", "local s = 'it' .. \"s\" .. [[raw]] -- comment", "it"),
+            ("rust", "let s: &str = \"a\\n\"; // comment", "a\n"),
+            ("python", "def f():\n    return 'a' + \"b\" + '''doc\nmore''' # comment", "a"),
+            ("javascript", "const s = 'a' + \"b\" + `c${d}`; // comment", "a"),
+        ];
+        for (name, source_code, first_string) in snippets {
+            let config = presets::by_name(name).unwrap();
+            Scanner::default().run(source_code, config, &mut scanner_data).unwrap();
+            let (index, _) = scanner_data.find_all(TokenKind::StringLiteral).next().unwrap();
+            assert_eq!(scanner_data.token_types[index], TokenType::StringLiteral(first_string.to_owned()));
+            assert_eq!(scanner_data.token_kinds.last(), Some(&TokenKind::Comment), "{}", name);
+        }
+        let source_code = "\"\"\"doc\nmore \"quoted\\\"\"\"\" '''a'b'''";
+        Scanner::default().run(source_code, &presets::PYTHON, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_types, &[
+            TokenType::StringLiteral("doc\nmore \"quoted\"".to_owned()),
+            TokenType::StringLiteral("a'b".to_owned()),
+        ]);
+        // raw byte strings and raw C strings
+        let source_code = r##"br"raw\n" br#"a "q" b"# cr"c" b"\n""##;
+        Scanner::default().run(source_code, &presets::RUST, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_types, &[
+            TokenType::StringLiteral("raw\\n".to_owned()),
+            TokenType::StringLiteral("a \"q\" b".to_owned()),
+            TokenType::StringLiteral("c".to_owned()),
+            TokenType::StringLiteral("\n".to_owned()),
+        ]);
+        let prefixes: Vec<_> = (0..4).map(|i| scanner_data.string_prefix(i)).collect();
+        assert_eq!(prefixes, &[Some("b"), Some("b"), Some("c"), Some("b")]);
+    }
+
+    #[test]
+    fn char_literals() {
+        let source_code = r#"c = 'a'; s = "a"; nl = '\n'; q = '\''; u = 'é';"#;
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(source_code, &presets::C, &mut scanner_data).unwrap();
+        let literals: Vec<&TokenType> = scanner_data.token_types.iter().filter(|t| !matches!(t, TokenType::Identifier(_) | TokenType::Symbol(_))).collect();
+        assert_eq!(literals, &[
+            &TokenType::CharLiteral('a'),
+            &TokenType::StringLiteral("a".to_string()),
+            &TokenType::CharLiteral('\n'),
+            &TokenType::CharLiteral('\''),
+            &TokenType::CharLiteral('é'),
+        ]);
+        let res = Scanner::default().run("'ab'", &presets::C, &mut ScannerData::default());
+        assert!(matches!(res, Err(ScanError::UnknownToken(e)) if (e.line, e.offset) == (1, 0)));
+    }
+
+    #[test]
+    fn raw_strings() {
+        let source_code = "s = [[a\\n]] .. [==[b]]c]==] --[[comment]]";
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(source_code, &presets::LUA, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_types,&[
+            TokenType::Identifier("s".to_string()),
+            TokenType::Symbol("=".to_string()),
+            TokenType::StringLiteral("a\\n".to_string()),
+            TokenType::Symbol("..".to_string()),
+            TokenType::StringLiteral("b]]c".to_string()),
+            TokenType::Comment("--[[comment]]".to_string()),
+        ]);
+
+        let source_code = r###"let s = r#"say "hi""#; let t = r"\d";"###;
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(source_code, &presets::RUST, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_types[3], TokenType::StringLiteral(r#"say "hi""#.to_string()));
+        assert_eq!(scanner_data.token_types[8], TokenType::StringLiteral(r"\d".to_string()));
+
+        let res = Scanner::default().run("[=[ abc ]]", &presets::LUA, &mut ScannerData::default());
+        assert!(matches!(res, Err(ScanError::UnexpectedEof(e)) if (e.line, e.offset) == (1, 0)));
+    }
+
+    #[test]
+    fn heredoc() {
+        const SHELL: ScannerConfig = ScannerConfig {
+            symbols: &["|", ";", "<"],
+            single_line_cmt: Some("#"),
+            heredoc_start: Some("<<"),
+            ..ScannerConfig::DEFAULT
+        };
+        let source_code = "cat <<'END' | wc\nline 1\n  line 2\nEND\necho done <<\n";
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(source_code, &SHELL, &mut scanner_data).unwrap();
+        // without terminator, << is not a heredoc
+        assert_eq!(scanner_data.token_types.len(), 6);
+        assert_eq!(scanner_data.token_types[..4],[
+            TokenType::Identifier("cat".to_string()),
+            TokenType::StringLiteral("line 1\n  line 2".to_string()),
+            TokenType::Identifier("echo".to_string()),
+            TokenType::Identifier("done".to_string()),
+        ]);
+        assert_eq!(scanner_data.token_lines[..4], [1,1,5,5]);
+        let res = Scanner::default().run("cat <<EOF\nabc", &SHELL, &mut ScannerData::default());
+        assert!(matches!(res, Err(ScanError::UnexpectedEof(e)) if (e.line, e.offset) == (2, 4)));
+    }
+
+    #[test]
+    fn string_interpolation() {
+        let source_code = "let s = `a ${x + f({y: 1})} b \\${c}` + `${n}`;";
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(source_code, &presets::JAVASCRIPT, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_types[3..],[
+            TokenType::StringPart("a ".to_string()),
+            TokenType::InterpolationStart,
+            TokenType::Identifier("x".to_string()),
+            TokenType::Symbol("+".to_string()),
+            TokenType::Identifier("f".to_string()),
+            TokenType::Symbol("(".to_string()),
+            TokenType::Symbol("{".to_string()),
+            TokenType::Identifier("y".to_string()),
+            TokenType::Symbol(":".to_string()),
+            TokenType::NumberLiteral("1".to_string(), NumberValue::Int(1), None, 10),
+            TokenType::Symbol("}".to_string()),
+            TokenType::Symbol(")".to_string()),
+            TokenType::InterpolationEnd,
+            TokenType::StringPart(" b ${c}".to_string()),
+            TokenType::Symbol("+".to_string()),
+            TokenType::StringPart("".to_string()),
+            TokenType::InterpolationStart,
+            TokenType::Identifier("n".to_string()),
+            TokenType::InterpolationEnd,
+            TokenType::StringPart("".to_string()),
+            TokenType::Symbol(";".to_string()),
+        ]);
+        let res = Scanner::default().run("`abc ${x}", &presets::JAVASCRIPT, &mut ScannerData::default());
+        assert!(matches!(res, Err(ScanError::UnexpectedEof(e)) if (e.line, e.offset) == (1, 9)));
+    }
+
+    #[test]
+    fn unicode_escapes() {
+        let source_code = r#"s = "\u{1F600} \u00e9\x41 \uD83D\uDE00 \u{zz}""#;
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(source_code, &presets::JAVASCRIPT, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_types[2], TokenType::StringLiteral("😀 éA 😀 u{zz}".to_string()));
+        assert_eq!(scanner_data.token_len_chars(2), source_code.chars().count() - 4);
+    }
+
+    #[test]
+    fn single_line_strings() {
+        const CONFIG: ScannerConfig = ScannerConfig {
+            symbols: &["="],
+            strings_may_span_lines: false,
+            ..ScannerConfig::DEFAULT
+        };
+        let source_code = "a = \"ab\\\ncd\"\nb = \"ef\nc";
+
+        let mut scanner_data = ScannerData::default();
+        let res = Scanner::default().run(source_code, &CONFIG, &mut scanner_data);
+        assert!(matches!(res, Err(ScanError::UnterminatedString(e)) if (e.line, e.offset) == (3, 17)));
+        assert_eq!(scanner_data.token_types[2], TokenType::StringLiteral("ab\ncd".to_string()));
+        assert_eq!(scanner_data.token_types[5], TokenType::StringLiteral("ef".to_string()));
+        assert_eq!(scanner_data.token_lines[5], 3);
+        assert_eq!(scanner_data.token_spans[5].len(), 3);
+
+        let config = OwnedScannerConfig::default();
+        assert!(config.strings_may_span_lines);
+        assert!(Scanner::default().run("\"a\nb\"", &config, &mut ScannerData::default()).is_ok());
+    }
+
+    #[test]
+    fn raw_and_decoded_strings() {
+        let source_code = r#"print("a\tb\"c")"#;
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(source_code, &LUA_CONFIG, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_types[2], TokenType::StringLiteral("a\tb\"c".to_string()));
+        assert_eq!(scanner_data.raw_text(2), r#""a\tb\"c""#);
+        let rebuilt: String = (0..scanner_data.token_kinds.len()).map(|i| scanner_data.raw_text(i)).collect();
+        assert_eq!(rebuilt, source_code);
+    }
+
+    #[test]
+    fn scientific_notation() {
+        let source_code = "x = 1e10 + 2.5E-3 - 3e+2 * 4e";
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(source_code, &presets::LUA, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_types[2], TokenType::NumberLiteral("1e10".to_string(), NumberValue::Float(1e10), None, 10));
+        assert_eq!(scanner_data.token_types[4], TokenType::NumberLiteral("2.5E-3".to_string(), NumberValue::Float(2.5e-3), None, 10));
+        assert_eq!(scanner_data.token_types[6], TokenType::NumberLiteral("3e+2".to_string(), NumberValue::Float(300.0), None, 10));
+        assert_eq!(scanner_data.token_types[8], TokenType::NumberLiteral("4".to_string(), NumberValue::Int(4), None, 10));
+        assert_eq!(scanner_data.token_types[9], TokenType::Identifier("e".to_string()));
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run("1e10", &LUA_CONFIG, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_types.len(), 2);
+    }
+
+    #[test]
+    fn number_separators() {
+        let source_code = "1_000_000 + 0xFF_FF + 0b1010_1010 + 1_0.5_5 + 2_";
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(source_code, &presets::RUST, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_types[0], TokenType::NumberLiteral("1_000_000".to_string(), NumberValue::Int(1_000_000), None, 10));
+        assert_eq!(scanner_data.token_types[2], TokenType::NumberLiteral("0xFF_FF".to_string(), NumberValue::Int(65535), None, 16));
+        assert_eq!(scanner_data.token_types[4], TokenType::NumberLiteral("0b1010_1010".to_string(), NumberValue::Int(170), None, 2));
+        assert_eq!(scanner_data.token_types[6], TokenType::NumberLiteral("1_0.5_5".to_string(), NumberValue::Float(10.55), None, 10));
+        assert_eq!(scanner_data.token_types[8], TokenType::NumberLiteral("2".to_string(), NumberValue::Int(2), None, 10));
+        assert_eq!(scanner_data.token_types[9], TokenType::Identifier("_".to_string()));
+    }
+
+    #[test]
+    fn integer_literals() {
+        let source_code = "9007199254740993 1.0 0x10000000000000000";
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(source_code, &presets::LUA, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_types[0], TokenType::NumberLiteral("9007199254740993".to_string(), NumberValue::Int(9007199254740993), None, 10));
+        assert_eq!(scanner_data.token_types[1], TokenType::NumberLiteral("1.0".to_string(), NumberValue::Float(1.0), None, 10));
+        // too large for an i64
+        assert_eq!(scanner_data.token_types[2], TokenType::NumberLiteral("0x10000000000000000".to_string(), NumberValue::Float(18446744073709551616.0), None, 16));
+        assert_eq!(NumberValue::Int(3).as_f64(), 3.0);
+        assert_eq!(NumberValue::Float(3.0).as_i64(), None);
+    }
+
+    #[test]
+    fn number_suffixes() {
+        let source_code = "1u32 + 2.5f64 + 0xFFu8 + 0x1f32 + 3usize + 4ux";
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(source_code, &presets::RUST, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_types[0], TokenType::NumberLiteral("1u32".to_string(), NumberValue::Int(1), Some("u32".to_string()), 10));
+        assert_eq!(scanner_data.token_types[2], TokenType::NumberLiteral("2.5f64".to_string(), NumberValue::Float(2.5), Some("f64".to_string()), 10));
+        assert_eq!(scanner_data.token_types[4], TokenType::NumberLiteral("0xFFu8".to_string(), NumberValue::Int(255), Some("u8".to_string()), 16));
+        // f32 are hexadecimal digits
+        assert_eq!(scanner_data.token_types[6], TokenType::NumberLiteral("0x1f32".to_string(), NumberValue::Int(0x1f32), None, 16));
+        assert_eq!(scanner_data.token_types[8], TokenType::NumberLiteral("3usize".to_string(), NumberValue::Int(3), Some("usize".to_string()), 10));
+        // not a known suffix
+        assert_eq!(scanner_data.token_types[10], TokenType::NumberLiteral("4".to_string(), NumberValue::Int(4), None, 10));
+        assert_eq!(scanner_data.token_types[11], TokenType::Identifier("ux".to_string()));
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run("10UL 1.5f", &presets::C, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_types[0], TokenType::NumberLiteral("10UL".to_string(), NumberValue::Int(10), Some("UL".to_string()), 10));
+        assert_eq!(scanner_data.token_types[1], TokenType::NumberLiteral("1.5f".to_string(), NumberValue::Float(1.5), Some("f".to_string()), 10));
+    }
+
+    #[test]
+    fn octal_numbers() {
+        let source_code = "0o777 0777 0 09 0.5";
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(source_code, &presets::C, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_types[0], TokenType::NumberLiteral("0o777".to_string(), NumberValue::Int(511), None, 8));
+        assert_eq!(scanner_data.token_types[1], TokenType::NumberLiteral("0777".to_string(), NumberValue::Int(511), None, 8));
+        assert_eq!(scanner_data.token_types[2], TokenType::NumberLiteral("0".to_string(), NumberValue::Int(0), None, 10));
+        assert_eq!(scanner_data.token_types[3], TokenType::NumberLiteral("09".to_string(), NumberValue::Int(9), None, 10));
+        assert_eq!(scanner_data.token_types[4], TokenType::NumberLiteral("0.5".to_string(), NumberValue::Float(0.5), None, 10));
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(source_code, &presets::RUST, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_types[0], TokenType::NumberLiteral("0o777".to_string(), NumberValue::Int(511), None, 8));
+        assert_eq!(scanner_data.token_types[1], TokenType::NumberLiteral("0777".to_string(), NumberValue::Int(777), None, 10));
+    }
+
+    #[test]
+    fn hex_floats() {
+        let source_code = "0x1.8p3 0xA.8 0x1p-2 0x1.8p3f 0xFF";
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(source_code, &presets::C, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_types[0], TokenType::NumberLiteral("0x1.8p3".to_string(), NumberValue::Float(12.0), None, 16));
+        assert_eq!(scanner_data.token_types[1], TokenType::NumberLiteral("0xA.8".to_string(), NumberValue::Float(10.5), None, 16));
+        assert_eq!(scanner_data.token_types[2], TokenType::NumberLiteral("0x1p-2".to_string(), NumberValue::Float(0.25), None, 16));
+        assert_eq!(scanner_data.token_types[3], TokenType::NumberLiteral("0x1.8p3f".to_string(), NumberValue::Float(12.0), Some("f".to_string()), 16));
+        assert_eq!(scanner_data.token_types[4], TokenType::NumberLiteral("0xFF".to_string(), NumberValue::Int(255), None, 16));
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run("0x1.8p3", &presets::RUST, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_types[0], TokenType::NumberLiteral("0x1".to_string(), NumberValue::Int(1), None, 16));
+    }
+
+    #[test]
+    fn exact_floats() {
+        let source_code = "0.1 9007199254740993.0 99999999999999999999 1.7976931348623157e308 0.30000000000000004";
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(source_code, &presets::LUA, &mut scanner_data).unwrap();
+        let values: Vec<f64> = scanner_data.token_types.iter().filter_map(|t| match t {
+            TokenType::NumberLiteral(_, value, _, _) => Some(value.as_f64()),
+            _ => None,
+        }).collect();
+        assert_eq!(values, vec![0.1, 9007199254740992.0, 1e20, f64::MAX, 0.30000000000000004]);
+    }
+
+    #[test]
+    fn custom_char_predicates() {
+        const LISP: ScannerConfig = ScannerConfig {
+            keywords: &["defun"],
+            symbols: &["(", ")", "-"],
+            identifier_char: |c| crate::is_alphanum(c) || c == '-' || c == '?',
+            space_char: |c| crate::is_space(c) || c == ',',
+            ..ScannerConfig::DEFAULT
+        };
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run("(defun-x empty? a,b - c)", &LISP, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_types, vec![
+            TokenType::Symbol("(".to_string()),
+            TokenType::Identifier("defun-x".to_string()),
+            TokenType::Identifier("empty?".to_string()),
+            TokenType::Identifier("a".to_string()),
+            TokenType::Identifier("b".to_string()),
+            TokenType::Symbol("-".to_string()),
+            TokenType::Identifier("c".to_string()),
+            TokenType::Symbol(")".to_string()),
+        ]);
+
+        let shell = ScannerConfig {
+            identifier_start: |c| crate::is_alpha(c) || c == '$',
+            ..presets::C
+        };
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run("echo $HOME", &shell, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_types[1], TokenType::Identifier("$HOME".to_string()));
+    }
+
+    #[test]
+    fn unicode_identifiers() {
+        let source_code = "données = 变量 + x²";
+
+        let mut scanner_data = ScannerData::default();
+        assert!(matches!(Scanner::default().run(source_code, &LUA_CONFIG, &mut scanner_data), Err(ScanError::UnknownToken(e)) if (e.line, e.offset) == (1, 4)));
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(source_code, &presets::PYTHON, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_types, vec![
+            TokenType::Identifier("données".to_string()),
+            TokenType::Symbol("=".to_string()),
+            TokenType::Identifier("变量".to_string()),
+            TokenType::Symbol("+".to_string()),
+            TokenType::Identifier("x²".to_string()),
+        ]);
+
+        #[cfg(feature = "config")]
+        {
+            let config = OwnedScannerConfig::from_json(r#"{ "unicode_identifiers": true }"#).unwrap();
+            let mut scanner_data = ScannerData::default();
+            Scanner::default().run("变量", &config, &mut scanner_data).unwrap();
+            assert_eq!(scanner_data.token_types[0], TokenType::Identifier("变量".to_string()));
+        }
+    }
+
+    #[test]
+    fn nested_comments() {
+        let source_code = "/* a /* b */ c */ d";
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(source_code, &presets::RUST, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_types, vec![
+            TokenType::Comment("/* a /* b */ c */".to_string()),
+            TokenType::Identifier("d".to_string()),
+        ]);
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(source_code, &presets::C, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_types, vec![
+            TokenType::Comment("/* a /* b */".to_string()),
+            TokenType::Identifier("c".to_string()),
+            TokenType::Symbol("*".to_string()),
+            TokenType::Symbol("/".to_string()),
+            TokenType::Identifier("d".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn skip_comments() {
+        let source_code = "x = 1 -- one\n--[[ two ]] y";
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().with_comments(false).run(source_code, &LUA_CONFIG, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_types, vec![
+            TokenType::Identifier("x".to_string()),
+            TokenType::Symbol("=".to_string()),
+            TokenType::NumberLiteral("1".to_string(), NumberValue::Int(1), None, 10),
+            TokenType::Identifier("y".to_string()),
+        ]);
+        assert_eq!(scanner_data.token_lines, &[1, 1, 1, 2]);
+
+        let tokens = Scanner::default().with_comments(false).tokens(source_code, &LUA_CONFIG);
+        assert_eq!(tokens.count(), 4);
+    }
+
+    #[test]
+    fn directives() {
+        let source_code = "#include <stdio.h>\n  # define X 1\nint a = X # 2;";
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(source_code, &presets::C, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_types[0], TokenType::Directive("#include <stdio.h>".to_string()));
+        assert_eq!(scanner_data.token_types[1], TokenType::Directive("# define X 1".to_string()));
+        assert_eq!(scanner_data.token_lines[1], 2);
+        assert_eq!(scanner_data.token_start_char(1), 21);
+        // not at the beginning of a line
+        assert_eq!(scanner_data.token_types[6], TokenType::Symbol("#".to_string()));
+    }
+
+    #[test]
+    fn line_continuation() {
+        let source_code = "#define MAX(a, b) \\\n  ((a) > (b) ? (a) : (b))\nint x = 1 \\\r\n + 2;";
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(source_code, &presets::C, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_types[0], TokenType::Directive("#define MAX(a, b) \\\n  ((a) > (b) ? (a) : (b))".to_string()));
+        assert_eq!(scanner_data.token_types[5], TokenType::Symbol("+".to_string()));
+        // physical lines
+        assert_eq!(scanner_data.token_lines, &[1, 3, 3, 3, 3, 4, 4, 4]);
+
+        let mut scanner_data = ScannerData::default();
+        let result = Scanner::default().run("a \\ b", &presets::C, &mut scanner_data);
+        assert!(matches!(result, Err(ScanError::UnknownToken(e)) if (e.line, e.offset) == (1, 2)));
+    }
+
+    #[test]
+    fn newline_tokens() {
+        let source_code = "a = 1\r\n--[[ x\n]] b = 2 -- c\n\n";
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().with_newlines(true).run(source_code, &LUA_CONFIG, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_kinds, &[
+            TokenKind::Identifier, TokenKind::Symbol, TokenKind::NumberLiteral, TokenKind::NewLine,
+            TokenKind::Comment, TokenKind::Identifier, TokenKind::Symbol, TokenKind::NumberLiteral,
+            TokenKind::Comment, TokenKind::NewLine, TokenKind::NewLine,
+        ]);
+        assert_eq!(scanner_data.token_types[3], TokenType::NewLine);
+        assert_eq!(scanner_data.token_lines[3], 1);
+        assert_eq!(scanner_data.token_start_char(3), 6);
+        assert_eq!(scanner_data.token_len_chars(3), 1);
+        assert_eq!(scanner_data.token_lines[10], 4);
+    }
+
+    #[test]
+    fn full_fidelity() {
+        let source_code = "local s = \"à\" -- comment\r\n\tx=0x1f  \n--[[ a\n]]";
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().with_whitespace(true).run(source_code, &LUA_CONFIG, &mut scanner_data).unwrap();
+        let text: String = scanner_data.token_spans.iter().map(|span| &source_code[span.range()]).collect();
+        assert_eq!(text, source_code);
+        assert_eq!(scanner_data.token_kinds[1], TokenKind::Ignore);
+        assert_eq!(scanner_data.token_types[1], TokenType::Ignore);
+    }
+
+    #[test]
+    fn trivia() {
+        let source_code = "-- header\nlocal x = 1 -- one\n\n  --[[ doc ]] y = 2 \n";
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().with_trivia(true).run(source_code, &LUA_CONFIG, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_kinds.len(), 7);
+        assert_eq!(scanner_data.leading_trivia(0), "-- header\n");
+        assert_eq!(scanner_data.trailing_trivia(0), " ");
+        assert_eq!(scanner_data.trailing_trivia(3), " -- one\n");
+        assert_eq!(scanner_data.leading_trivia(4), "\n  --[[ doc ]] ");
+        assert_eq!(scanner_data.trailing_trivia(6), " \n");
+        let text: String = (0..7).map(|i| {
+            format!("{}{}{}", scanner_data.leading_trivia(i), scanner_data.raw_text(i), scanner_data.trailing_trivia(i))
+        }).collect();
+        assert_eq!(text, source_code);
+    }
+
+    #[test]
+    fn utf16_positions() {
+        let source_code = "s = \"😀à\" .. x\n  y";
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(source_code, &LUA_CONFIG, &mut scanner_data).unwrap();
+        // the emoji needs two UTF-16 code units
+        assert_eq!(scanner_data.token_utf16_range(2), ((0, 4), (0, 9)));
+        assert_eq!(scanner_data.token_utf16_range(4), ((0, 13), (0, 14)));
+        assert_eq!(scanner_data.token_utf16_range(5), ((1, 2), (1, 3)));
+        assert_eq!(scanner_data.utf16_position(6), (0, 5));
+        assert_eq!(scanner_data.utf16_position(1000), (1, 3));
+    }
+
+    #[test]
+    fn token_view() {
+        let source_code = "local x\n= 1";
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(source_code, &LUA_CONFIG, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.len(), 4);
+        let token = scanner_data.get(2).unwrap();
+        assert_eq!(token.kind, TokenKind::Symbol);
+        assert_eq!(token.line, 2);
+        assert_eq!(token.text(scanner_data.source), "=");
+        assert!(scanner_data.get(4).is_none());
+        let texts: Vec<&str> = scanner_data.iter().map(|t| t.text(source_code)).collect();
+        assert_eq!(texts, &["local", "x", "=", "1"]);
+    }
+
+    #[test]
+    fn token_text() {
+        let source_code = r#"local s="à" -- comment"#;
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(source_code, &LUA_CONFIG, &mut scanner_data).unwrap();
+        let st: String = (0..scanner_data.len()).map(|i| scanner_data.token_text(i)).collect();
+        assert_eq!(&st, "locals=\"à\"-- comment");
+        assert_eq!(scanner_data.token_range(3), 8..12);
+        assert_eq!(scanner_data.token_range(4), 13..23);
+    }
+
+    #[test]
+    fn line_index() {
+        let source_code = "a = 1\n\nb = \"é\"\n";
+
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(source_code, &LUA_CONFIG, &mut scanner_data).unwrap();
+        let index = &scanner_data.line_index;
+        assert_eq!(index.line_count(), 4);
+        assert_eq!(index.offset_to_line_col(0), (1, 0));
+        assert_eq!(index.offset_to_line_col(6), (2, 0));
+        assert_eq!(index.offset_to_line_col(scanner_data.token_spans[5].start()), (3, 4));
+        assert_eq!(index.line_col_to_offset(3, 4), Some(11));
+        assert_eq!(index.line_col_to_offset(3, 8), Some(15));
+        assert_eq!(index.line_col_to_offset(3, 9), None);
+        assert_eq!(index.line_col_to_offset(2, 1), None);
+        assert_eq!(index.line_col_to_offset(4, 0), Some(source_code.len()));
+        assert_eq!(index.line_col_to_offset(5, 0), None);
+        assert_eq!(index, &LineIndex::new(source_code));
+        assert_eq!(scanner_data.line_starts(), &[0, 6, 7, 16]);
+        assert_eq!(scanner_data.line_text(1), Some("a = 1"));
+        assert_eq!(scanner_data.line_text(2), Some(""));
+        assert_eq!(scanner_data.line_text(4), Some(""));
+        assert_eq!(scanner_data.line_text(5), None);
+        let mut crlf_data = ScannerData::default();
+        Scanner::default().run("a\r\nb", &LUA_CONFIG, &mut crlf_data).unwrap();
+        assert_eq!(crlf_data.line_text(1), Some("a"));
+        assert_eq!(crlf_data.line_text(2), Some("b"));
+    }
+
+    #[test]
+    fn lossy_scan() {
+        let source_code = "a = ? 1 ! b\nc = \"x";
+
+        let (scanner_data, errors) = Scanner::default().run_lossy(source_code, &LUA_CONFIG);
+        let errors: Vec<_> = errors.iter().map(|e| (e.message(), e.position())).collect();
+        assert_eq!(errors, vec![
+            ("unknown token", (1, 4)),
+            ("unknown token", (1, 8)),
+            ("unexpected end of file", (2, 16)),
+        ]);
+        assert_eq!(scanner_data.token_kinds, &[
+            TokenKind::Identifier, TokenKind::Symbol, TokenKind::Unknown, TokenKind::NumberLiteral,
+            TokenKind::Unknown, TokenKind::Identifier, TokenKind::Identifier, TokenKind::Symbol,
+            TokenKind::StringLiteral,
+        ]);
+        assert_eq!(scanner_data.token_text(8), "\"x");
+
+        let mut scanner_data = ScannerData::default();
+        assert!(matches!(Scanner::default().run(source_code, &LUA_CONFIG, &mut scanner_data), Err(ScanError::UnknownToken(e)) if (e.line, e.offset) == (1, 4)));
+        assert_eq!(scanner_data.len(), 3);
+    }
+
+    #[test]
+    fn diagnostics() {
+        let source_code = "local a = 1\nlocal b = a ? 2\n";
+
+        let mut scanner_data = ScannerData::default();
+        let error = Scanner::default().run(source_code, &LUA_CONFIG, &mut scanner_data).unwrap_err();
+        assert_eq!(
+            crate::diagnostics::render(&error, source_code, "foo.lua"),
+            "error[E0001]: unknown token\n --> foo.lua:2:13\n  |\n2 | local b = a ? 2\n  |             ^\n"
+        );
+
+        let source_code = "x = \"abc";
+        let error = Scanner::default().run(source_code, &LUA_CONFIG, &mut scanner_data).unwrap_err();
+        assert_eq!(
+            crate::diagnostics::render(&error, source_code, "bar.lua"),
+            "error[E0002]: unexpected end of file\n --> bar.lua:1:5\n  |\n1 | x = \"abc\n  |     ^^^^\n"
+        );
+    }
+
+    #[test]
+    fn error_spans() {
+        fn scan(source_code: &str) -> Result<usize, Box<dyn std::error::Error>> {
+            let mut scanner_data = ScannerData::default();
+            Scanner::default().run(source_code, &LUA_CONFIG, &mut scanner_data)?;
+            Ok(scanner_data.len())
+        }
+        assert_eq!(scan("a = 1").unwrap(), 3);
+        let error = scan("a = 1\nb = é").unwrap_err();
+        assert_eq!(error.to_string(), "2:10 : unknown token");
+
+        let mut scanner_data = ScannerData::default();
+        let error = Scanner::default().run("a = 1\nb = \"é\nc", &LUA_CONFIG, &mut scanner_data).unwrap_err();
+        assert_eq!(error, ScanError::UnexpectedEof(ErrorSpan {
+            span: Span::new(10, 5),
+            line: 3,
+            offset: 10,
+            column: 4,
+        }));
+        assert_eq!(error.clone().span().span.range(), 10..15);
+    }
+
+    #[test]
+    fn error_codes() {
+        let (_, errors) = Scanner::default().run_lossy("a ? \"b", &LUA_CONFIG);
+        let codes: Vec<_> = errors.iter().map(ScanError::code).collect();
+        assert_eq!(codes, &["E0001", "E0002"]);
+        #[cfg(feature = "json")]
+        {
+            assert_eq!(
+                errors[0].to_json(),
+                r#"{"code":"E0001","message":"unknown token","line":1,"offset":2,"column":2,"start":2,"len":1}"#
+            );
+            assert_eq!(crate::value::Value::from_json(&errors[1].to_json()).unwrap(), errors[1].to_value());
+        }
+    }
+
+    #[test]
+    fn scanner_state() {
+        let source_code = "let s = `a${ {b: 1} }c` + 2;";
+
+        let mut tokens = Scanner::default().tokens(source_code, &presets::JAVASCRIPT);
+        let first: Vec<_> = tokens.by_ref().take(6).map(|t| t.unwrap().text(source_code)).collect();
+        assert_eq!(first, &["let", "s", "=", "`a", "${", "{"]);
+        let state = tokens.state();
+        #[cfg(feature = "json")]
+        {
+            let saved = state.to_json();
+            assert_eq!(crate::ScannerState::from_value(&crate::value::Value::from_json(&saved).unwrap()).unwrap(), state);
+        }
+        assert_eq!(state.offset, 14);
+        let rest: Vec<_> = Scanner::default()
+            .tokens(source_code, &presets::JAVASCRIPT)
+            .with_state(&state)
+            .map(|t| t.unwrap().text(source_code))
+            .collect();
+        assert_eq!(rest, &["b", ":", "1", "}", "}", "c`", "+", "2", ";"]);
+    }
+
+    #[test]
+    fn scan_range() {
+        let source_code = "a = 1\nb = \"x\ny\" c\nd = 2";
+
+        let mut scanner_data = ScannerData::default();
+        let state = Scanner::default().run_range(source_code, &LUA_CONFIG, &mut scanner_data, &Default::default(), 11).unwrap();
+        // the string starts before the end of the range
+        let texts: Vec<_> = scanner_data.iter().map(|t| t.text(source_code)).collect();
+        assert_eq!(texts, &["a", "=", "1", "b", "=", "\"x\ny\""]);
+        assert_eq!(state.line, 3);
+
+        let mut scanner_data = ScannerData::default();
+        let state = Scanner::default().run_range(source_code, &LUA_CONFIG, &mut scanner_data, &state, source_code.len()).unwrap();
+        let texts: Vec<_> = scanner_data.iter().map(|t| t.text(source_code)).collect();
+        assert_eq!(texts, &["c", "d", "=", "2"]);
+        assert_eq!(scanner_data.token_lines, &[3, 4, 4, 4]);
+        assert_eq!(state.offset, source_code.len());
+    }
+
+    #[test]
+    fn reuse_scanner_data() {
+        let mut scanner_data = ScannerData::default();
+        let mut scanner = Scanner::default();
+        scanner.run("local a = 1 -- long enough", &LUA_CONFIG, &mut scanner_data).unwrap();
+        let capacity = scanner_data.token_kinds.capacity();
+        scanner.run("b", &LUA_CONFIG, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_types, &[TokenType::Identifier("b".to_string())]);
+        assert_eq!(scanner_data.source, "b");
+        assert_eq!(scanner_data.token_kinds.capacity(), capacity);
+
+        scanner_data.clear();
+        assert!(scanner_data.is_empty());
+        assert!(scanner_data.source.is_empty());
+    }
+
+    #[test]
+    fn interning() {
+        let mut scanner_data = ScannerData::default();
+        let mut scanner = Scanner::default().with_interning(true);
+        scanner
+            .run("local a = a + b local", &LUA_CONFIG, &mut scanner_data)
+            .unwrap();
+        let symbols: Vec<_> = scanner_data.token_symbols.clone();
+        assert_eq!(symbols.len(), scanner_data.len());
+        assert_eq!(symbols[0], symbols[6]);
+        assert_eq!(symbols[1], symbols[3]);
+        assert_ne!(symbols[1], symbols[5]);
+        assert_eq!(symbols[2], None);
+        assert_eq!(scanner_data.symbols.len(), 3);
+        assert_eq!(scanner_data.resolve(symbols[5].unwrap()), "b");
+        // symbols are kept when the data is reused
+        scanner.run("b", &LUA_CONFIG, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_symbol(0), symbols[5]);
+    }
+
+    #[test]
+    fn symbol_order() {
+        // the longest matching entry wins, whatever the order of the list
+        let config: ScannerConfig = ScannerConfig {
+            keywords: &["do", "done"],
+            symbols: &["=", "==", "<=", "<"],
+            ..ScannerConfig::DEFAULT
+        };
+        let mut scanner_data = ScannerData::default();
+        Scanner::default()
+            .run("== <= done do", &config, &mut scanner_data)
+            .unwrap();
+        assert_eq!(
+            scanner_data.token_types,
+            vec![
+                TokenType::Symbol("==".to_owned()),
+                TokenType::Symbol("<=".to_owned()),
+                TokenType::Keyword("done".to_owned()),
+                TokenType::Keyword("do".to_owned()),
+            ]
+        );
+        let config: ScannerConfig = ScannerConfig {
+            number_suffixes: &["L", "UL"],
+            ..ScannerConfig::DEFAULT
+        };
+        Scanner::default()
+            .run("10UL", &config, &mut scanner_data)
+            .unwrap();
+        assert_eq!(
+            scanner_data.token_types[0],
+            TokenType::NumberLiteral("10UL".to_owned(), NumberValue::Int(10), Some("UL".to_owned()), 10)
+        );
+    }
+
+    #[test]
+    fn compiled_config() {
+        let config = presets::LUA.compile();
+        let mut scanner = Scanner::default();
+        let mut scanner_data = ScannerData::default();
+        for source in ["local a = 1", "return a .. \"b\""] {
+            scanner.run(source, &config, &mut scanner_data).unwrap();
+            let mut expected = ScannerData::default();
+            scanner.run(source, &presets::LUA, &mut expected).unwrap();
+            assert_eq!(scanner_data.token_types, expected.token_types);
+        }
+        let tokens: Vec<_> = Scanner::default().tokens("a.b", &config).collect();
+        assert_eq!(tokens.len(), 3);
+    }
+
+    #[test]
+    fn long_runs() {
+        // exercises the word-at-a-time paths of the simd feature
+        let source = "        let identifier_with_a_long_name = 1 // comment with àccents and more text\n\t\t  élan_vital_identifier";
+        let mut scanner_data = ScannerData::default();
+        Scanner::default()
+            .run(source, &presets::RUST, &mut scanner_data)
+            .unwrap();
+        assert_eq!(scanner_data.token_text(1), "identifier_with_a_long_name");
+        assert_eq!(
+            scanner_data.token_text(4),
+            "// comment with àccents and more text"
+        );
+        assert_eq!(scanner_data.token_kinds[5], TokenKind::Identifier);
+        assert_eq!(scanner_data.token_lines[5], 2);
+        assert_eq!(scanner_data.token_start_char(5), source.chars().count() - 21);
+        assert_eq!(scanner_data.token_len_chars(4), 37);
+    }
+
+    #[test]
+    fn parallel() {
+        let source = "local a = 1 --[[ a comment\nover several\nlines ]]\nlocal b = [[ a raw string\nover several\nlines ]]\n"
+            .repeat(20);
+        let mut expected = ScannerData::default();
+        let mut scanner = Scanner::default()
+            .with_trivia(true)
+            .with_interning(true)
+            .with_line_states(true);
+        scanner.run(&source, &presets::LUA, &mut expected).unwrap();
+        for threads in 1..8 {
+            let mut scanner_data = ScannerData::default();
+            scanner
+                .run_parallel(&source, &presets::LUA, &mut scanner_data, threads)
+                .unwrap();
+            assert_eq!(scanner_data.token_types, expected.token_types);
+            assert_eq!(scanner_data.token_lines, expected.token_lines);
+            assert_eq!(scanner_data.token_starts_chars(), expected.token_starts_chars());
+            assert_eq!(scanner_data.token_trivia, expected.token_trivia);
+            assert_eq!(scanner_data.token_symbols, expected.token_symbols);
+            assert_eq!(scanner_data.line_states, expected.line_states);
+        }
+        // a slash at a chunk start is a division or a regex depending on the previous chunk
+        let source = "let a = b\n/ c / d;\nlet e = /f/;\n".repeat(50);
+        scanner.run(&source, &presets::JAVASCRIPT, &mut expected).unwrap();
+        for threads in 1..8 {
+            let mut scanner_data = ScannerData::default();
+            scanner
+                .run_parallel(&source, &presets::JAVASCRIPT, &mut scanner_data, threads)
+                .unwrap();
+            assert_eq!(scanner_data.token_types, expected.token_types);
+            assert_eq!(scanner_data.line_states, expected.line_states);
+        }
+        let res = scanner.run_parallel("a = 1\nb = 2\n@", &presets::LUA, &mut expected, 3);
+        assert!(matches!(res, Err(ScanError::UnknownToken(e)) if (e.line, e.offset) == (3, 12)));
+    }
+
+    #[test]
+    fn run_file() {
+        let path = std::env::temp_dir().join("uscan_run_file.lua");
+        std::fs::write(&path, "local a = \"été\"\n").unwrap();
+        let file = crate::SourceFile::open(&path).unwrap();
+        let mut scanner_data = ScannerData::default();
+        Scanner::default()
+            .run_file(&file, &presets::LUA, &mut scanner_data)
+            .unwrap();
+        assert_eq!(scanner_data.len(), 4);
+        std::fs::write(&path, b"local a = \xff").unwrap();
+        let file = crate::SourceFile::open(&path).unwrap();
+        let res = Scanner::default().run_file(&file, &presets::LUA, &mut scanner_data);
+        assert!(matches!(res, Err(crate::FileError::Utf8(_))));
+        #[cfg(all(feature = "mmap", unix, target_pointer_width = "64"))]
+        {
+            std::fs::write(&path, "local a = 1\n").unwrap();
+            // SAFETY: the file is not modified while it is mapped
+            let file = unsafe { crate::SourceFile::open_mapped(&path) }.unwrap();
+            Scanner::default().run_file(&file, &presets::LUA, &mut scanner_data).unwrap();
+            assert_eq!(scanner_data.len(), 4);
+            if cfg!(target_os = "linux") {
+                // special files are read
+                let file = unsafe { crate::SourceFile::open_mapped("/proc/self/status") }.unwrap();
+                assert!(!file.as_bytes().is_empty());
+            }
+        }
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[cfg(feature = "ffi")]
+    #[test]
+    fn ffi() {
+        use crate::ffi::*;
+        let source = "local a = @";
+        unsafe {
+            let result = uscan_scan(source.as_ptr(), source.len(), c"lua".as_ptr());
+            assert_eq!(uscan_token_count(result), 4);
+            let mut token = UscanToken { kind: 0, start: 0, len: 0, line: 0 };
+            assert!(uscan_token_at(result, 1, &mut token));
+            assert_eq!(token, UscanToken { kind: USCAN_IDENTIFIER, start: 6, len: 1, line: 1 });
+            assert!(!uscan_token_at(result, 4, &mut token));
+            let mut offset = 0;
+            assert_eq!(uscan_error(result, std::ptr::null_mut(), &mut offset), 1);
+            assert_eq!(offset, 10);
+            uscan_free(result);
+            assert!(uscan_scan(source.as_ptr(), source.len(), c"cobol".as_ptr()).is_null());
+        }
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn serialization() {
+        let source = "local a = 0x10 + 1.5 -- comment\nreturn a .. \"b\"";
+        let mut scanner_data = ScannerData::default();
+        Scanner::default()
+            .with_trivia(true)
+            .with_interning(true)
+            .run(source, &presets::LUA, &mut scanner_data)
+            .unwrap();
+        let json = scanner_data.to_json();
+        let copy = ScannerData::from_json(&json, source).unwrap();
+        assert_eq!(copy.token_types, scanner_data.token_types);
+        assert_eq!(copy.token_kinds, scanner_data.token_kinds);
+        assert_eq!(copy.token_spans, scanner_data.token_spans);
+        assert_eq!(copy.token_starts_chars(), scanner_data.token_starts_chars());
+        assert_eq!(copy.token_trivia, scanner_data.token_trivia);
+        assert_eq!(copy.token_symbols, scanner_data.token_symbols);
+        assert_eq!(copy.resolve(copy.token_symbol(1).unwrap()), "a");
+        assert_eq!(
+            TokenType::NumberLiteral("0x10".to_owned(), NumberValue::Int(16), None, 16).to_value().to_string(),
+            r#"{"type":"NumberLiteral","text":"0x10","int":16,"suffix":null,"radix":16}"#
+        );
+        let res = Scanner::default().run("a @", &presets::LUA, &mut scanner_data);
+        let error = res.unwrap_err();
+        assert_eq!(ScanError::from_value(&error.to_value()), Some(error));
+    }
+
+    #[test]
+    fn html() {
+        let source = "if a < 1 then -- <b>\n  return \"&\" end";
+        let mut scanner_data = ScannerData::default();
+        Scanner::default()
+            .run(source, &presets::LUA, &mut scanner_data)
+            .unwrap();
+        assert_eq!(
+            crate::emit::html::render(&scanner_data),
+            "<span class=\"keyword\">if</span> a &lt; <span class=\"number\">1</span> \
+            <span class=\"keyword\">then</span> <span class=\"comment\">-- &lt;b&gt;</span>\n  \
+            <span class=\"keyword\">return</span> <span class=\"string\">&quot;&amp;&quot;</span> \
+            <span class=\"keyword\">end</span>"
+        );
+        let html = crate::emit::html::render_with(&scanner_data, |kind| {
+            (kind == TokenKind::Identifier).then_some("id")
+        });
+        assert_eq!(html, "if <span class=\"id\">a</span> &lt; 1 then -- &lt;b&gt;\n  return &quot;&amp;&quot; end");
+    }
+
+    #[test]
+    fn ansi() {
+        let mut scanner_data = ScannerData::default();
+        Scanner::default()
+            .run("local a = 1 -- one", &presets::LUA, &mut scanner_data)
+            .unwrap();
+        assert_eq!(
+            crate::emit::ansi::render(&scanner_data),
+            "\x1b[35mlocal\x1b[0m a = \x1b[33m1\x1b[0m \x1b[90m-- one\x1b[0m"
+        );
+        let out = crate::emit::ansi::render_with(&scanner_data, |kind| {
+            (kind == TokenKind::Identifier).then_some("1")
+        });
+        assert_eq!(out, "local \x1b[1ma\x1b[0m = 1 -- one");
+    }
+
+    #[test]
+    fn semantic_tokens() {
+        use crate::emit::lsp::{encode, Legend};
+        let mut scanner_data = ScannerData::default();
+        let source = "local a = \"é\" --[[ x\r\ny ]] a";
+        Scanner::default()
+            .run(source, &presets::LUA, &mut scanner_data)
+            .unwrap();
+        let legend = Legend::default().with_modifiers(TokenKind::Keyword, &["declaration"]);
+        assert_eq!(legend.token_types[0], "keyword");
+        assert_eq!(legend.token_modifiers, vec!["declaration"]);
+        assert_eq!(
+            encode(&scanner_data, &legend),
+            vec![
+                0, 0, 5, 0, 1, // local
+                0, 6, 1, 6, 0, // a
+                0, 2, 1, 5, 0, // =
+                0, 2, 3, 1, 0, // "é"
+                0, 4, 6, 3, 0, // --[[ x
+                1, 0, 4, 3, 0, // y ]]
+                0, 5, 1, 6, 0, // a
+            ]
+        );
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn theme() {
+        use crate::emit::theme::{Color, Style, Theme};
+        let theme = Theme::from_toml(
+            r##"
+name = "test"
+[default]
+fg = "#d0d0d0"
+[styles]
+keyword = { fg = "#c678dd", bold = true }
+[keyword_categories.control]
+keywords = ["if", "end"]
+fg = "#e06c75"
+"##,
+        )
+        .unwrap();
+        assert_eq!(theme.name, "test");
+        assert_eq!(
+            theme.style(Some(TokenKind::Keyword), "local"),
+            Style { bold: true, ..Style::fg(Color::new(0xc6, 0x78, 0xdd)) }
+        );
+        assert_eq!(theme.style(Some(TokenKind::Keyword), "if").fg, Color::from_hex("#e06c75"));
+        assert_eq!(theme.style(Some(TokenKind::Identifier), "a"), theme.default);
+        assert!(Theme::from_toml("[styles]\nkeywords = { fg = \"#000000\" }").is_err());
+        let mut scanner_data = ScannerData::default();
+        Scanner::default()
+            .run("if a then end", &presets::LUA, &mut scanner_data)
+            .unwrap();
+        assert_eq!(
+            crate::emit::html::render_themed(&scanner_data, &theme),
+            "<span style=\"color:#e06c75\">if</span> a \
+            <span style=\"color:#c678dd;font-weight:bold\">then</span> \
+            <span style=\"color:#e06c75\">end</span>"
+        );
+        assert!(crate::emit::ansi::render_themed(&scanner_data, &Theme::dark())
+            .starts_with("\x1b[38;2;198;120;221;48;2;40;44;52mif\x1b[0m"));
+    }
+
+    #[test]
+    fn console_line() {
+        use crate::emit::console::{render_line, DEFAULT_BACK};
+        use crate::emit::theme::{Color, Style, Theme};
+        let theme = Theme {
+            name: String::new(),
+            default: Style::fg(Color::new(1, 1, 1)),
+            styles: vec![(TokenKind::Comment, Style::fg(Color::new(2, 2, 2)))],
+            keyword_categories: Vec::new(),
+        };
+        let mut scanner_data = ScannerData::default();
+        Scanner::default()
+            .run("a --[[ x\r\ny ]] b\r\n", &presets::LUA, &mut scanner_data)
+            .unwrap();
+        let line = render_line(&scanner_data, &theme, 2);
+        assert_eq!(line.chars.iter().collect::<String>(), "y ]] b");
+        assert_eq!(line.fore[3], (2, 2, 2, 255));
+        assert_eq!(line.fore[4], (1, 1, 1, 255));
+        assert_eq!(line.back[0], DEFAULT_BACK);
+        assert_eq!(render_line(&scanner_data, &theme, 1).chars.len(), 8);
+        assert!(render_line(&scanner_data, &theme, 4).chars.is_empty());
+    }
+
+    #[test]
+    fn token_rules() {
+        use crate::Cursor;
+        let color = |cursor: &mut Cursor| {
+            cursor.eat("#").then_some(())?;
+            (cursor.advance_while(|c| c.is_ascii_hexdigit()) == 6)
+                .then(|| TokenType::StringLiteral(cursor.text().to_owned()))
+        };
+        let config: ScannerConfig = ScannerConfig {
+            keywords: &["end"],
+            symbols: &["#", "="],
+            ..ScannerConfig::DEFAULT
+        };
+        let config = config
+            .compile()
+            .with_rule(color)
+            .with_fallback_rule(|cursor: &mut Cursor| {
+                cursor.eat("@").then(|| TokenType::Symbol("at".to_owned()))
+            });
+        let mut scanner_data = ScannerData::default();
+        Scanner::default()
+            .run("c = #ff00aa # @end", &config, &mut scanner_data)
+            .unwrap();
+        assert_eq!(
+            scanner_data.token_types,
+            vec![
+                TokenType::Identifier("c".to_owned()),
+                TokenType::Symbol("=".to_owned()),
+                TokenType::StringLiteral("#ff00aa".to_owned()),
+                TokenType::Symbol("#".to_owned()),
+                TokenType::Symbol("at".to_owned()),
+                TokenType::Keyword("end".to_owned()),
+            ]
+        );
+        assert_eq!(scanner_data.token_len_chars(2), 7);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn regex_rules() {
+        use crate::regex::Regex;
+        let regex = Regex::new(r"[0-9a-f]{8}(-[0-9a-f]{4}){3}-[0-9a-f]{12}").unwrap();
+        assert_eq!(regex.match_len("123e4567-e89b-12d3-a456-426614174000 x"), Some(36));
+        assert_eq!(regex.match_len("123e4567-e89b"), None);
+        assert_eq!(Regex::new(r"a+?").unwrap().match_len("aaa"), Some(1));
+        assert_eq!(Regex::new(r"(ab|a)*c").unwrap().match_len("ababac"), Some(6));
+        assert_eq!(Regex::new(r"\d+\.\d*").unwrap().match_len("12.5e"), Some(4));
+        assert_eq!(Regex::new(r"[^\s]+").unwrap().match_len("été x"), Some(5));
+        assert!(Regex::new("a{2").is_err());
+        assert!(Regex::new("(a").is_err());
+        assert!(Regex::new("*a").is_err());
+        // long inputs must not overflow the stack
+        let long = format!("x{} y", "a".repeat(200_000));
+        assert_eq!(Regex::new(r"x\w+").unwrap().match_len(&long), Some(200_001));
+        assert_eq!(Regex::new(r"x.*?y").unwrap().match_len(&long), Some(200_003));
+        let pairs = "ab".repeat(100_000);
+        assert_eq!(Regex::new(r"(ab)*").unwrap().match_len(&pairs), Some(200_000));
+        let nested = format!("{}c", "ab".repeat(300)).repeat(300);
+        assert_eq!(Regex::new(r"((ab)*c)*").unwrap().match_len(&nested), Some(nested.len()));
+        // nested quantifiers take a linear time instead of an exponential one
+        let a = "a".repeat(100_000);
+        assert_eq!(Regex::new(r"(a|a)*b").unwrap().match_len(&a), None);
+        assert_eq!(Regex::new(r"(a*)*b").unwrap().match_len(&a), None);
+        assert_eq!(Regex::new(r"(a|a)*").unwrap().match_len(&a), Some(100_000));
+        assert_eq!(Regex::new(r"(a|ab)(c|bcd)").unwrap().match_len("abcd"), Some(4));
+        assert_eq!(Regex::new(r"a{2,3}?").unwrap().match_len("aaaa"), Some(2));
+        assert!(Regex::new(r"(a{100}){100}").is_err());
+        let config = presets::LUA
+            .compile()
+            .with_regex_rule("word", "x\\w+")
+            .unwrap();
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(&long, &config, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_kinds[0], TokenKind::custom("word"));
+        let config = presets::LUA
+            .compile()
+            .with_regex_rule("color", "#[0-9a-fA-F]{6}")
+            .unwrap();
+        let mut scanner_data = ScannerData::default();
+        Scanner::default()
+            .run("c = #ff00aa # 1", &config, &mut scanner_data)
+            .unwrap();
+        assert_eq!(scanner_data.token_types[2], TokenType::Custom("color", "#ff00aa".to_owned()));
+        assert_eq!(scanner_data.token_kinds[2], TokenKind::custom("color"));
+        assert_eq!(scanner_data.token_types[3], TokenType::Symbol("#".to_owned()));
+    }
+
+    #[test]
+    fn custom_tokens() {
+        use crate::Cursor;
+        let lifetime = |cursor: &mut Cursor| {
+            cursor.eat("'").then_some(())?;
+            (cursor.advance_while(char::is_alphanumeric) > 0)
+                .then(|| TokenType::Custom("lifetime", cursor.text().to_owned()))
+        };
+        let config = presets::RUST.compile().with_rule(lifetime);
+        let mut scanner_data = ScannerData::default();
+        Scanner::default()
+            .run("&'a str", &config, &mut scanner_data)
+            .unwrap();
+        assert_eq!(scanner_data.token_kinds[1], TokenKind::custom("lifetime"));
+        assert_eq!(TokenKind::custom("lifetime").class(), Some("lifetime"));
+        #[cfg(feature = "json")]
+        {
+            let json = scanner_data.to_json();
+            let copy = ScannerData::from_json(&json, "&'a str").unwrap();
+            assert_eq!(copy.token_types, scanner_data.token_types);
+            // unknown classes are rejected instead of being allocated forever
+            let unknown = json.replace("Custom:lifetime", "Custom:unknown_class");
+            assert!(ScannerData::from_json(&unknown, "&'a str").is_none());
+            TokenKind::register("unknown_class");
+            assert!(ScannerData::from_json(&unknown, "&'a str").is_some());
+        }
+        assert_eq!(
+            crate::emit::html::render(&scanner_data),
+            "&amp;<span class=\"lifetime\">&#39;a</span> str"
+        );
+        #[cfg(feature = "config")]
+        {
+            use crate::emit::theme::{Color, Theme};
+            let theme = Theme::from_toml("[styles.custom]\nlifetime = { fg = \"#e5c07b\" }").unwrap();
+            assert_eq!(
+                theme.style(Some(TokenKind::custom("lifetime")), "'a").fg,
+                Some(Color::new(0xe5, 0xc0, 0x7b))
+            );
+        }
+    }
+
+    #[test]
+    fn embedded_languages() {
+        let markdown: ScannerConfig = ScannerConfig {
+            keywords: &[],
+            symbols: &["#"],
+            ..ScannerConfig::DEFAULT
+        };
+        let config = markdown
+            .compile()
+            .with_embedded("lua", "```lua", "```", presets::LUA.compile());
+        let source = "# title\n```lua\nlocal s = \"```\"\n```\nlocal";
+        let mut scanner_data = ScannerData::default();
+        Scanner::default()
+            .run(source, &config, &mut scanner_data)
+            .unwrap();
+        assert_eq!(
+            scanner_data.token_kinds,
+            vec![
+                TokenKind::Symbol,
+                TokenKind::Identifier,
+                TokenKind::Symbol,
+                TokenKind::Keyword,
+                TokenKind::Identifier,
+                TokenKind::Symbol,
+                TokenKind::StringLiteral,
+                TokenKind::Symbol,
+                TokenKind::Identifier,
+            ]
+        );
+        assert_eq!(
+            scanner_data.token_languages,
+            vec![None, None, None, Some("lua"), Some("lua"), Some("lua"), Some("lua"), None, None]
+        );
+        #[cfg(feature = "json")]
+        {
+            let copy = ScannerData::from_json(&scanner_data.to_json(), source).unwrap();
+            assert_eq!(copy.token_languages, scanner_data.token_languages);
+        }
+        // resume a scan inside the embedded region
+        let mut tokens = Scanner::default().tokens(source, &config);
+        tokens.nth(3);
+        let state = tokens.state();
+        #[cfg(feature = "json")]
+        {
+            let value = crate::value::Value::from_json(&state.to_json()).unwrap();
+            assert_eq!(crate::ScannerState::from_value(&value).unwrap(), state);
+        }
+        let rest = Scanner::default().tokens(source, &config).with_state(&state);
+        assert_eq!(rest.count(), 5);
+    }
+
+    #[test]
+    fn token_cursor() {
+        use crate::TokenCursor;
+        let source = "local x -- comment\n = f(1)";
+        let mut scanner_data = ScannerData::default();
+        Scanner::default()
+            .with_whitespace(true)
+            .run(source, &presets::LUA, &mut scanner_data)
+            .unwrap();
+        let mut cursor = TokenCursor::new(&scanner_data).with_skip(|kind| {
+            matches!(kind, TokenKind::Ignore | TokenKind::NewLine | TokenKind::Comment)
+        });
+        let start = cursor.checkpoint();
+        assert_eq!(cursor.peek_text(1), Some("x"));
+        assert_eq!(cursor.peek_kind(2), Some(TokenKind::Symbol));
+        assert!(cursor.eat_keyword("local").is_some());
+        let error = cursor.expect_symbol("=").unwrap_err();
+        assert_eq!(error.to_string(), "expected `=`, found Identifier at line 1");
+        cursor.bump();
+        assert!(cursor.expect_symbol("=").is_ok());
+        let call = cursor.checkpoint();
+        assert!(cursor.eat(TokenKind::Identifier).is_some());
+        if cursor.eat_symbol("[").is_none() {
+            // not an index, backtrack
+            cursor.rewind(call);
+        }
+        assert!(cursor.at(TokenKind::Identifier));
+        while cursor.bump().is_some() {}
+        assert!(cursor.is_at_end());
+        assert_eq!(cursor.span_since(call).range(), 22..26);
+        assert_eq!(cursor.span_since(start), Span::new(0, source.len()));
+        assert_eq!(
+            cursor.expect(TokenKind::Identifier).unwrap_err().to_string(),
+            "expected Identifier, found end of file"
+        );
+    }
+
+    #[test]
+    fn bracket_matching() {
+        use crate::brackets::{BracketError, Brackets, DEFAULT_PAIRS};
+        let source = "f(a[1], \")\") { x }";
+        let mut scanner_data = ScannerData::default();
+        Scanner::default()
+            .run(source, &presets::LUA, &mut scanner_data)
+            .unwrap();
+        let brackets = Brackets::new(&scanner_data, DEFAULT_PAIRS);
+        assert!(brackets.is_balanced());
+        assert_eq!(brackets.matching(1), Some(8));
+        assert_eq!(brackets.matching(3), Some(5));
+        assert_eq!(brackets.matching(0), None);
+        assert_eq!(brackets.enclosing(&scanner_data, 4), Some((3, Some(5))));
+        assert_eq!(brackets.enclosing(&scanner_data, 7), Some((1, Some(8))));
+        assert_eq!(brackets.enclosing(&scanner_data, 0), None);
+        let source = ") ( [ }";
+        Scanner::default()
+            .run(source, &presets::LUA, &mut scanner_data)
+            .unwrap();
+        let brackets = Brackets::new(&scanner_data, DEFAULT_PAIRS);
+        assert_eq!(
+            brackets.errors(),
+            &[
+                BracketError::Unopened { index: 0, span: Span::new(0, 1) },
+                BracketError::Unclosed { index: 1, span: Span::new(2, 1) },
+                BracketError::Mismatched { open: 2, close: 3, span: Span::new(6, 1) },
+            ]
+        );
+        assert_eq!(brackets.enclosing(&scanner_data, 7), Some((1, None)));
+    }
+
+    #[test]
+    fn indent_hints() {
+        use crate::indent;
+        let source = "function f(a)\n  if a then\n    g({\n    })\n  elseif b then\n  else\n  end\nend\n-- {";
+        let mut scanner_data = ScannerData::default();
+        Scanner::default()
+            .run(source, &presets::LUA, &mut scanner_data)
+            .unwrap();
+        let levels: Vec<_> = (1..=9)
+            .map(|line| indent::LUA.line_level(&scanner_data, line))
+            .collect();
+        assert_eq!(levels, vec![0, 1, 2, 2, 1, 1, 1, 0, 0]);
+        let deltas: Vec<_> = (1..=8)
+            .map(|line| indent::LUA.indent_delta(&scanner_data, line))
+            .collect();
+        assert_eq!(deltas, vec![1, 1, 2, 0, 1, 1, 0, 0]);
+    }
+
+    #[test]
+    fn input_completeness() {
+        use crate::indent::{self, Completeness};
+        use crate::IncompleteKind;
+        let check = |source| indent::LUA.is_input_complete(source, &presets::LUA);
+        assert_eq!(check("print(1)"), Completeness::Complete);
+        assert_eq!(check("if a then b() else c() end"), Completeness::Complete);
+        assert_eq!(check("function f()\n  return 1"), Completeness::Incomplete(IncompleteKind::Block));
+        assert_eq!(check("t = {1, 2,"), Completeness::Incomplete(IncompleteKind::Bracket));
+        assert_eq!(check("s = [[text"), Completeness::Incomplete(IncompleteKind::String));
+        assert_eq!(check("--[[ comment"), Completeness::Incomplete(IncompleteKind::Comment));
+        assert_eq!(check("print(1))"), Completeness::Invalid);
+        assert_eq!(check("a = 1 end"), Completeness::Invalid);
+        assert_eq!(check("a = 1 $"), Completeness::Invalid);
+    }
+
+    #[test]
+    fn token_diff() {
+        use crate::diff::{diff, TokenEdit};
+        let scan = |source| {
+            let mut scanner_data = ScannerData::default();
+            Scanner::default()
+                .run(source, &presets::LUA, &mut scanner_data)
+                .unwrap();
+            scanner_data
+        };
+        let old = scan("local a = b + c");
+        assert_eq!(diff(&old, &old), vec![]);
+        assert_eq!(
+            diff(&old, &scan("local a = b * c")),
+            vec![TokenEdit::Replace { old: 4..5, new: 4..5 }]
+        );
+        assert_eq!(
+            diff(&old, &scan("local x, a = b")),
+            vec![
+                TokenEdit::Insert { old: 1, new: 1..3 },
+                TokenEdit::Delete { old: 4..6, new: 6 },
+            ]
+        );
+        assert_eq!(
+            diff(&old, &scan("")),
+            vec![TokenEdit::Delete { old: 0..6, new: 0 }]
+        );
+        assert_eq!(
+            diff(&scan(""), &old),
+            vec![TokenEdit::Insert { old: 0, new: 0..6 }]
+        );
+        // completely different scans
+        let old_source: String = (0..2000).map(|i| format!("a{} ", i)).collect();
+        let new_source: String = (0..2000).map(|i| format!("b{} ", i)).collect();
+        assert_eq!(
+            diff(&scan(&old_source), &scan(&new_source)),
+            vec![TokenEdit::Replace { old: 0..2000, new: 0..2000 }]
+        );
+    }
+
+    #[test]
+    fn comment_stripping() {
+        use crate::minify::{minify, strip_comments};
+        let source = "-- header\nlocal s = \"-- not a comment\" -- trailing\nlocal x = 1 - -y--[[ block ]]+ 2\n";
+        let mut scanner_data = ScannerData::default();
+        Scanner::default()
+            .run(source, &presets::LUA, &mut scanner_data)
+            .unwrap();
+        assert_eq!(
+            strip_comments(&scanner_data),
+            "local s = \"-- not a comment\"\nlocal x = 1 - -y + 2\n"
+        );
+        assert_eq!(
+            minify(&scanner_data, &presets::LUA, false),
+            "local s=\"-- not a comment\" local x=1- -y+2"
+        );
+        assert_eq!(
+            minify(&scanner_data, &presets::LUA, true),
+            "local s=\"-- not a comment\"\nlocal x=1- -y+2"
+        );
+        // the minified code is scanned to the same tokens
+        let sources = [
+            (&presets::PYTHON, "c = a @ b\nx = 1\n@dec\ndef f(): pass", "c=a@b x=1\n@dec def f():pass"),
+            (&presets::RUST, "let x = 1. 0x1F;", "let x=1 .0x1F;"),
+            (&presets::C, "/*c*/  #x\nint y = a - -b;", "/*c*/  #x int y=a- -b;"),
+            (&presets::LUA, "x = 1 - -y -- c\nz = 'a' .. 1 .. 2", "x=1- -y z='a'..1 ..2"),
+        ];
+        let tokens = |data: &ScannerData| -> Vec<(TokenKind, String)> {
+            data.iter()
+                .filter(|token| token.kind != TokenKind::Comment)
+                .map(|token| (token.kind, data.source[token.span.range()].to_owned()))
+                .collect()
+        };
+        for (config, source, expected) in sources {
+            Scanner::default().run(source, config, &mut scanner_data).unwrap();
+            let minified = minify(&scanner_data, config, false);
+            assert_eq!(minified, expected);
+            let mut minified_data = ScannerData::default();
+            Scanner::default().run(&minified, config, &mut minified_data).unwrap();
+            assert_eq!(tokens(&minified_data), tokens(&scanner_data));
+        }
+    }
+
+    #[test]
+    fn lossless_reconstruction() {
+        let sources = [
+            (&presets::LUA, "-- c\nlocal s = [[long\nstring]] .. \"a\\\"b\" --[[ x\n]]\r\n\tx = 0x1F + 1e3\n\n"),
+            (&presets::C, "#include <a.h>\n  /* c */ int x = 'a'; // end\n#define X \\\n  1\nchar *s = \"\\n\";"),
+            (&presets::RUST, "fn main() { let s = r#\"raw \"q\" \"#; let é = 1_000u32; } /* a /* nested */ b */"),
+            (&presets::PYTHON, "def f(x):\n    return f\"{x + 1} é\"  # done\n"),
+            (&presets::JAVASCRIPT, "let s = `a ${b ? `c` : d} e`; // x"),
+            (&presets::JSON, " {\"a\": [1, -2.5e3, true, null]} "),
+        ];
+        for (config, source) in sources {
+            let mut scanner_data = ScannerData::default();
+            Scanner::default()
+                .with_whitespace(true)
+                .run(source, config, &mut scanner_data)
+                .unwrap();
+            assert_eq!(scanner_data.reconstruct(), source);
+            Scanner::default()
+                .with_trivia(true)
+                .run(source, config, &mut scanner_data)
+                .unwrap();
+            assert_eq!(scanner_data.reconstruct(), source);
+        }
+    }
+
+    #[test]
+    fn source_metrics() {
+        use crate::metrics::Metrics;
+        let source = "-- header\n\nlocal name = [[a\nb]] -- trailing\n--[[ block\ncomment ]]\nprint(name)\n";
+        let mut scanner_data = ScannerData::default();
+        Scanner::default()
+            .run(source, &presets::LUA, &mut scanner_data)
+            .unwrap();
+        let metrics = Metrics::new(&scanner_data);
+        assert_eq!(metrics.lines, 7);
+        assert_eq!(metrics.code_lines, 3);
+        assert_eq!(metrics.comment_lines, 3);
+        assert_eq!(metrics.blank_lines, 1);
+        assert_eq!(metrics.token_count(TokenKind::Identifier), 3);
+        assert_eq!(metrics.token_count(TokenKind::Comment), 3);
+        assert_eq!(metrics.token_count(TokenKind::NumberLiteral), 0);
+        assert_eq!(metrics.average_identifier_len, 13.0 / 3.0);
+        assert_eq!(metrics.comment_density, 0.5);
+        #[cfg(feature = "json")]
+        {
+            assert!(metrics.to_json().starts_with(
+                "{\"lines\":7,\"code_lines\":3,\"comment_lines\":3,\"blank_lines\":1,\"token_counts\":{\"Comment\":3,"
+            ));
+        }
+    }
+
+    #[test]
+    fn task_markers() {
+        use crate::tasks::{find_tasks, Task, DEFAULT_MARKERS};
+        let source = "// TODO: check bounds\nint x; /* FIXME overflow!\n HACK(jice) */\nchar *s = \"TODO\"; // TODOS";
+        let mut scanner_data = ScannerData::default();
+        Scanner::default()
+            .run(source, &presets::C, &mut scanner_data)
+            .unwrap();
+        assert_eq!(
+            find_tasks(&scanner_data, DEFAULT_MARKERS),
+            vec![
+                Task { marker: "TODO", text: "check bounds", span: Span::new(3, 4), line: 1 },
+                Task { marker: "FIXME", text: "overflow!", span: Span::new(32, 5), line: 2 },
+                Task { marker: "HACK", text: "(jice)", span: Span::new(49, 4), line: 3 },
+            ]
+        );
+    }
+
+    #[test]
+    fn unicode_security() {
+        use crate::security::{find_unicode_issues, UnicodeIssue};
+        let source = "local ассess = 1 -- \u{202E}} \u{2066}\nlocal привет = \"a\u{200B}b\"";
+        let mut scanner_data = ScannerData::default();
+        let config = OwnedScannerConfig {
+            identifier_start: crate::is_unicode_alpha,
+            identifier_char: crate::is_unicode_alphanum,
+            ..OwnedScannerConfig::from(&presets::LUA)
+        };
+        Scanner::default().run(source, &config, &mut scanner_data).unwrap();
+        let warnings = find_unicode_issues(&scanner_data);
+        let issues: Vec<_> = warnings.iter().map(|w| (w.issue.clone(), w.span, w.line)).collect();
+        assert_eq!(issues, vec![
+            (UnicodeIssue::Confusable { skeleton: "access".to_string() }, Span::new(6, 9), 1),
+            (UnicodeIssue::BidiControl('\u{202E}'), Span::new(23, 3), 1),
+            (UnicodeIssue::BidiControl('\u{2066}'), Span::new(28, 3), 1),
+            (UnicodeIssue::Invisible('\u{200B}'), Span::new(55, 3), 2),
+        ]);
+        assert_eq!(warnings[0].issue.to_string(), "identifier confusable with 'access'");
+    }
+
+    #[test]
+    fn token_visitor() {
+        use crate::{TokenVisitor, VisitedToken};
+        #[derive(Default)]
+        struct Collector {
+            keywords: usize,
+            strings: Vec<String>,
+            others: usize,
+        }
+        impl TokenVisitor for Collector {
+            fn visit_token(&mut self, _token: &VisitedToken<'_>) {
+                self.others += 1;
+            }
+            fn visit_keyword(&mut self, _token: &VisitedToken<'_>) {
+                self.keywords += 1;
+            }
+            fn visit_string(&mut self, token: &VisitedToken<'_>) {
+                if let Some(TokenType::StringLiteral(value)) = token.value {
+                    self.strings.push(value.clone());
+                }
+            }
+        }
+        let source = "local a = \"x\" .. \"y\" -- done";
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(source, &LUA_CONFIG, &mut scanner_data).unwrap();
+        let mut collector = Collector::default();
+        scanner_data.accept(&mut collector);
+        assert_eq!(collector.keywords, 1);
+        assert_eq!(collector.strings, vec!["x".to_string(), "y".to_string()]);
+        // identifier, symbols and comment
+        assert_eq!(collector.others, 4);
+    }
+
+    #[test]
+    fn lints() {
+        use crate::lint::{LintRegistry, LongLines, Severity};
+        let source = "local é = 1  \n \tlocal s = \"a  \n\t b\"\nlocal long = 12345678";
+        let config = OwnedScannerConfig {
+            identifier_start: crate::is_unicode_alpha,
+            identifier_char: crate::is_unicode_alphanum,
+            ..OwnedScannerConfig::from(&LUA_CONFIG)
+        };
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(source, &config, &mut scanner_data).unwrap();
+        let mut registry = LintRegistry::builtin();
+        registry.register(LongLines { max: 20 });
+        assert!(registry.set_severity("trailing-whitespace", Severity::Error));
+        assert!(!registry.set_severity("unknown", Severity::Error));
+        let diagnostics: Vec<_> = registry
+            .run(&scanner_data)
+            .iter()
+            .map(|d| (d.lint, d.severity, d.span, d.line))
+            .collect();
+        assert_eq!(diagnostics, vec![
+            ("non-ascii-identifier", Severity::Info, Span::new(6, 2), 1),
+            ("trailing-whitespace", Severity::Error, Span::new(12, 2), 1),
+            ("mixed-indentation", Severity::Warning, Span::new(15, 2), 2),
+            ("long-line", Severity::Warning, Span::new(37, 21), 4),
+        ]);
+        assert_eq!(
+            registry.run(&scanner_data)[3].to_string(),
+            "4: warning[long-line]: line is 21 characters long (maximum 20)"
+        );
+    }
+
+    #[test]
+    fn token_queries() {
+        let source = "local function f(a) return a end";
+        let mut scanner_data = ScannerData::default();
+        Scanner::default()
+            .run(source, &presets::LUA, &mut scanner_data)
+            .unwrap();
+        let identifiers: Vec<_> = scanner_data.find_all(TokenKind::Identifier).map(|(i, _)| i).collect();
+        assert_eq!(identifiers, vec![2, 4, 7]);
+        let a: Vec<_> = scanner_data.find_text("a").collect();
+        assert_eq!(a, vec![(4, Span::new(17, 1)), (7, Span::new(27, 1))]);
+        let range: Vec<_> = scanner_data.tokens_in_range(Span::new(7, 11)).map(|(i, _)| i).collect();
+        assert_eq!(range, vec![1, 2, 3, 4]);
+        let at: Vec<_> = scanner_data.tokens_in_range(Span::new(8, 0)).map(|(i, _)| i).collect();
+        assert_eq!(at, vec![1]);
+        assert_eq!(scanner_data.tokens_in_range(Span::new(5, 1)).count(), 0);
+    }
+
+    #[test]
+    fn token_at_position() {
+        let source = "local s = [[a\nb]]\nprint(s)";
+        let mut scanner_data = ScannerData::default();
+        Scanner::default()
+            .run(source, &presets::LUA, &mut scanner_data)
+            .unwrap();
+        assert_eq!(scanner_data.token_at(0), Some(0));
+        assert_eq!(scanner_data.token_at(4), Some(0));
+        assert_eq!(scanner_data.token_at(5), None);
+        assert_eq!(scanner_data.token_at(14), Some(3));
+        assert_eq!(scanner_data.token_at(source.len()), None);
+        assert_eq!(scanner_data.token_at_line_col(2, 1), Some(3));
+        assert_eq!(scanner_data.token_at_line_col(3, 6), Some(6));
+        assert_eq!(scanner_data.token_at_line_col(4, 0), None);
+    }
+
+    #[test]
+    fn text_sources() {
+        use crate::TextSource;
+        use std::borrow::Cow;
+        // a text split in chunks like a rope
+        struct Chunks(Vec<&'static str>);
+        impl TextSource for Chunks {
+            fn len(&self) -> usize {
+                self.0.iter().map(|chunk| chunk.len()).sum()
+            }
+            fn chunks(&self) -> impl Iterator<Item = &str> {
+                self.0.iter().copied()
+            }
+        }
+        let text = Chunks(vec!["local na", "me = \"é", "\""]);
+        let mut buffer = String::new();
+        let mut scanner_data = ScannerData::default();
+        Scanner::default()
+            .run_text(&text, &presets::LUA, &mut buffer, &mut scanner_data)
+            .unwrap();
+        assert_eq!(scanner_data.len(), 4);
+        assert_eq!(scanner_data.get(1).unwrap().text_in(&text), "name");
+        assert!(matches!(scanner_data.get(0).unwrap().text_in(&text), Cow::Borrowed("local")));
+        assert_eq!(text.slice(6..16), "name = \"é");
+        assert_eq!(text.slice(16..16), "");
+        let source = "local name = \"é\"";
+        let mut unused = String::new();
+        let mut direct = ScannerData::default();
+        Scanner::default()
+            .run_text(source, &presets::LUA, &mut unused, &mut direct)
+            .unwrap();
+        assert_eq!(direct.token_spans, scanner_data.token_spans);
+    }
+
+    #[test]
+    fn config_validation() {
+        use crate::ConfigIssue;
+        for preset in [&presets::LUA, &presets::C, &presets::RUST, &presets::PYTHON, &presets::JAVASCRIPT, &presets::JSON] {
+            assert_eq!(preset.validate(), vec![]);
+        }
+        let config: ScannerConfig = ScannerConfig {
+            keywords: &["if", "not"],
+            symbols: &["=", "==", "-->", "not", ""],
+            single_line_cmt: Some("--"),
+            ..ScannerConfig::DEFAULT
+        };
+        let issues = config.validate();
+        assert_eq!(
+            issues,
+            vec![
+                ConfigIssue::EmptyEntry { field: "symbols" },
+                ConfigIssue::CommentCollision { symbol: "-->".to_owned(), marker: "--".to_owned() },
+                ConfigIssue::KeywordIsSymbol("not".to_owned()),
+            ]
+        );
+        assert!(!issues[2].is_error());
+        assert_eq!(
+            issues[1].to_string(),
+            "symbol '-->' is never matched because it starts a comment ('--')"
+        );
+    }
+
+    #[test]
+    fn bool_and_nil_literals() {
+        let mut scanner_data = ScannerData::default();
+        Scanner::default()
+            .run("local t = { true, false, nil, trueish }", &presets::LUA, &mut scanner_data)
+            .unwrap();
+        assert_eq!(
+            scanner_data.token_types[4..10],
+            [
+                TokenType::BoolLiteral(true),
+                TokenType::Symbol(",".to_owned()),
+                TokenType::BoolLiteral(false),
+                TokenType::Symbol(",".to_owned()),
+                TokenType::NilLiteral,
+                TokenType::Symbol(",".to_owned()),
+            ]
+        );
+        assert_eq!(scanner_data.token_kinds[10], TokenKind::Identifier);
+        Scanner::default()
+            .run("[True, None]", &presets::PYTHON, &mut scanner_data)
+            .unwrap();
+        assert_eq!(scanner_data.token_types[1], TokenType::BoolLiteral(true));
+        assert_eq!(scanner_data.token_types[3], TokenType::NilLiteral);
+        #[cfg(feature = "json")]
+        {
+            let copy = ScannerData::from_json(&scanner_data.to_json(), "[True, None]").unwrap();
+            assert_eq!(copy.token_types, scanner_data.token_types);
+        }
+    }
+
+    #[test]
+    fn js_regex_literals() {
+        let source = "let r = /[/]+\\//gi; x = a / b / c; f(/a/)";
+        let mut scanner_data = ScannerData::default();
+        Scanner::default()
+            .run(source, &presets::JAVASCRIPT, &mut scanner_data)
+            .unwrap();
+        assert_eq!(
+            scanner_data.token_types[3],
+            TokenType::RegexLiteral("[/]+\\/".to_owned(), "gi".to_owned())
+        );
+        assert_eq!(scanner_data.token_text(3), "/[/]+\\//gi");
+        assert_eq!(scanner_data.find_text("/").count(), 2);
+        assert_eq!(
+            scanner_data.token_types[15],
+            TokenType::RegexLiteral("a".to_owned(), String::new())
+        );
+        #[cfg(feature = "json")]
+        {
+            let copy = ScannerData::from_json(&scanner_data.to_json(), source).unwrap();
+            assert_eq!(copy.token_types, scanner_data.token_types);
+        }
+        // disabled by default
+        let config = ScannerConfig {
+            regex_literals: false,
+            ..presets::JAVASCRIPT
+        };
+        Scanner::default().run("= /a/", &config, &mut scanner_data).unwrap();
+        assert_eq!(
+            scanner_data.token_kinds[1..],
+            [TokenKind::Symbol, TokenKind::Identifier, TokenKind::Symbol]
+        );
+    }
+
+    #[test]
+    fn attributes() {
+        let source = "#[derive(Debug, Clone)]\n#[doc = \"]\"] struct A; x # [";
+        let mut scanner_data = ScannerData::default();
+        Scanner::default()
+            .run(source, &presets::RUST, &mut scanner_data)
+            .unwrap();
+        assert_eq!(
+            scanner_data.token_types[..2],
+            [
+                TokenType::Attribute("#[derive(Debug, Clone)]".to_owned()),
+                TokenType::Attribute("#[doc = \"]\"]".to_owned()),
+            ]
+        );
+        assert_eq!(
+            scanner_data.token_kinds[5..],
+            [TokenKind::Identifier, TokenKind::Symbol, TokenKind::Symbol]
+        );
+        let source = "@app.route(\"/\", methods=(1, 2))\n@staticmethod\ndef f(): pass";
+        Scanner::default()
+            .run(source, &presets::PYTHON, &mut scanner_data)
+            .unwrap();
+        assert_eq!(scanner_data.token_text(0), "@app.route(\"/\", methods=(1, 2))");
+        assert_eq!(scanner_data.token_text(1), "@staticmethod");
+        assert_eq!(scanner_data.token_kinds[1], TokenKind::Attribute);
+        let config: ScannerConfig = ScannerConfig {
+            attribute_prefixes: &["#pragma"],
+            ..presets::C
+        };
+        Scanner::default()
+            .run("#pragma once\nint x;", &config, &mut scanner_data)
+            .unwrap();
+        assert_eq!(
+            scanner_data.token_types[0],
+            TokenType::Attribute("#pragma once".to_owned())
+        );
+        // the matrix multiplication operator
+        for source in ["c = a @b", "c = a@b", "c = (a)@b"] {
+            Scanner::default()
+                .run(source, &presets::PYTHON, &mut scanner_data)
+                .unwrap();
+            let at = scanner_data.len() - 2;
+            assert_eq!(scanner_data.token_kinds[at..], [TokenKind::Symbol, TokenKind::Identifier]);
+            assert_eq!(scanner_data.token_text(at), "@");
+        }
+        Scanner::default()
+            .run("x = 1; @dec\nclass A: pass", &presets::PYTHON, &mut scanner_data)
+            .unwrap();
+        assert_eq!(scanner_data.token_text(4), "@dec");
+    }
+
+    #[test]
+    fn rust_lifetimes() {
+        let source = "fn f<'a>(s: &'a str) { 'outer: loop { break 'outer } 'x' }";
+        let mut scanner_data = ScannerData::default();
+        Scanner::default()
+            .run(source, &presets::RUST, &mut scanner_data)
+            .unwrap();
+        assert_eq!(scanner_data.token_types[3], TokenType::Lifetime("a".to_owned()));
+        assert_eq!(scanner_data.token_text(9), "'a");
+        let lifetimes: Vec<_> = scanner_data.find_all(TokenKind::Lifetime).collect();
+        assert_eq!(lifetimes.len(), 4);
+        assert_eq!(scanner_data.token_types[20], TokenType::CharLiteral('x'));
+    }
+
+    #[test]
+    fn punctuation() {
+        let config: ScannerConfig = ScannerConfig {
+            punctuation: &["(", ")", ",", ";"],
+            ..presets::LUA
+        };
+        let mut scanner_data = ScannerData::default();
+        Scanner::default()
+            .run("f(a, b + 1) == c", &config, &mut scanner_data)
+            .unwrap();
+        assert_eq!(
+            scanner_data.token_types[1..4],
+            [
+                TokenType::Punctuation("(".to_owned()),
+                TokenType::Identifier("a".to_owned()),
+                TokenType::Punctuation(",".to_owned()),
+            ]
+        );
+        assert_eq!(scanner_data.token_types[5], TokenType::Symbol("+".to_owned()));
+        assert_eq!(scanner_data.token_kinds[8], TokenKind::Symbol);
+        assert!(scanner_data.token_kinds[1].is_symbol());
+        let brackets = crate::brackets::Brackets::new(&scanner_data, crate::brackets::DEFAULT_PAIRS);
+        assert_eq!(brackets.matching(1), Some(7));
+        let config: ScannerConfig = ScannerConfig {
+            punctuation: &["("],
+            ..ScannerConfig::DEFAULT
+        };
+        assert_eq!(
+            config.validate(),
+            vec![crate::ConfigIssue::PunctuationNotSymbol("(".to_owned())]
+        );
+    }
+
+    #[test]
+    fn quoted_identifiers() {
+        let config: ScannerConfig = ScannerConfig {
+            keywords: &["select", "from"],
+            symbols: &[","],
+            quoted_identifiers: &["\"", "`"],
+            ..ScannerConfig::DEFAULT
+        };
+        let source = "select \"first name\", `a ``b`` c`, \"x\"\"y\" from t";
+        let mut scanner_data = ScannerData::default();
+        Scanner::default()
+            .run(source, &config, &mut scanner_data)
+            .unwrap();
+        assert_eq!(
+            scanner_data.token_types[1..7],
+            [
+                TokenType::Identifier("first name".to_owned()),
+                TokenType::Symbol(",".to_owned()),
+                TokenType::Identifier("a `b` c".to_owned()),
+                TokenType::Symbol(",".to_owned()),
+                TokenType::Identifier("x\"y".to_owned()),
+                TokenType::Keyword("from".to_owned()),
+            ]
+        );
+        assert_eq!(scanner_data.token_text(5), "\"x\"\"y\"");
+        assert!(Scanner::default()
+            .run("select \"unterminated", &config, &mut scanner_data)
+            .is_err());
+    }
+
+    #[test]
+    fn raw_escapes() {
+        let config = ScannerConfig {
+            decode_escapes: false,
+            ..presets::JAVASCRIPT
+        };
+        let source = r#"a = "x\"\né"; b = `p\t${a}\``"#;
+        let mut scanner_data = ScannerData::default();
+        Scanner::default()
+            .run(source, &config, &mut scanner_data)
+            .unwrap();
+        assert_eq!(
+            scanner_data.token_types[2],
+            TokenType::StringLiteral(r#"x\"\né"#.to_owned())
+        );
+        assert_eq!(scanner_data.token_types[6], TokenType::StringPart(r"p\t".to_owned()));
+        assert_eq!(scanner_data.token_types[10], TokenType::StringPart(r"\`".to_owned()));
+        Scanner::default()
+            .run(source, &presets::JAVASCRIPT, &mut scanner_data)
+            .unwrap();
+        assert_eq!(
+            scanner_data.token_types[2],
+            TokenType::StringLiteral("x\"\né".to_owned())
+        );
+    }
+
+    #[test]
+    fn token_lengths() {
+        let mut scanner_data = ScannerData::default();
+        Scanner::default()
+            .run("s = \"à\"", &presets::LUA, &mut scanner_data)
+            .unwrap();
+        assert_eq!(scanner_data.token_len_bytes(2), 4);
+        assert_eq!(scanner_data.token_len_chars(2), 3);
+        let token = scanner_data.get(2).unwrap();
+        assert_eq!(token.len_bytes(), 4);
+        assert_eq!(token.len_chars(scanner_data.source), 3);
+        assert_eq!(scanner_data.token_types[2].len_bytes(), 4);
+        assert_eq!(scanner_data.token_types[2].len_chars(), 3);
+        assert_eq!(TokenType::CharLiteral('é').len_chars(), 3);
+        // the missing closing quote of an unterminated string counts in its length
+        let source = "é = 1\nb = \"àb";
+        let (scanner_data, _) = Scanner::default().run_lossy(source, &presets::LUA);
+        assert_eq!(scanner_data.unterminated, &[TokenIndex::new(5)]);
+        assert_eq!(scanner_data.token_starts_chars(), &[0, 2, 4, 6, 8, 10]);
+        assert_eq!(scanner_data.token_lens_chars(), &[1, 1, 1, 1, 1, 4]);
+        #[cfg(feature = "json")]
+        {
+            let copy = ScannerData::from_json(&scanner_data.to_json(), source).unwrap();
+            assert_eq!(copy.token_lens_chars(), scanner_data.token_lens_chars());
+        }
+    }
+
+    #[test]
+    fn memory_usage() {
+        let mut scanner_data = ScannerData::default();
+        Scanner::default()
+            .with_values(false)
+            .run("local a = 1", &presets::LUA, &mut scanner_data)
+            .unwrap();
+        let usage = scanner_data.memory_usage();
+        assert_eq!(usage.values, 0);
+        // kind, span, start line, end line and column
+        assert_eq!(std::mem::size_of::<TokenKind>(), 8);
+        assert_eq!(std::mem::size_of::<Span>(), 8);
+        let per_token = std::mem::size_of::<TokenKind>() + std::mem::size_of::<Span>() + 12;
+        assert!(usage.tokens >= 4 * per_token);
+        assert!(usage.tokens <= 8 * per_token);
+        assert_eq!(usage.total(), usage.tokens + usage.lines + usage.extra);
+        Scanner::default()
+            .run("local a = 1", &presets::LUA, &mut scanner_data)
+            .unwrap();
+        assert!(scanner_data.memory_usage().values >= 4 * std::mem::size_of::<TokenType>());
+    }
+
+    #[test]
+    fn scan_stats() {
+        let source = "local a = 1\nlocal b = a + 1";
+        let mut scanner_data = ScannerData::default();
+        let stats = Scanner::default()
+            .run_with_stats(source, &presets::LUA, &mut scanner_data)
+            .unwrap();
+        assert_eq!(stats.tokens, scanner_data.len());
+        assert_eq!((stats.tokens, stats.bytes, stats.lines), (10, source.len(), 2));
+        assert_eq!(stats.trivia_time, std::time::Duration::ZERO);
+        assert!(stats.total_time() >= stats.scan_time);
+        assert!(stats.tokens_per_sec() >= 0.0 && stats.bytes_per_sec() >= stats.tokens_per_sec());
+        assert!(Scanner::default()
+            .run_with_stats("local s = \"", &presets::LUA, &mut scanner_data)
+            .is_err());
+        // the phases reported to an observer
+        struct Phases(Vec<crate::ScanPhase>);
+        impl crate::ScanObserver for Phases {
+            fn phase(&mut self, phase: crate::ScanPhase, _duration: std::time::Duration) {
+                self.0.push(phase);
+            }
+        }
+        let mut phases = Phases(Vec::new());
+        Scanner::default()
+            .with_trivia(true)
+            .run_observed(source, &presets::LUA, &mut scanner_data, &mut phases)
+            .unwrap();
+        use crate::ScanPhase::*;
+        assert_eq!(phases.0, &[Compile, LineIndex, Scan, Trivia]);
+    }
+
+    #[test]
+    fn never_panics() {
+        // a deterministic fuzzer : random texts made of the characters meaningful to the presets
+        let alphabet: Vec<char> = "aZ_09.xXeEpP+-*/%=<>!&|^~?:;,()[]{}#@$\\'\"`\n\r\t r#[=]-->/**/ éà😀\u{0}"
+            .chars()
+            .collect();
+        let mut seed = 0x2545_f491_4f6c_dd1du64;
+        let mut random = move |n: usize| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % n as u64) as usize
+        };
+        // every option at once, with overlapping markers
+        let everything: ScannerConfig = ScannerConfig {
+            keywords: &["if", "r"],
+            symbols: &["-", "--", "[", "]", "=", "#", "/", "<", "<<", "$", "{", "}"],
+            punctuation: &["[", "]"],
+            true_literals: &["t"],
+            nil_literals: &["x"],
+            single_line_cmt: Some("--"),
+            multi_line_cmt_start: Some("--[["),
+            multi_line_cmt_end: Some("]]"),
+            directive_prefixes: &["#"],
+            attribute_prefixes: &["@", "#[", "#pragma"],
+            line_continuation: Some('\\'),
+            char_delimiter: Some('\''),
+            lifetimes: true,
+            raw_string_start: Some("["),
+            raw_string_end: Some("]"),
+            raw_string_level: Some('='),
+            heredoc_start: Some("<<"),
+            interpolated_string: Some('`'),
+            interpolation_start: Some("${"),
+            interpolation_end: Some("}"),
+            strings_may_span_lines: false,
+            regex_literals: true,
+            scientific_notation: true,
+            number_separator: Some('_'),
+            hex_float: true,
+            legacy_octal: true,
+            number_suffixes: &["x", "e", "p1"],
+            quoted_identifiers: &["\"", "``"],
+            string_delimiters: &["\"", "'''"],
+            string_prefixes: &["r", "b", "br"],
+            ..ScannerConfig::DEFAULT
+        };
+        let mut sources: Vec<String> = Vec::new();
+        for i in 0..3000 {
+            // a few long texts among the short ones
+            let len = if i % 300 == 0 { 2000 } else { random(24) };
+            sources.push((0..len).map(|_| alphabet[random(alphabet.len())]).collect());
+        }
+        // long runs and deep nesting
+        for pattern in ["a", " ", "-", "(", "[", "{", "--[[", "[=[", "`${", "\"", "'", "/", "\\\n", "0x", "é", "<<a\n"] {
+            sources.push(pattern.repeat(10_000 / pattern.len()));
+        }
+        for source in &sources {
+            for name in ["lua", "c", "rust", "python", "javascript", "json", ""] {
+                let config = presets::by_name(name).unwrap_or(&everything);
+                let result = std::panic::catch_unwind(|| {
+                    let mut data = ScannerData::default();
+                    let _ = Scanner::default().with_trivia(true).run(source, config, &mut data);
+                    let _ = Scanner::default().with_whitespace(true).run_lossy(source, config);
+                    let _ = Scanner::default().run_parallel(source, config, &mut data, 3);
+                    let _ = Scanner::default().tokens(source, config).count();
+                });
+                assert!(result.is_ok(), "{} panics on {:?}", name, source);
+            }
+        }
+        #[cfg(feature = "regex")]
+        {
+            let config = everything.compile().with_regex_rule("word", "a(\\w|-)*").unwrap();
+            for source in &sources {
+                let _ = Scanner::default().run_lossy(source, &config);
+            }
+        }
+        // deeply nested configuration files and saved data
+        #[cfg(any(feature = "config", feature = "json"))]
+        for nesting in ["[", "{\"a\":", "[[", "{\"token_types\":["] {
+            let text = nesting.repeat(200_000);
+            #[cfg(feature = "config")]
+            assert!(OwnedScannerConfig::from_json(&text).is_err());
+            #[cfg(feature = "json")]
+            assert!(ScannerData::from_json(&text, "").is_none());
+        }
+        #[cfg(feature = "json")]
+        {
+            // saved data not matching the source code
+            let mut data = ScannerData::default();
+            Scanner::default().run("local a", &presets::LUA, &mut data).unwrap();
+            assert!(ScannerData::from_json(&data.to_json(), "local").is_none());
+            assert!(ScannerData::from_json(&data.to_json(), "ééé a").is_none());
+        }
+    }
+
+    #[test]
+    fn suggestions() {
+        use crate::lint::{Lint, MisspelledKeywords};
+        use crate::suggest::{edit_distance, suggestions};
+        assert_eq!(edit_distance("functoin", "function"), 1);
+        assert_eq!(edit_distance("retrun", "return"), 1);
+        assert_eq!(edit_distance("whle", "while"), 1);
+        assert_eq!(edit_distance("", "end"), 3);
+        assert_eq!(suggestions("esle", LUA_CONFIG.keywords, 1), vec!["else"]);
+
+        let source = "if a != 2 then\n  locla b = 1 end";
+        let mut scanner_data = ScannerData::default();
+        let error = Scanner::default().run(source, &LUA_CONFIG, &mut scanner_data).unwrap_err();
+        assert_eq!(
+            crate::diagnostics::render_with_suggestions(&error, source, "foo.lua", LUA_CONFIG.symbols),
+            "error[E0001]: unknown token\n --> foo.lua:1:6\n  |\n1 | if a != 2 then\n  |      ^\n  \
+             = help: did you mean `==`, `~=`, `<=` or `>=`?\n"
+        );
+
+        let source = "if a == 2 then\n  locla b = 1 end";
+        Scanner::default().run(source, &LUA_CONFIG, &mut scanner_data).unwrap();
+        let mut hints = Vec::new();
+        MisspelledKeywords::new(LUA_CONFIG.keywords).check(&scanner_data, &mut |span, message| {
+            hints.push((span, message))
+        });
+        assert_eq!(hints, vec![(Span::new(17, 5), "identifier 'locla' looks like the keyword 'local'".to_owned())]);
+    }
+
+    #[test]
+    fn completion_candidates() {
+        let source = "local list, length = 1, 2\nlocal l = lis + length + le";
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(source, &LUA_CONFIG, &mut scanner_data).unwrap();
+        let cursor = source.len();
+        assert_eq!(scanner_data.completion_candidates("le", cursor), vec!["length"]);
+        assert_eq!(scanner_data.completion_candidates("l", cursor), vec!["l", "length", "lis", "list", "local"]);
+        let cursor = source.find("lis ").unwrap() + 3;
+        assert_eq!(scanner_data.completion_candidates("lis", cursor), vec!["list"]);
+        assert!(scanner_data.completion_candidates("x", cursor).is_empty());
+    }
+
+    #[test]
+    fn string_prefixes() {
+        let source = r#"x = b"a\n" + rb"a\n" + f"{x}" + bad"s" + b'c'"#;
+        let mut scanner_data = ScannerData::default();
+        Scanner::default().run(source, &presets::PYTHON, &mut scanner_data).unwrap();
+        let texts: Vec<_> = (0..scanner_data.len()).map(|i| scanner_data.token_text(i)).collect();
+        assert_eq!(texts, vec![
+            "x", "=", "b\"a\\n\"", "+", "rb\"a\\n\"", "+", "f\"{x}\"", "+", "bad", "\"s\"", "+", "b'c'",
+        ]);
+        assert_eq!(scanner_data.token_types[2], TokenType::StringLiteral("a\n".to_owned()));
+        assert_eq!(scanner_data.token_types[4], TokenType::StringLiteral("a\\n".to_owned()));
+        assert_eq!(scanner_data.token_types[11], TokenType::StringLiteral("c".to_owned()));
+        assert_eq!(scanner_data.string_prefix(4), Some("rb"));
+        assert_eq!(scanner_data.string_prefix(9), None);
+        assert_eq!(scanner_data.string_prefix(8), None);
+        assert_eq!(scanner_data.string_prefix(11), Some("b"));
+        #[cfg(feature = "json")]
+        {
+            let json = scanner_data.to_json();
+            assert_eq!(ScannerData::from_json(&json, source).unwrap().token_prefixes, scanner_data.token_prefixes);
+        }
+        Scanner::default().run("f'{x}' + 'y'", &presets::PYTHON, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.string_prefix(0), Some("f"));
+        assert_eq!(scanner_data.string_prefix(2), None);
+
+        Scanner::default().run("let r = b'a' + c\"s\";", &presets::RUST, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_kinds[3], TokenKind::CharLiteral);
+        assert_eq!(scanner_data.string_prefix(3), Some("b"));
+        assert_eq!(scanner_data.string_prefix(5), Some("c"));
+        // only the configured prefixes, not any word before a string
+        Scanner::default().run("c = u8'a' + R\"(x)\";", &presets::C, &mut scanner_data).unwrap();
+        assert_eq!(scanner_data.token_kinds[2], TokenKind::CharLiteral);
+        assert_eq!(scanner_data.string_prefix(2), Some("u8"));
+        assert_eq!(scanner_data.string_prefix(5), None);
+        Scanner::default().run("x = \"a\"", &presets::C, &mut scanner_data).unwrap();
+        assert!(scanner_data.token_prefixes.is_empty());
+    }
+}
@@ -0,0 +1,87 @@
+/// Start offset of each line of a source code, to convert byte offsets
+/// to line/column positions without scanning the source again.
+/// Lines start at 1 like ScannerData::token_lines, columns are byte offsets from the line start.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineIndex {
+    /// byte offset of the first character of each line. The first line starts at 0
+    line_starts: Vec<usize>,
+    /// character offset of the first character of each line
+    char_starts: Vec<usize>,
+    /// length of the source code in bytes
+    len: usize,
+}
+
+impl Default for LineIndex {
+    fn default() -> Self {
+        Self {
+            line_starts: vec![0],
+            char_starts: vec![0],
+            len: 0,
+        }
+    }
+}
+
+impl LineIndex {
+    pub fn new(source: &str) -> Self {
+        let mut line_starts = vec![0];
+        let mut char_starts = vec![0];
+        let mut chars = 0;
+        for (i, b) in source.bytes().enumerate() {
+            // count the bytes which are not UTF-8 continuation bytes
+            chars += usize::from((b as i8) >= -0x40);
+            if b == b'\n' {
+                line_starts.push(i + 1);
+                char_starts.push(chars);
+            }
+        }
+        Self {
+            line_starts,
+            char_starts,
+            len: source.len(),
+        }
+    }
+    /// byte offset of the first character of each line, the first line at index 0
+    pub fn line_starts(&self) -> &[usize] {
+        &self.line_starts
+    }
+    /// number of lines. A source code ending with a new line has an empty last line
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+    /// byte offset of the start of a line, or None if the line does not exist
+    pub fn line_start(&self, line: usize) -> Option<usize> {
+        self.line_starts.get(line.checked_sub(1)?).copied()
+    }
+    /// the bytes of a line without its line ending, or None if the line does not exist
+    pub fn line_range(&self, source: &str, line: usize) -> Option<std::ops::Range<usize>> {
+        let start = self.line_start(line)?;
+        let end = match self.line_start(line + 1) {
+            Some(next) if next >= 2 && source.as_bytes().get(next - 2) == Some(&b'\r') => next - 2,
+            Some(next) => next - 1,
+            None => self.len,
+        };
+        Some(start..end.max(start))
+    }
+    /// the line containing a byte offset, and the column in bytes from the line start
+    pub fn offset_to_line_col(&self, offset: usize) -> (usize, usize) {
+        let line = self.line_starts.partition_point(|start| *start <= offset);
+        (line, offset - self.line_starts[line - 1])
+    }
+    /// the position in characters of a byte offset of the source code
+    pub fn char_offset(&self, source: &str, offset: usize) -> usize {
+        let line = self.line_starts.partition_point(|start| *start <= offset);
+        let line_start = self.line_starts[line - 1];
+        self.char_starts[line - 1] + source.get(line_start..offset).map_or(0, |text| text.chars().count())
+    }
+    /// memory used by the line starts, in bytes
+    pub(crate) fn memory_usage(&self) -> usize {
+        (self.line_starts.capacity() + self.char_starts.capacity()) * std::mem::size_of::<usize>()
+    }
+    /// the byte offset of a line/column position,
+    /// or None if the line does not exist or the column is after the line end
+    pub fn line_col_to_offset(&self, line: usize, col: usize) -> Option<usize> {
+        let start = self.line_start(line)?;
+        let end = self.line_start(line + 1).unwrap_or(self.len + 1);
+        (start + col < end).then_some(start + col)
+    }
+}
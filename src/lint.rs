@@ -0,0 +1,275 @@
+//! Lints checking the style of a scanned source code, like trailing whitespace or long lines.
+//! A LintRegistry runs a set of lints and collects their diagnostics
+use crate::{suggest, ScannerData, Span, TokenKind};
+
+/// How serious a lint diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A problem found by a lint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintDiagnostic {
+    /// name of the lint, like "trailing-whitespace"
+    pub lint: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    /// position of the problem in the source code in bytes
+    pub span: Span,
+    /// line of the problem, in the position convention of the ScannerData
+    pub line: usize,
+}
+
+impl std::fmt::Display for LintDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}[{}]: {}", self.line, self.severity, self.lint, self.message)
+    }
+}
+
+/// A check of the tokens of a source code
+pub trait Lint {
+    /// unique name of the lint, like "trailing-whitespace"
+    fn name(&self) -> &'static str;
+    /// severity of the diagnostics, unless changed with LintRegistry::set_severity
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+    /// call report with the position and the message of each problem
+    fn check(&self, data: &ScannerData, report: &mut dyn FnMut(Span, String));
+}
+
+/// A set of lints with their severity
+#[derive(Default)]
+pub struct LintRegistry {
+    lints: Vec<(Box<dyn Lint>, Severity)>,
+}
+
+impl LintRegistry {
+    /// an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// the built-in lints : MixedIndentation, TrailingWhitespace,
+    /// LongLines with the default maximum and NonAsciiIdentifiers
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+        registry
+            .register(MixedIndentation)
+            .register(TrailingWhitespace)
+            .register(LongLines::default())
+            .register(NonAsciiIdentifiers);
+        registry
+    }
+    /// add a lint with its default severity. A lint with the same name is replaced
+    pub fn register(&mut self, lint: impl Lint + 'static) -> &mut Self {
+        self.remove(lint.name());
+        let severity = lint.default_severity();
+        self.lints.push((Box::new(lint), severity));
+        self
+    }
+    /// remove a lint. Returns false if there is no lint with this name
+    pub fn remove(&mut self, name: &str) -> bool {
+        let len = self.lints.len();
+        self.lints.retain(|(lint, _)| lint.name() != name);
+        self.lints.len() != len
+    }
+    /// change the severity of the diagnostics of a lint.
+    /// Returns false if there is no lint with this name
+    pub fn set_severity(&mut self, name: &str, severity: Severity) -> bool {
+        match self.lints.iter_mut().find(|(lint, _)| lint.name() == name) {
+            Some((_, lint_severity)) => {
+                *lint_severity = severity;
+                true
+            }
+            None => false,
+        }
+    }
+    /// the names of the registered lints
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.lints.iter().map(|(lint, _)| lint.name())
+    }
+    /// run all the lints, returning their diagnostics in the order of the source code
+    pub fn run(&self, data: &ScannerData) -> Vec<LintDiagnostic> {
+        let mut diagnostics = Vec::new();
+        for (lint, severity) in &self.lints {
+            lint.check(data, &mut |span, message| {
+                diagnostics.push(LintDiagnostic {
+                    lint: lint.name(),
+                    severity: *severity,
+                    message,
+                    span,
+                    line: data.offset_line(span.start()),
+                })
+            });
+        }
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span.start());
+        diagnostics
+    }
+}
+
+/// Lines indented with both tabulations and spaces
+pub struct MixedIndentation;
+
+impl Lint for MixedIndentation {
+    fn name(&self) -> &'static str {
+        "mixed-indentation"
+    }
+    fn check(&self, data: &ScannerData, report: &mut dyn FnMut(Span, String)) {
+        for (start, text) in code_lines(data) {
+            let indent_len = text.len() - text.trim_start_matches([' ', '\t']).len();
+            let indent = &text[..indent_len];
+            if indent.contains(' ') && indent.contains('\t') {
+                report(
+                    Span::new(start, indent_len),
+                    "indentation mixes tabulations and spaces".to_owned(),
+                );
+            }
+        }
+    }
+}
+
+/// Spaces and tabulations at the end of the lines
+pub struct TrailingWhitespace;
+
+impl Lint for TrailingWhitespace {
+    fn name(&self) -> &'static str {
+        "trailing-whitespace"
+    }
+    fn check(&self, data: &ScannerData, report: &mut dyn FnMut(Span, String)) {
+        for (start, text) in lines(data) {
+            let len = text.len() - text.trim_end_matches([' ', '\t']).len();
+            let end = start + text.len();
+            if len > 0 && !inside_string(data, end - len) {
+                report(Span::new(end - len, len), "trailing whitespace".to_owned());
+            }
+        }
+    }
+}
+
+/// Lines longer than a maximum number of characters
+pub struct LongLines {
+    pub max: usize,
+}
+
+impl Default for LongLines {
+    /// 100 characters
+    fn default() -> Self {
+        Self { max: 100 }
+    }
+}
+
+impl Lint for LongLines {
+    fn name(&self) -> &'static str {
+        "long-line"
+    }
+    fn check(&self, data: &ScannerData, report: &mut dyn FnMut(Span, String)) {
+        for (start, text) in lines(data) {
+            let len = text.chars().count();
+            if len > self.max {
+                report(
+                    Span::new(start, text.len()),
+                    format!("line is {} characters long (maximum {})", len, self.max),
+                );
+            }
+        }
+    }
+}
+
+/// Identifiers containing non-ASCII characters, which may be hard to type or to tell apart
+pub struct NonAsciiIdentifiers;
+
+impl Lint for NonAsciiIdentifiers {
+    fn name(&self) -> &'static str {
+        "non-ascii-identifier"
+    }
+    fn default_severity(&self) -> Severity {
+        Severity::Info
+    }
+    fn check(&self, data: &ScannerData, report: &mut dyn FnMut(Span, String)) {
+        for index in 0..data.len() {
+            let text = data.token_text(index);
+            if data.token_kinds[index] == TokenKind::Identifier && !text.is_ascii() {
+                report(
+                    data.token_spans[index],
+                    format!("identifier '{}' contains non-ASCII characters", text),
+                );
+            }
+        }
+    }
+}
+
+/// Identifiers looking like a misspelled keyword, like `functoin` for `function`.
+/// Not in the built-in lints since it needs the keywords of the language
+pub struct MisspelledKeywords {
+    keywords: Vec<String>,
+}
+
+impl MisspelledKeywords {
+    /// a lint for the keywords of a configuration, like `MisspelledKeywords::new(presets::LUA.keywords)`
+    pub fn new<S: AsRef<str>>(keywords: &[S]) -> Self {
+        Self {
+            keywords: keywords.iter().map(|keyword| keyword.as_ref().to_owned()).collect(),
+        }
+    }
+}
+
+impl Lint for MisspelledKeywords {
+    fn name(&self) -> &'static str {
+        "misspelled-keyword"
+    }
+    fn default_severity(&self) -> Severity {
+        Severity::Info
+    }
+    /// reports the identifiers at an edit distance of 1 (up to 5 characters) or 2 of a keyword
+    fn check(&self, data: &ScannerData, report: &mut dyn FnMut(Span, String)) {
+        for index in 0..data.len() {
+            if data.token_kinds[index] != TokenKind::Identifier {
+                continue;
+            }
+            let text = data.token_text(index);
+            let max_distance = suggest::max_distance(text.chars().count());
+            if let Some(keyword) = suggest::suggestions(text, &self.keywords, max_distance).first() {
+                report(
+                    data.token_spans[index],
+                    format!("identifier '{}' looks like the keyword '{}'", text, keyword),
+                );
+            }
+        }
+    }
+}
+
+/// the start offset and the text without line ending of each line
+fn lines<'a>(data: &'a ScannerData) -> impl Iterator<Item = (usize, &'a str)> + 'a {
+    let source = data.source;
+    (1..=data.line_index.line_count()).filter_map(move |line| {
+        let range = data.line_index.line_range(source, line)?;
+        Some((range.start, source.get(range)?))
+    })
+}
+
+/// the lines which do not start inside a multi-line string
+fn code_lines<'a>(data: &'a ScannerData) -> impl Iterator<Item = (usize, &'a str)> + 'a {
+    lines(data).filter(move |(start, _)| !inside_string(data, *start))
+}
+
+/// whether an offset is inside a string literal, after its first character
+fn inside_string(data: &ScannerData, offset: usize) -> bool {
+    data.tokens_in_range(Span::new(offset, 0)).any(|(index, span)| {
+        span.start() < offset
+            && matches!(data.token_kinds[index], TokenKind::StringLiteral | TokenKind::StringPart)
+    })
+}
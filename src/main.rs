@@ -0,0 +1,135 @@
+//! Command line scanner, enabled by the `cli` feature.
+//! `uscan [--lang <preset> | --config <file>] [--json | --highlight] <file>`
+use std::process::ExitCode;
+
+use uscan::value::Value;
+use uscan::{diagnostics, emit, presets, OwnedScannerConfig, Scanner, ScannerConfig};
+
+const USAGE: &str = "usage: uscan [--lang <preset> | --config <file>] [--json | --highlight] <file>
+  --lang <preset>   lua, c, rust, python, javascript or json. Default: from the file extension
+  --config <file>   a .json or .toml language definition
+  --json            print the tokens as a JSON array
+  --highlight       print the source code with ANSI colors";
+
+enum Output {
+    Tokens,
+    Json,
+    Highlight,
+}
+
+struct Args {
+    lang: Option<String>,
+    config: Option<String>,
+    output: Output,
+    file: String,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = std::env::args().skip(1);
+    let mut lang = None;
+    let mut config = None;
+    let mut output = Output::Tokens;
+    let mut file = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--lang" => lang = Some(args.next().ok_or("missing preset after --lang")?),
+            "--config" => config = Some(args.next().ok_or("missing file after --config")?),
+            "--json" => output = Output::Json,
+            "--highlight" => output = Output::Highlight,
+            "-h" | "--help" => return Err(String::new()),
+            _ if arg.starts_with('-') => return Err(format!("unknown option {}", arg)),
+            _ if file.is_none() => file = Some(arg),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+    Ok(Args {
+        lang,
+        config,
+        output,
+        file: file.ok_or("missing file")?,
+    })
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(message) => {
+            if !message.is_empty() {
+                eprintln!("{}", message);
+            }
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    match run(&args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(message) => {
+            eprintln!("uscan: {}", message);
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// scan the file and print the result. Returns false if there were scan errors
+fn run(args: &Args) -> Result<bool, String> {
+    let source = std::fs::read_to_string(&args.file).map_err(|e| format!("{}: {}", args.file, e))?;
+    let config: OwnedScannerConfig = match (&args.config, &args.lang) {
+        (Some(path), _) => {
+            let config =
+                OwnedScannerConfig::from_file(path).map_err(|e| format!("{}: {}", path, e))?;
+            for issue in config.validate() {
+                eprintln!("{}: warning: {}", path, issue);
+            }
+            config
+        }
+        (None, lang) => {
+            let name = lang.as_deref().or_else(|| {
+                std::path::Path::new(&args.file)
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+            });
+            let preset: &ScannerConfig = name
+                .and_then(presets::by_name)
+                .ok_or("unknown language, use --lang or --config")?;
+            preset.into()
+        }
+    };
+    let (data, errors) = Scanner::default()
+        .with_values(false)
+        .run_lossy(&source, &config);
+    match args.output {
+        Output::Tokens => {
+            for i in 0..data.len() {
+                let (line, column) = data.line_index.offset_to_line_col(data.token_spans[i].start());
+                println!(
+                    "{}:{} {:?} {:?}",
+                    line,
+                    column + 1,
+                    data.token_kinds[i],
+                    data.token_text(i)
+                );
+            }
+        }
+        Output::Json => {
+            let tokens: Vec<Value> = data
+                .iter()
+                .map(|token| {
+                    Value::Object(vec![
+                        ("kind".to_owned(), format!("{:?}", token.kind).into()),
+                        ("text".to_owned(), token.text(&source).into()),
+                        ("line".to_owned(), token.line.into()),
+                        ("start".to_owned(), token.span.start().into()),
+                        ("len".to_owned(), token.span.len().into()),
+                    ])
+                })
+                .collect();
+            println!("{}", Value::from(tokens));
+        }
+        Output::Highlight => print!("{}", emit::ansi::render(&data)),
+    }
+    for error in errors.iter() {
+        eprint!("{}", diagnostics::render(error, &source, &args.file));
+    }
+    Ok(errors.is_empty())
+}
@@ -0,0 +1,108 @@
+//! Line and token statistics of a scanned source code
+#[cfg(feature = "json")]
+use crate::scanner::kind_name;
+#[cfg(feature = "json")]
+use crate::value::Value;
+use crate::{ScannerData, TokenKind};
+
+/// Statistics about a source code, computed from its tokens.
+/// The comment lines are only detected when the comments are tokens,
+/// so the source code must be scanned with comments enabled (the default) and without trivia
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metrics {
+    /// total number of lines. A final new line does not start a new line
+    pub lines: usize,
+    /// lines containing code, possibly followed by a comment
+    pub code_lines: usize,
+    /// lines containing only comments
+    pub comment_lines: usize,
+    /// lines containing only spaces
+    pub blank_lines: usize,
+    /// number of tokens of each kind, in the order of their first appearance.
+    /// Spaces and new lines are not counted
+    pub token_counts: Vec<(TokenKind, usize)>,
+    /// average length of the identifiers, in characters
+    pub average_identifier_len: f64,
+    /// ratio of comment lines among the non blank lines, between 0 and 1
+    pub comment_density: f64,
+}
+
+impl Metrics {
+    pub fn new(data: &ScannerData) -> Self {
+        let source = data.source;
+        let final_new_line = source.is_empty() || source.ends_with('\n');
+        let lines = data.line_index.line_count() - usize::from(final_new_line);
+        // 0 for blank lines, 1 for comment lines, 2 for code lines
+        let mut line_kinds = vec![0u8; lines];
+        let mut token_counts: Vec<(TokenKind, usize)> = Vec::new();
+        let mut identifiers = (0, 0);
+        for index in 0..data.len() {
+            let kind = data.token_kinds[index];
+            if matches!(kind, TokenKind::Ignore | TokenKind::NewLine) {
+                continue;
+            }
+            match token_counts.iter_mut().find(|(k, _)| *k == kind) {
+                Some((_, count)) => *count += 1,
+                None => token_counts.push((kind, 1)),
+            }
+            if kind == TokenKind::Identifier {
+                identifiers.0 += 1;
+                identifiers.1 += data.token_len_chars(index);
+            }
+            let line_kind = if kind == TokenKind::Comment { 1 } else { 2 };
+            let span = data.token_spans[index];
+            let last_char = span.end().max(span.start() + 1) - 1;
+            // the line index counts from 1 whatever the position convention of the tokens
+            let first_line = data.line_index.offset_to_line_col(span.start()).0;
+            let last_line = data.line_index.offset_to_line_col(last_char).0;
+            for line in first_line..=last_line.min(lines) {
+                line_kinds[line - 1] = line_kinds[line - 1].max(line_kind);
+            }
+        }
+        let count = |line_kind| line_kinds.iter().filter(|k| **k == line_kind).count();
+        let (code_lines, comment_lines) = (count(2), count(1));
+        let ratio = |a: usize, b: usize| if b == 0 { 0.0 } else { a as f64 / b as f64 };
+        Self {
+            lines,
+            code_lines,
+            comment_lines,
+            blank_lines: count(0),
+            token_counts,
+            average_identifier_len: ratio(identifiers.1, identifiers.0),
+            comment_density: ratio(comment_lines, code_lines + comment_lines),
+        }
+    }
+    /// the number of tokens of a kind
+    pub fn token_count(&self, kind: TokenKind) -> usize {
+        self.token_counts
+            .iter()
+            .find(|(k, _)| *k == kind)
+            .map_or(0, |(_, count)| *count)
+    }
+    #[cfg(feature = "json")]
+    /// the metrics as a JSON-like value with the same fields as this structure.
+    /// token_counts is an object with the kind names as keys
+    pub fn to_value(&self) -> Value {
+        Value::Object(vec![
+            ("lines".to_owned(), self.lines.into()),
+            ("code_lines".to_owned(), self.code_lines.into()),
+            ("comment_lines".to_owned(), self.comment_lines.into()),
+            ("blank_lines".to_owned(), self.blank_lines.into()),
+            (
+                "token_counts".to_owned(),
+                Value::Object(
+                    self.token_counts
+                        .iter()
+                        .map(|(kind, count)| (kind_name(*kind), Value::from(*count)))
+                        .collect(),
+                ),
+            ),
+            ("average_identifier_len".to_owned(), self.average_identifier_len.into()),
+            ("comment_density".to_owned(), self.comment_density.into()),
+        ])
+    }
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> String {
+        self.to_value().to_string()
+    }
+}
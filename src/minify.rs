@@ -0,0 +1,160 @@
+//! Writing a scanned source code back without its comments and redundant spaces.
+//! Only the token spans are used, so the comment-like text inside strings is kept
+use crate::{Compile, Scanner, ScannerData, Span, TokenKind};
+
+/// the source code without its comments. The comment tokens must be in the data,
+/// so the source code must be scanned with comments enabled (the default) and without trivia.
+/// The lines containing only a comment are removed, the other lines keep their layout
+pub fn strip_comments(data: &ScannerData) -> String {
+    let source = data.source;
+    let mut out = String::with_capacity(source.len());
+    let mut pos = 0;
+    for (kind, span) in data.token_kinds.iter().zip(&data.token_spans) {
+        if *kind != TokenKind::Comment {
+            continue;
+        }
+        out.push_str(&source[pos..span.start()]);
+        out.truncate(out.trim_end_matches([' ', '\t']).len());
+        pos = span.end();
+        let rest = &source[pos..];
+        if out.is_empty() || out.ends_with('\n') {
+            // the comment was alone on its line
+            if rest.starts_with("\r\n") {
+                pos += 2;
+            } else if rest.starts_with('\n') {
+                pos += 1;
+            }
+        } else if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+            // a comment between two tokens separates them
+            out.push(' ');
+        }
+    }
+    out.push_str(&source[pos..]);
+    out
+}
+
+/// the tokens of the source code without comments, separated by a single space only when
+/// the tokens would merge otherwise, like two identifiers or two operators.
+/// When keep_newlines is true, the tokens on different lines are separated by a new line,
+/// for the languages where new lines are significant.
+/// The comments and spaces are removed whether they are tokens or not.
+/// The result is scanned again with config, which must be the config used to scan data,
+/// and the text between two tokens is kept when removing it changes the tokens,
+/// like a comment before a C `#` which would start a directive otherwise
+pub fn minify<C: Compile + ?Sized>(data: &ScannerData, config: &C, keep_newlines: bool) -> String {
+    let config = config.compiled();
+    let source = data.source;
+    let tokens: Vec<(TokenKind, Span)> = data
+        .token_kinds
+        .iter()
+        .zip(&data.token_spans)
+        .filter(|(kind, _)| !matches!(kind, TokenKind::Comment | TokenKind::Ignore | TokenKind::NewLine))
+        .map(|(kind, span)| (*kind, *span))
+        .collect();
+    // the source code between a token and the previous one
+    let gap = |index: usize| {
+        let start = index.checked_sub(1).map_or(0, |previous| tokens[previous].1.end());
+        &source[start..tokens[index].1.start()]
+    };
+    let mut separators: Vec<Separator> = (0..tokens.len())
+        .map(|index| {
+            let gap = gap(index);
+            if index == 0 {
+                Separator::Nothing
+            } else if keep_newlines && gap.contains('\n') {
+                Separator::NewLine
+            } else if !gap.is_empty()
+                && needs_space(&source[tokens[index - 1].1.range()], &source[tokens[index].1.range()])
+            {
+                Separator::Space
+            } else {
+                Separator::Nothing
+            }
+        })
+        .collect();
+    loop {
+        let mut out = String::with_capacity(source.len());
+        let mut starts = Vec::with_capacity(tokens.len());
+        for (index, (separator, (_, span))) in separators.iter().zip(&tokens).enumerate() {
+            match separator {
+                Separator::Nothing => (),
+                Separator::Space => out.push(' '),
+                Separator::NewLine => out.push('\n'),
+                Separator::Gap => out.push_str(gap(index)),
+            }
+            starts.push(out.len());
+            out.push_str(&source[span.range()]);
+        }
+        let (scanned, _) = Scanner::default().with_values(false).run_lossy(&out, &*config);
+        let mut rescanned = scanned
+            .token_kinds
+            .iter()
+            .zip(&scanned.token_spans)
+            .filter(|(kind, _)| !matches!(kind, TokenKind::Comment | TokenKind::Ignore | TokenKind::NewLine))
+            .peekable();
+        let mut changed = false;
+        // the last token scanned again starting before the current one
+        let mut last: Option<(TokenKind, Span)> = None;
+        // only the first token of a run of changed tokens is fixed, the next ones may follow from it
+        let mut mismatch = false;
+        for (index, (kind, span)) in tokens.iter().enumerate() {
+            let start = starts[index];
+            while let Some((other_kind, other_span)) = rescanned.next_if(|(_, other)| other.start() <= start) {
+                last = Some((*other_kind, *other_span));
+            }
+            if last == Some((*kind, Span::new(start, span.len()))) {
+                mismatch = false;
+                continue;
+            }
+            if mismatch {
+                continue;
+            }
+            mismatch = true;
+            // a token merged with the next one is fixed by separating them
+            let merged = last.is_some_and(|(_, other)| other.end() > start + span.len());
+            let candidates = if merged { [index + 1, index] } else { [index, index + 1] };
+            for candidate in candidates {
+                if let Some(wider) = separators.get(candidate).and_then(|separator| separator.wider()) {
+                    separators[candidate] = wider;
+                    changed = true;
+                    break;
+                }
+            }
+        }
+        if !changed {
+            return out;
+        }
+    }
+}
+
+/// how a token is separated from the previous one in the minified code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Separator {
+    Nothing,
+    Space,
+    NewLine,
+    /// the original text between the tokens
+    Gap,
+}
+
+impl Separator {
+    /// the next separator to try when the tokens change with this one
+    fn wider(self) -> Option<Separator> {
+        match self {
+            Separator::Nothing => Some(Separator::Space),
+            Separator::Space | Separator::NewLine => Some(Separator::Gap),
+            Separator::Gap => None,
+        }
+    }
+}
+
+/// whether two tokens separated in the source code would be scanned differently when joined
+fn needs_space(before: &str, after: &str) -> bool {
+    let (Some(a), Some(b)) = (before.chars().last(), after.chars().next()) else {
+        return false;
+    };
+    // quotes next to a word could make a string prefix or suffix, like r"raw" or "text"sv
+    let word = |c: char| c.is_alphanumeric() || "_\"'`".contains(c) || !c.is_ascii();
+    let operator = |c: char| c.is_ascii_punctuation() && !"_()[]{},;\"'`".contains(c);
+    (word(a) && word(b)) || (operator(a) && operator(b)) || (a.is_ascii_digit() && b == '.')
+}
@@ -0,0 +1,186 @@
+//! Ready-made configurations for common languages
+// all presets end with ..ScannerConfig::DEFAULT, even when they set every field
+#![allow(clippy::needless_update)]
+use crate::{is_unicode_alpha, is_unicode_alphanum, ScannerConfig};
+
+pub const LUA: ScannerConfig = ScannerConfig {
+    keywords: &[
+        "and", "break", "do", "else", "elseif", "end", "for", "function", "goto", "if", "in",
+        "local", "not", "or", "repeat", "return", "then", "until", "while",
+    ],
+    true_literals: &["true"],
+    false_literals: &["false"],
+    nil_literals: &["nil"],
+    symbols: &[
+        "...", "..", "==", "~=", "<=", ">=", "//", "::", "<<", ">>", "+", "-", "*", "/", "%", "^",
+        "#", "&", "~", "|", "<", ">", "=", "(", ")", "{", "}", "[", "]", ";", ":", ",", ".",
+    ],
+    single_line_cmt: Some("--"),
+    multi_line_cmt_start: Some("--[["),
+    multi_line_cmt_end: Some("]]"),
+    nested_comments: false,
+    raw_string_start: Some("[["),
+    raw_string_end: Some("]]"),
+    raw_string_level: Some('='),
+    string_delimiters: &["\"", "'"],
+    strings_may_span_lines: false,
+    scientific_notation: true,
+    hex_float: true,
+    ..ScannerConfig::DEFAULT
+};
+
+pub const C: ScannerConfig = ScannerConfig {
+    keywords: &[
+        "auto", "break", "case", "char", "const", "continue", "default", "do", "double", "else",
+        "enum", "extern", "float", "for", "goto", "if", "inline", "int", "long", "register",
+        "restrict", "return", "short", "signed", "sizeof", "static", "struct", "switch",
+        "typedef", "union", "unsigned", "void", "volatile", "while",
+    ],
+    symbols: &[
+        "<<=", ">>=", "...", "->", "++", "--", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||",
+        "+=", "-=", "*=", "/=", "%=", "&=", "^=", "|=", "##", "+", "-", "*", "/", "%", "<", ">",
+        "=", "!", "~", "&", "|", "^", "?", ":", ";", ",", ".", "(", ")", "[", "]", "{", "}", "#",
+    ],
+    single_line_cmt: Some("//"),
+    multi_line_cmt_start: Some("/*"),
+    multi_line_cmt_end: Some("*/"),
+    nested_comments: false,
+    directive_prefixes: &["#"],
+    line_continuation: Some('\\'),
+    char_delimiter: Some('\''),
+    string_prefixes: &["u8", "u", "U", "L"],
+    strings_may_span_lines: false,
+    scientific_notation: true,
+    hex_float: true,
+    legacy_octal: true,
+    number_suffixes: &[
+        "ULL", "ull", "LLU", "llu", "UL", "ul", "LU", "lu", "LL", "ll", "U", "u", "L", "l", "F", "f",
+    ],
+    ..ScannerConfig::DEFAULT
+};
+
+pub const RUST: ScannerConfig = ScannerConfig {
+    keywords: &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "extern", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
+        "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait", "type",
+        "unsafe", "use", "where", "while",
+    ],
+    true_literals: &["true"],
+    false_literals: &["false"],
+    symbols: &[
+        "<<=", ">>=", "...", "..=", "::", "->", "=>", "==", "!=", "<=", ">=", "&&", "||", "+=",
+        "-=", "*=", "/=", "%=", "^=", "&=", "|=", "<<", ">>", "..", "+", "-", "*", "/", "%", "^",
+        "!", "&", "|", "=", "<", ">", "@", ".", ",", ";", ":", "#", "$", "?", "(", ")", "[", "]",
+        "{", "}",
+    ],
+    single_line_cmt: Some("//"),
+    multi_line_cmt_start: Some("/*"),
+    multi_line_cmt_end: Some("*/"),
+    char_delimiter: Some('\''),
+    lifetimes: true,
+    string_prefixes: &["b", "c"],
+    raw_string_start: Some("r\""),
+    raw_string_end: Some("\""),
+    raw_string_level: Some('#'),
+    attribute_prefixes: &["#[", "#!["],
+    number_separator: Some('_'),
+    scientific_notation: true,
+    number_suffixes: &[
+        "isize", "usize", "i128", "u128", "i16", "i32", "i64", "u16", "u32", "u64", "f32", "f64",
+        "i8", "u8",
+    ],
+    identifier_start: is_unicode_alpha,
+    identifier_char: is_unicode_alphanum,
+    ..ScannerConfig::DEFAULT
+};
+
+pub const PYTHON: ScannerConfig = ScannerConfig {
+    keywords: &[
+        "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+        "elif", "else", "except", "finally", "for", "from", "global", "if", "import", "in", "is",
+        "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while", "with",
+        "yield",
+    ],
+    true_literals: &["True"],
+    false_literals: &["False"],
+    nil_literals: &["None"],
+    symbols: &[
+        "**=", "//=", ">>=", "<<=", "...", "->", ":=", "**", "//", "<<", ">>", "<=", ">=", "==",
+        "!=", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=", "@=", "+", "-", "*", "/", "%", "@",
+        "&", "|", "^", "~", "<", ">", "(", ")", "[", "]", "{", "}", ",", ":", ".", ";", "=",
+    ],
+    single_line_cmt: Some("#"),
+    attribute_prefixes: &["@"],
+    line_continuation: Some('\\'),
+    string_delimiters: &["\"\"\"", "'''", "\"", "'"],
+    string_prefixes: &[
+        "r", "R", "b", "B", "f", "F", "u", "U", "rb", "br", "Rb", "bR", "rB", "Br", "RB", "BR", "fr",
+        "rf", "Fr", "rF", "fR", "Rf", "FR", "RF",
+    ],
+    strings_may_span_lines: false,
+    number_separator: Some('_'),
+    scientific_notation: true,
+    number_suffixes: &["j", "J"],
+    identifier_start: is_unicode_alpha,
+    identifier_char: is_unicode_alphanum,
+    ..ScannerConfig::DEFAULT
+};
+
+pub const JAVASCRIPT: ScannerConfig = ScannerConfig {
+    keywords: &[
+        "async", "await", "break", "case", "catch", "class", "const", "continue", "debugger",
+        "default", "delete", "do", "else", "export", "extends", "finally", "for", "function",
+        "if", "import", "in", "instanceof", "let", "new", "of", "return", "static", "super",
+        "switch", "this", "throw", "try", "typeof", "undefined", "var", "void", "while", "with",
+        "yield",
+    ],
+    true_literals: &["true"],
+    false_literals: &["false"],
+    nil_literals: &["null"],
+    symbols: &[
+        ">>>=", "===", "!==", "**=", "<<=", ">>=", ">>>", "...", "&&=", "||=", "??=", "=>", "==",
+        "!=", "<=", ">=", "&&", "||", "??", "?.", "++", "--", "+=", "-=", "*=", "/=", "%=", "&=",
+        "|=", "^=", "**", "<<", ">>", "+", "-", "*", "/", "%", "&", "|", "^", "!", "~", "<", ">",
+        "=", "?", ":", ";", ",", ".", "(", ")", "[", "]", "{", "}",
+    ],
+    single_line_cmt: Some("//"),
+    multi_line_cmt_start: Some("/*"),
+    multi_line_cmt_end: Some("*/"),
+    nested_comments: false,
+    interpolated_string: Some('`'),
+    interpolation_start: Some("${"),
+    interpolation_end: Some("}"),
+    number_separator: Some('_'),
+    string_delimiters: &["\"", "'"],
+    strings_may_span_lines: false,
+    regex_literals: true,
+    scientific_notation: true,
+    number_suffixes: &["n"],
+    identifier_start: is_unicode_alpha,
+    identifier_char: is_unicode_alphanum,
+    ..ScannerConfig::DEFAULT
+};
+
+pub const JSON: ScannerConfig = ScannerConfig {
+    true_literals: &["true"],
+    false_literals: &["false"],
+    nil_literals: &["null"],
+    symbols: &["{", "}", "[", "]", ":", ",", "-"],
+    strings_may_span_lines: false,
+    scientific_notation: true,
+    ..ScannerConfig::DEFAULT
+};
+
+/// find a preset by its name (case insensitive), for example "lua" or "javascript"
+pub fn by_name(name: &str) -> Option<&'static ScannerConfig> {
+    match name.to_ascii_lowercase().as_str() {
+        "lua" => Some(&LUA),
+        "c" => Some(&C),
+        "rust" | "rs" => Some(&RUST),
+        "python" | "py" => Some(&PYTHON),
+        "javascript" | "js" => Some(&JAVASCRIPT),
+        "json" => Some(&JSON),
+        _ => None,
+    }
+}
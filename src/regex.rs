@@ -0,0 +1,394 @@
+//! A small regular expression engine for token rules, enabled by the `regex` feature.
+//! Supported syntax : literals, `.`, classes like `[a-z_]` or `[^"]`, `\d \w \s \D \W \S`,
+//! escapes like `\.` or `\n`, groups `( )`, alternation `|`
+//! and the greedy or lazy quantifiers `* + ? {n} {n,} {n,m}`.
+//! Patterns are always anchored at the start of the text.
+//! The pattern is compiled to a small program run on all the alternatives at once (a Pike VM),
+//! without backtracking nor recursion: the match time is linear in the length of the text,
+//! even for nested quantifiers like `(a|a)*b`, and a match returns the same length
+//! as a backtracking engine trying the alternatives in order.
+
+/// Error in a regular expression pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegexError {
+    pub message: &'static str,
+    /// position of the error in the pattern, in characters
+    pub position: usize,
+}
+
+impl std::fmt::Display for RegexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for RegexError {}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Char(char),
+    /// any character but a new line
+    Any,
+    /// character ranges, negated or not
+    Class(Vec<(char, char)>, bool),
+    Concat(Vec<Node>),
+    Alt(Vec<Node>),
+    /// node, min, max, greedy
+    Repeat(Box<Node>, usize, Option<usize>, bool),
+}
+
+/// the maximum number of instructions of a compiled pattern.
+/// A counted repetition like `(ab){1000}` is compiled to one copy of its node per iteration
+pub const MAX_PROGRAM_LEN: usize = 10_000;
+
+/// an instruction of the compiled pattern
+#[derive(Debug, Clone)]
+enum Inst {
+    /// consume a character matching a Char, Any or Class node
+    Char(Node),
+    /// continue at both targets, the first one having the priority
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+/// A compiled regular expression
+#[derive(Debug, Clone)]
+pub struct Regex {
+    program: Vec<Inst>,
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Self, RegexError> {
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            pos: 0,
+        };
+        let node = parser.alternation()?;
+        if parser.peek().is_some() {
+            return Err(parser.error("unmatched )"));
+        }
+        let mut program = Vec::new();
+        compile(&node, &mut program);
+        if program.len() >= MAX_PROGRAM_LEN {
+            return Err(parser.error("pattern too large"));
+        }
+        program.push(Inst::Match);
+        Ok(Self { program })
+    }
+    /// the length in bytes of the match at the start of text.
+    /// The text is read once, all the alternatives being tried in parallel,
+    /// so the time is proportional to the length of the text times the size of the pattern
+    pub fn match_len(&self, text: &str) -> Option<usize> {
+        let mut threads = Threads::new(self.program.len());
+        let mut next = Threads::new(self.program.len());
+        threads.add(&self.program, 0);
+        let mut found = None;
+        let mut chars = text.char_indices();
+        loop {
+            let current = chars.next();
+            for &pc in &threads.list {
+                match &self.program[pc] {
+                    Inst::Match => {
+                        // the threads after this one have a lower priority
+                        found = Some(current.map_or(text.len(), |(pos, _)| pos));
+                        break;
+                    }
+                    Inst::Char(node) => {
+                        if current.is_some_and(|(_, c)| char_matches(node, c)) {
+                            next.add(&self.program, pc + 1);
+                        }
+                    }
+                    Inst::Split(..) | Inst::Jump(_) => (),
+                }
+            }
+            if next.list.is_empty() {
+                return found;
+            }
+            std::mem::swap(&mut threads, &mut next);
+            next.clear();
+        }
+    }
+}
+
+/// the instructions reached at a position of the text, in priority order
+struct Threads {
+    list: Vec<usize>,
+    added: Vec<bool>,
+    stack: Vec<usize>,
+}
+
+impl Threads {
+    fn new(len: usize) -> Self {
+        Self {
+            list: Vec::new(),
+            added: vec![false; len],
+            stack: Vec::new(),
+        }
+    }
+    fn clear(&mut self) {
+        self.list.clear();
+        self.added.iter_mut().for_each(|added| *added = false);
+    }
+    /// add an instruction and the ones reachable from it without consuming a character.
+    /// An instruction already added has a higher priority and is not added again,
+    /// which also stops the loops matching an empty text
+    fn add(&mut self, program: &[Inst], pc: usize) {
+        self.stack.push(pc);
+        while let Some(pc) = self.stack.pop() {
+            if std::mem::replace(&mut self.added[pc], true) {
+                continue;
+            }
+            match program[pc] {
+                Inst::Jump(target) => self.stack.push(target),
+                Inst::Split(first, second) => {
+                    self.stack.push(second);
+                    self.stack.push(first);
+                }
+                Inst::Char(_) | Inst::Match => self.list.push(pc),
+            }
+        }
+    }
+}
+
+/// append the instructions of a node to the program
+fn compile(node: &Node, program: &mut Vec<Inst>) {
+    if program.len() >= MAX_PROGRAM_LEN {
+        // Regex::new reports the error
+        return;
+    }
+    match node {
+        Node::Char(_) | Node::Any | Node::Class(..) => program.push(Inst::Char(node.clone())),
+        Node::Concat(nodes) => nodes.iter().for_each(|node| compile(node, program)),
+        Node::Alt(nodes) => {
+            let mut jumps = Vec::new();
+            for (i, node) in nodes.iter().enumerate() {
+                if i + 1 == nodes.len() {
+                    compile(node, program);
+                    break;
+                }
+                let split = program.len();
+                program.push(Inst::Split(split + 1, 0));
+                compile(node, program);
+                jumps.push(program.len());
+                program.push(Inst::Jump(0));
+                program[split] = Inst::Split(split + 1, program.len());
+            }
+            let end = program.len();
+            for jump in jumps {
+                program[jump] = Inst::Jump(end);
+            }
+        }
+        Node::Repeat(node, min, max, greedy) => {
+            for _ in 0..*min {
+                compile(node, program);
+            }
+            let split = |body, out| match greedy {
+                true => Inst::Split(body, out),
+                false => Inst::Split(out, body),
+            };
+            match max {
+                None => {
+                    let start = program.len();
+                    program.push(Inst::Match);
+                    compile(node, program);
+                    program.push(Inst::Jump(start));
+                    program[start] = split(start + 1, program.len());
+                }
+                Some(max) => {
+                    // each optional iteration may skip to the end
+                    let mut splits = Vec::new();
+                    for _ in *min..*max {
+                        splits.push(program.len());
+                        program.push(Inst::Match);
+                        compile(node, program);
+                    }
+                    let end = program.len();
+                    for start in splits {
+                        program[start] = split(start + 1, end);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn char_matches(node: &Node, c: char) -> bool {
+    match node {
+        Node::Char(expected) => c == *expected,
+        Node::Any => c != '\n',
+        Node::Class(ranges, negated) => {
+            ranges.iter().any(|(low, high)| (*low..=*high).contains(&c)) != *negated
+        }
+        _ => false,
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn error(&self, message: &'static str) -> RegexError {
+        RegexError {
+            message,
+            position: self.pos,
+        }
+    }
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+    fn alternation(&mut self) -> Result<Node, RegexError> {
+        let mut nodes = vec![self.concat()?];
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            nodes.push(self.concat()?);
+        }
+        Ok(match nodes.len() {
+            1 => nodes.remove(0),
+            _ => Node::Alt(nodes),
+        })
+    }
+    fn concat(&mut self) -> Result<Node, RegexError> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.atom()?;
+            nodes.push(self.quantifier(atom)?);
+        }
+        Ok(Node::Concat(nodes))
+    }
+    fn atom(&mut self) -> Result<Node, RegexError> {
+        match self.next() {
+            Some('(') => {
+                let node = self.alternation()?;
+                match self.next() {
+                    Some(')') => Ok(node),
+                    _ => Err(self.error("missing )")),
+                }
+            }
+            Some('.') => Ok(Node::Any),
+            Some('[') => self.class(),
+            Some('\\') => self.escape(),
+            Some(c @ ('*' | '+' | '?' | '{')) => {
+                self.pos -= 1;
+                Err(self.error(match c {
+                    '{' => "nothing to repeat before {",
+                    _ => "nothing to repeat",
+                }))
+            }
+            Some(c) => Ok(Node::Char(c)),
+            None => Err(self.error("unexpected end of pattern")),
+        }
+    }
+    fn escape(&mut self) -> Result<Node, RegexError> {
+        let digit = vec![('0', '9')];
+        let word = vec![('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')];
+        let space = vec![(' ', ' '), ('\t', '\r')];
+        Ok(match self.next().ok_or_else(|| self.error("unexpected end of pattern"))? {
+            'd' => Node::Class(digit, false),
+            'D' => Node::Class(digit, true),
+            'w' => Node::Class(word, false),
+            'W' => Node::Class(word, true),
+            's' => Node::Class(space, false),
+            'S' => Node::Class(space, true),
+            c => Node::Char(escaped_char(c)),
+        })
+    }
+    fn class(&mut self) -> Result<Node, RegexError> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.pos += 1;
+        }
+        let mut ranges = Vec::new();
+        loop {
+            let c = match self.next() {
+                None => return Err(self.error("missing ]")),
+                Some(']') if !ranges.is_empty() => break,
+                Some('\\') => match self.escape()? {
+                    Node::Class(class, false) => {
+                        ranges.extend(class);
+                        continue;
+                    }
+                    Node::Char(c) => c,
+                    _ => return Err(self.error("negated class inside a class")),
+                },
+                Some(c) => c,
+            };
+            if self.peek() == Some('-') && self.chars.get(self.pos + 1).is_some_and(|c| *c != ']') {
+                self.pos += 1;
+                let high = match self.next() {
+                    Some('\\') => self.next().map(escaped_char),
+                    high => high,
+                }
+                .ok_or_else(|| self.error("missing ]"))?;
+                if high < c {
+                    return Err(self.error("invalid range"));
+                }
+                ranges.push((c, high));
+            } else {
+                ranges.push((c, c));
+            }
+        }
+        Ok(Node::Class(ranges, negated))
+    }
+    fn quantifier(&mut self, atom: Node) -> Result<Node, RegexError> {
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => self.range()?,
+            _ => return Ok(atom),
+        };
+        // the quantifier character, or the } ending a range
+        self.pos += 1;
+        let greedy = self.peek() != Some('?');
+        if !greedy {
+            self.pos += 1;
+        }
+        Ok(Node::Repeat(Box::new(atom), min, max, greedy))
+    }
+    /// parse a {n}, {n,} or {n,m} range, stopping on the closing }
+    fn range(&mut self) -> Result<(usize, Option<usize>), RegexError> {
+        self.pos += 1;
+        let min = self.number().ok_or_else(|| self.error("missing repetition count"))?;
+        let max = match self.peek() {
+            Some(',') => {
+                self.pos += 1;
+                self.number()
+            }
+            _ => Some(min),
+        };
+        if self.peek() != Some('}') {
+            return Err(self.error("missing }"));
+        }
+        if max.is_some_and(|max| max < min) {
+            return Err(self.error("invalid repetition range"));
+        }
+        Ok((min, max))
+    }
+    fn number(&mut self) -> Option<usize> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect::<String>().parse().ok()
+    }
+}
+
+fn escaped_char(c: char) -> char {
+    match c {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        c => c,
+    }
+}
@@ -0,0 +1,89 @@
+use crate::TokenType;
+
+/// A custom token recognizer, registered with CompiledConfig::with_rule
+/// or CompiledConfig::with_fallback_rule. Example : a color literal like `#ff00aa`
+///
+/// ```text
+/// let config = presets::C.compile().with_rule(|cursor: &mut Cursor| {
+///     cursor.eat("#").then_some(())?;
+///     (cursor.advance_while(|c| c.is_ascii_hexdigit()) == 6)
+///         .then(|| TokenType::Symbol(cursor.text().to_owned()))
+/// });
+/// ```
+pub trait TokenRule: Send + Sync {
+    /// try to recognize a token at the cursor position, advancing the cursor to its end.
+    /// Returns None, or a token of length 0, to let the other rules and the built-in scanners try
+    fn try_scan(&self, cursor: &mut Cursor) -> Option<TokenType>;
+}
+
+impl<F: Fn(&mut Cursor) -> Option<TokenType> + Send + Sync> TokenRule for F {
+    fn try_scan(&self, cursor: &mut Cursor) -> Option<TokenType> {
+        self(cursor)
+    }
+}
+
+/// The position of a TokenRule in the source code
+pub struct Cursor<'a> {
+    source: &'a str,
+    start: usize,
+    current: usize,
+}
+
+impl<'a> Cursor<'a> {
+    pub(crate) fn new(source: &'a str, start: usize) -> Self {
+        Self {
+            source,
+            start,
+            current: start,
+        }
+    }
+    /// the whole source code
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+    /// position of the token start in bytes
+    pub fn start(&self) -> usize {
+        self.start
+    }
+    /// current position in bytes
+    pub fn offset(&self) -> usize {
+        self.current
+    }
+    /// the text consumed since the token start
+    pub fn text(&self) -> &'a str {
+        &self.source[self.start..self.current]
+    }
+    /// the source code after the current position
+    pub fn rest(&self) -> &'a str {
+        &self.source[self.current..]
+    }
+    pub fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+    /// the nth character after the current position, starting at 0
+    pub fn peek_nth(&self, n: usize) -> Option<char> {
+        self.rest().chars().nth(n)
+    }
+    pub fn advance(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.current += c.len_utf8();
+        Some(c)
+    }
+    /// advance while the characters match a predicate. Returns the number of characters consumed
+    pub fn advance_while(&mut self, pred: impl Fn(char) -> bool) -> usize {
+        let mut count = 0;
+        while self.peek().is_some_and(&pred) {
+            self.advance();
+            count += 1;
+        }
+        count
+    }
+    /// advance past a text if it is at the current position
+    pub fn eat(&mut self, text: &str) -> bool {
+        let found = self.rest().starts_with(text);
+        if found {
+            self.current += text.len();
+        }
+        found
+    }
+}
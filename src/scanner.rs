@@ -1,51 +1,215 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::io::Write;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-pub type Number = f64;
+#[cfg(feature = "json")]
+use crate::value::Value;
+use crate::trie::Trie;
+use crate::brackets::{BracketError, Brackets, DEFAULT_PAIRS};
+use crate::{Cursor, FileError, Interner, LineIndex, SourceFile, Symbol, TextSource, TokenRule};
 
-/// The fields contain the line number and character position in the line
-#[derive(Debug,PartialEq)]
+/// The parsed value of a number literal
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NumberValue {
+    /// a literal without fractional part or exponent, like 42 or 0xFF
+    Int(i64),
+    /// a literal with a fractional part or an exponent, or an integer too large for an i64
+    Float(f64),
+}
+
+impl NumberValue {
+    /// the value as a float, whatever the literal type
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            NumberValue::Int(i) => i as f64,
+            NumberValue::Float(f) => f,
+        }
+    }
+    /// the value as an integer, if the literal is an integer
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            NumberValue::Int(i) => Some(i),
+            NumberValue::Float(_) => None,
+        }
+    }
+}
+
+/// Where a scan error occurred
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorSpan {
+    /// position of the token causing the error in bytes.
+    /// For an unterminated token, it goes until the point where the error was detected
+    pub span: Span,
+    /// line where the error was detected
+    pub line: usize,
+    /// character offset of the token from the start of the source code
+    pub offset: usize,
+    /// character offset of the token from the start of its line,
+    /// with the tabulations expanded, see Scanner::with_tab_width
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum ScanError {
     /// Unrecognized token.
-    UnknownToken(usize, usize),
+    UnknownToken(ErrorSpan),
     /// Eof of file before the end of current token
     /// (for example, an unterminated string)
-    UnexpectedEof(usize, usize),
+    UnexpectedEof(ErrorSpan),
+    /// New line inside a string literal
+    /// when ScannerConfig::strings_may_span_lines is false
+    UnterminatedString(ErrorSpan),
+    /// Control character outside of a token, reported as a warning
+    /// with ControlCharPolicy::Warning, see ScannerData::warnings
+    ControlCharacter(ErrorSpan),
+    /// A limit of Scanner::with_limits was exceeded.
+    /// The span is the token exceeding the limit, or the whole source code if it is too long
+    LimitExceeded(ErrorSpan),
+    /// The scan was cancelled, see Scanner::with_cancellation.
+    /// The span is empty, at the position where the scan stopped
+    Cancelled(ErrorSpan),
+}
+
+impl ScanError {
+    /// the line number and the character offset of the error
+    pub fn position(&self) -> (usize, usize) {
+        let span = self.span();
+        (span.line, span.offset)
+    }
+    /// the location of the error
+    pub fn span(&self) -> &ErrorSpan {
+        match self {
+            ScanError::UnknownToken(span) => span,
+            ScanError::UnexpectedEof(span) => span,
+            ScanError::UnterminatedString(span) => span,
+            ScanError::ControlCharacter(span) => span,
+            ScanError::LimitExceeded(span) => span,
+            ScanError::Cancelled(span) => span,
+        }
+    }
+    /// a stable code identifying the type of error, for tools consuming the errors
+    pub fn code(&self) -> &'static str {
+        match self {
+            ScanError::UnknownToken(_) => "E0001",
+            ScanError::UnexpectedEof(_) => "E0002",
+            ScanError::UnterminatedString(_) => "E0003",
+            ScanError::ControlCharacter(_) => "E0004",
+            ScanError::LimitExceeded(_) => "E0005",
+            ScanError::Cancelled(_) => "E0006",
+        }
+    }
+    #[cfg(feature = "json")]
+    /// the error as a JSON object :
+    /// `{"code":"E0001","message":"unknown token","line":1,"offset":4,"column":4,"start":4,"len":1}`.
+    /// start and len are in bytes
+    pub fn to_json(&self) -> String {
+        self.to_value().to_string()
+    }
+    #[cfg(feature = "json")]
+    /// the error as a Value, see to_json
+    pub fn to_value(&self) -> Value {
+        let span = self.span();
+        Value::Object(vec![
+            ("code".to_owned(), self.code().into()),
+            ("message".to_owned(), self.message().into()),
+            ("line".to_owned(), span.line.into()),
+            ("offset".to_owned(), span.offset.into()),
+            ("column".to_owned(), span.column.into()),
+            ("start".to_owned(), span.span.start().into()),
+            ("len".to_owned(), span.span.len().into()),
+        ])
+    }
+    #[cfg(feature = "json")]
+    /// rebuild an error from the output of to_value
+    pub fn from_value(value: &Value) -> Option<Self> {
+        let variant = match value.get("code")?.as_str()? {
+            "E0001" => ScanError::UnknownToken,
+            "E0002" => ScanError::UnexpectedEof,
+            "E0003" => ScanError::UnterminatedString,
+            "E0004" => ScanError::ControlCharacter,
+            "E0005" => ScanError::LimitExceeded,
+            "E0006" => ScanError::Cancelled,
+            _ => return None,
+        };
+        let field = |key| value.get(key)?.as_usize();
+        Some(variant(ErrorSpan {
+            span: Span::new(field("start")?, field("len")?),
+            line: field("line")?,
+            offset: field("offset")?,
+            column: field("column")?,
+        }))
+    }
+    /// a short description of the error
+    pub fn message(&self) -> &'static str {
+        match self {
+            ScanError::UnknownToken(_) => "unknown token",
+            ScanError::UnexpectedEof(_) => "unexpected end of file",
+            ScanError::UnterminatedString(_) => "unterminated string",
+            ScanError::ControlCharacter(_) => "control character",
+            ScanError::LimitExceeded(_) => "limit exceeded",
+            ScanError::Cancelled(_) => "scan cancelled",
+        }
+    }
 }
 
 impl std::fmt::Display for ScanError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let (line, offset) = match self {
-            ScanError::UnknownToken(line, offset) => (line, offset),
-            ScanError::UnexpectedEof(line, offset) => (line, offset),
-        };
-        write!(
-            f,
-            "{}:{} : {}",
-            line,
-            offset,
-            match self {
-                ScanError::UnknownToken(_, _) => "unknown token",
-                ScanError::UnexpectedEof(_, _) => "unexpected end of file",
-            }
-        )
+        let (line, offset) = self.position();
+        write!(f, "{}:{} : {}", line, offset, self.message())
     }
 }
 
-#[derive(Debug, PartialEq)]
+impl std::error::Error for ScanError {}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum TokenType {
-    /// a symbol from the symbols list
+    /// a symbol from the symbols list. When ScannerConfig::punctuation is used,
+    /// the symbols not in the punctuation list, which are the operators
     Symbol(String),
+    /// a symbol from the punctuation list, like "(" or ","
+    Punctuation(String),
     /// an identifier
     Identifier(String),
     /// a string litteral. value is the string value, without the delimiting quotes
     StringLiteral(String),
-    /// a number literal, with its string representation in the code and its parsed value
-    NumberLiteral(String, Number),
+    /// a character literal, without the delimiting quotes
+    CharLiteral(char),
+    /// a lifetime or a loop label like `'a` in Rust, without the quote.
+    /// Only produced when ScannerConfig::lifetimes is enabled
+    Lifetime(String),
+    /// the text of an interpolated string before, between or after the interpolations,
+    /// without the delimiting quotes
+    StringPart(String),
+    /// the token starting an interpolation inside an interpolated string, for example "${"
+    InterpolationStart,
+    /// the token ending an interpolation, for example "}"
+    InterpolationEnd,
+    /// a number literal, with its string representation in the code, its parsed value,
+    /// its type suffix if any, for example "u32" in `42u32`, and its base (2, 8, 10 or 16)
+    NumberLiteral(String, NumberValue, Option<String>, u32),
+    /// a word from the true_literals or false_literals lists, with its value
+    BoolLiteral(bool),
+    /// a word from the nil_literals list, like "nil" or "null"
+    NilLiteral,
+    /// a regular expression literal, with its pattern and its flags, for example
+    /// "a+" and "g" for `/a+/g`. Only produced when ScannerConfig::regex_literals is enabled
+    RegexLiteral(String, String),
     /// a keyword from the keywords list
     Keyword(String),
     /// a single or multi-line comment. The value contains the delimiting characters.
     Comment(String),
-    /// space, tabulations, ...
+    /// a preprocessor directive, for example `#include <stdio.h>`. The value is the whole line
+    Directive(String),
+    /// an attribute or a decorator, for example `#[derive(Debug)]` or `@app.route("/")`.
+    /// The value is the whole attribute
+    Attribute(String),
+    /// a token produced by a custom rule, with its class name and its text
+    Custom(&'static str, String),
+    /// space, tabulations, ... Only produced with Scanner::with_whitespace
     Ignore,
     /// a newline character
     NewLine,
@@ -55,433 +219,4033 @@ pub enum TokenType {
 }
 
 impl TokenType {
+    /// length of the value in bytes, with the delimiting quotes of the literals
+    #[deprecated(note = "ambiguous for non ASCII text, use len_bytes or len_chars")]
     pub fn len(&self) -> usize {
+        self.len_bytes()
+    }
+    /// length of the value in bytes, with the delimiting quotes of the literals.
+    /// It is the length of the token in the source code when the value contains no escape sequence
+    pub fn len_bytes(&self) -> usize {
+        self.measure(str::len)
+    }
+    /// length of the value in characters, with the delimiting quotes of the literals,
+    /// comparable to ScannerData::token_len_chars
+    pub fn len_chars(&self) -> usize {
+        self.measure(|s| s.chars().count())
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len_bytes() == 0
+    }
+    /// memory allocated by the strings of the value, in bytes
+    fn heap_size(&self) -> usize {
+        match self {
+            TokenType::Symbol(s)
+            | TokenType::Punctuation(s)
+            | TokenType::Identifier(s)
+            | TokenType::StringLiteral(s)
+            | TokenType::StringPart(s)
+            | TokenType::Keyword(s)
+            | TokenType::Comment(s)
+            | TokenType::Directive(s)
+            | TokenType::Attribute(s)
+            | TokenType::Lifetime(s)
+            | TokenType::Custom(_, s) => s.capacity(),
+            TokenType::NumberLiteral(text, _, suffix, _) => {
+                text.capacity() + suffix.as_ref().map_or(0, String::capacity)
+            }
+            TokenType::RegexLiteral(pattern, flags) => pattern.capacity() + flags.capacity(),
+            _ => 0,
+        }
+    }
+    fn measure(&self, len: fn(&str) -> usize) -> usize {
         match self {
-            TokenType::Symbol(s) => s.len(),
-            TokenType::Identifier(s) => s.len(),
-            TokenType::StringLiteral(s) => s.len() + 2,
-            TokenType::Keyword(s) => s.len(),
-            TokenType::NumberLiteral(s, _) => s.len(),
-            TokenType::Comment(s) => s.len(),
+            TokenType::Symbol(s)
+            | TokenType::Punctuation(s)
+            | TokenType::Identifier(s)
+            | TokenType::StringPart(s)
+            | TokenType::Keyword(s)
+            | TokenType::NumberLiteral(s, _, _, _)
+            | TokenType::Comment(s)
+            | TokenType::Directive(s)
+            | TokenType::Attribute(s)
+            | TokenType::Custom(_, s) => len(s),
+            TokenType::StringLiteral(s) => len(s) + 2,
+            TokenType::CharLiteral(c) => len(c.encode_utf8(&mut [0; 4])) + 2,
+            TokenType::Lifetime(s) => len(s) + 1,
+            TokenType::RegexLiteral(pattern, flags) => len(pattern) + len(flags) + 2,
             _ => 0,
         }
     }
+    #[cfg(feature = "json")]
+    /// the token as a JSON-like value, for example `{"type": "Identifier", "value": "a"}`
+    /// or `{"type": "Custom:color", "value": "#ff00aa"}`.
+    /// Number literals have the text, int or float, suffix and radix fields instead of value,
+    /// regex literals have the pattern and flags fields.
+    /// Integers are stored as numbers, so they lose precision above 2^53
+    pub fn to_value(&self) -> Value {
+        let mut fields = vec![("type".to_owned(), kind_name(self.kind()).into())];
+        let value = match self {
+            TokenType::Symbol(s)
+            | TokenType::Punctuation(s)
+            | TokenType::Identifier(s)
+            | TokenType::StringLiteral(s)
+            | TokenType::StringPart(s)
+            | TokenType::Keyword(s)
+            | TokenType::Comment(s)
+            | TokenType::Directive(s)
+            | TokenType::Attribute(s)
+            | TokenType::Lifetime(s)
+            | TokenType::Custom(_, s) => Some(Value::from(s.as_str())),
+            TokenType::CharLiteral(c) => Some(Value::from(c.to_string())),
+            TokenType::BoolLiteral(b) => Some(Value::from(*b)),
+            TokenType::NumberLiteral(text, number, suffix, radix) => {
+                fields.push(("text".to_owned(), text.as_str().into()));
+                fields.push(match number {
+                    NumberValue::Int(i) => ("int".to_owned(), Value::Number(*i as f64)),
+                    NumberValue::Float(f) => ("float".to_owned(), Value::Number(*f)),
+                });
+                fields.push(("suffix".to_owned(), suffix.clone().into()));
+                fields.push(("radix".to_owned(), (*radix as usize).into()));
+                None
+            }
+            TokenType::RegexLiteral(pattern, flags) => {
+                fields.push(("pattern".to_owned(), pattern.as_str().into()));
+                fields.push(("flags".to_owned(), flags.as_str().into()));
+                None
+            }
+            _ => None,
+        };
+        if let Some(value) = value {
+            fields.push(("value".to_owned(), value));
+        }
+        Value::Object(fields)
+    }
+    #[cfg(feature = "json")]
+    /// rebuild a token from the output of to_value
+    pub fn from_value(value: &Value) -> Option<Self> {
+        let text = || value.get("value")?.as_str().map(str::to_owned);
+        Some(match kind_from_name(value.get("type")?.as_str()?)? {
+            TokenKind::Symbol => TokenType::Symbol(text()?),
+            TokenKind::Punctuation => TokenType::Punctuation(text()?),
+            TokenKind::Identifier => TokenType::Identifier(text()?),
+            TokenKind::StringLiteral => TokenType::StringLiteral(text()?),
+            TokenKind::StringPart => TokenType::StringPart(text()?),
+            TokenKind::Keyword => TokenType::Keyword(text()?),
+            TokenKind::Comment => TokenType::Comment(text()?),
+            TokenKind::Directive => TokenType::Directive(text()?),
+            TokenKind::Attribute => TokenType::Attribute(text()?),
+            TokenKind::CharLiteral => TokenType::CharLiteral(text()?.chars().next()?),
+            TokenKind::Lifetime => TokenType::Lifetime(text()?),
+            TokenKind::NumberLiteral => {
+                let number = match (value.get("int"), value.get("float")) {
+                    (Some(i), _) => NumberValue::Int(i.as_f64()? as i64),
+                    (None, Some(f)) => NumberValue::Float(f.as_f64()?),
+                    _ => return None,
+                };
+                let suffix = match value.get("suffix") {
+                    None | Some(Value::Null) => None,
+                    Some(suffix) => Some(suffix.as_str()?.to_owned()),
+                };
+                TokenType::NumberLiteral(
+                    value.get("text")?.as_str()?.to_owned(),
+                    number,
+                    suffix,
+                    value.get("radix")?.as_usize()? as u32,
+                )
+            }
+            TokenKind::BoolLiteral => TokenType::BoolLiteral(value.get("value")?.as_bool()?),
+            TokenKind::NilLiteral => TokenType::NilLiteral,
+            TokenKind::RegexLiteral => TokenType::RegexLiteral(
+                value.get("pattern")?.as_str()?.to_owned(),
+                value.get("flags")?.as_str()?.to_owned(),
+            ),
+            TokenKind::InterpolationStart => TokenType::InterpolationStart,
+            TokenKind::InterpolationEnd => TokenType::InterpolationEnd,
+            TokenKind::Ignore => TokenType::Ignore,
+            TokenKind::NewLine => TokenType::NewLine,
+            TokenKind::Eof => TokenType::Eof,
+            TokenKind::Unknown => TokenType::Unknown,
+            kind @ TokenKind::Custom(_) => TokenType::Custom(kind.class()?, text()?),
+        })
+    }
+    /// the type of this token, without its value
+    pub fn kind(&self) -> TokenKind {
+        match self {
+            TokenType::Symbol(_) => TokenKind::Symbol,
+            TokenType::Punctuation(_) => TokenKind::Punctuation,
+            TokenType::Identifier(_) => TokenKind::Identifier,
+            TokenType::StringLiteral(_) => TokenKind::StringLiteral,
+            TokenType::CharLiteral(_) => TokenKind::CharLiteral,
+            TokenType::Lifetime(_) => TokenKind::Lifetime,
+            TokenType::StringPart(_) => TokenKind::StringPart,
+            TokenType::InterpolationStart => TokenKind::InterpolationStart,
+            TokenType::InterpolationEnd => TokenKind::InterpolationEnd,
+            TokenType::NumberLiteral(_, _, _, _) => TokenKind::NumberLiteral,
+            TokenType::BoolLiteral(_) => TokenKind::BoolLiteral,
+            TokenType::NilLiteral => TokenKind::NilLiteral,
+            TokenType::RegexLiteral(_, _) => TokenKind::RegexLiteral,
+            TokenType::Keyword(_) => TokenKind::Keyword,
+            TokenType::Comment(_) => TokenKind::Comment,
+            TokenType::Directive(_) => TokenKind::Directive,
+            TokenType::Attribute(_) => TokenKind::Attribute,
+            TokenType::Custom(class, _) => TokenKind::register(class),
+            TokenType::Ignore => TokenKind::Ignore,
+            TokenType::NewLine => TokenKind::NewLine,
+            TokenType::Eof => TokenKind::Eof,
+            TokenType::Unknown => TokenKind::Unknown,
+        }
+    }
 }
 
-#[derive(Default)]
-pub struct ScannerData {
-    /// complete source code
-    pub source: Vec<char>,
-    /// resulting list of tokens
-    pub token_types: Vec<TokenType>,
+/// The type of a token without its value.
+/// The token text can be sliced from the source code using its Span.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    Symbol,
+    Punctuation,
+    Identifier,
+    StringLiteral,
+    CharLiteral,
+    Lifetime,
+    StringPart,
+    InterpolationStart,
+    InterpolationEnd,
+    NumberLiteral,
+    BoolLiteral,
+    NilLiteral,
+    RegexLiteral,
+    Keyword,
+    Comment,
+    Directive,
+    Attribute,
+    /// a token produced by a custom rule, with the symbol of its class name, see TokenKind::class
+    Custom(Symbol),
+    Ignore,
+    NewLine,
+    Eof,
+    Unknown,
+}
+
+#[cfg(feature = "json")]
+const TOKEN_KINDS: [TokenKind; 21] = [
+    TokenKind::Symbol,
+    TokenKind::Punctuation,
+    TokenKind::Identifier,
+    TokenKind::StringLiteral,
+    TokenKind::CharLiteral,
+    TokenKind::Lifetime,
+    TokenKind::StringPart,
+    TokenKind::InterpolationStart,
+    TokenKind::InterpolationEnd,
+    TokenKind::NumberLiteral,
+    TokenKind::BoolLiteral,
+    TokenKind::NilLiteral,
+    TokenKind::RegexLiteral,
+    TokenKind::Keyword,
+    TokenKind::Comment,
+    TokenKind::Directive,
+    TokenKind::Attribute,
+    TokenKind::Ignore,
+    TokenKind::NewLine,
+    TokenKind::Eof,
+    TokenKind::Unknown,
+];
+
+impl TokenKind {
+    /// the kind of the custom tokens of a class whose name is only known at runtime,
+    /// for example read from a file. Each distinct class name is allocated once and never freed
+    pub fn custom(class: &str) -> Self {
+        let mut names = names();
+        if let Some(symbol) = names.symbols.get(class) {
+            return TokenKind::Custom(*symbol);
+        }
+        TokenKind::Custom(names.register(Box::leak(class.to_owned().into_boxed_str())))
+    }
+    /// the kind of the custom tokens of a class. Also makes the name known to
+    /// ScannerData::from_value, which is needed for the embedded language names too.
+    /// The names of CompiledConfig::with_regex_rule and CompiledConfig::with_embedded
+    /// and the names written by to_value are registered automatically
+    pub fn register(name: &'static str) -> Self {
+        TokenKind::Custom(names().register(name))
+    }
+    /// the class name of a custom token, None for the other kinds
+    pub fn class(self) -> Option<&'static str> {
+        match self {
+            TokenKind::Custom(symbol) => names().list.get(symbol.index()).copied(),
+            _ => None,
+        }
+    }
+    /// whether the token is from the symbols list, an operator or a punctuation
+    pub fn is_symbol(self) -> bool {
+        matches!(self, TokenKind::Symbol | TokenKind::Punctuation)
+    }
+}
+
+/// the custom token classes and embedded language names known to the program,
+/// see TokenKind::register. A custom TokenKind holds the symbol of its class in this table
+struct Names {
+    list: Vec<&'static str>,
+    symbols: BTreeMap<&'static str, Symbol>,
+}
+
+impl Names {
+    fn register(&mut self, name: &'static str) -> Symbol {
+        if let Some(symbol) = self.symbols.get(name) {
+            return *symbol;
+        }
+        let symbol = Symbol::new(self.list.len());
+        self.list.push(name);
+        self.symbols.insert(name, symbol);
+        symbol
+    }
+}
+
+fn names() -> std::sync::MutexGuard<'static, Names> {
+    static NAMES: Mutex<Names> = Mutex::new(Names {
+        list: Vec::new(),
+        symbols: BTreeMap::new(),
+    });
+    NAMES.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// the static copy of a registered name, None if the name is unknown.
+/// Used when reading untrusted data so that it cannot exhaust the memory
+#[cfg(feature = "json")]
+fn registered_name(name: &str) -> Option<&'static str> {
+    names().symbols.get_key_value(name).map(|(name, _)| *name)
+}
+
+/// the name of a token kind in serialized data, like "Identifier" or "Custom:lifetime"
+#[cfg(feature = "json")]
+pub(crate) fn kind_name(kind: TokenKind) -> String {
+    match kind.class() {
+        Some(class) => format!("Custom:{}", class),
+        None => format!("{:?}", kind),
+    }
+}
+
+/// the length of a text up to the closing bracket matching depth opened brackets,
+/// skipping the brackets inside double quoted strings
+fn bracketed_len(text: &str, mut depth: usize) -> Option<usize> {
+    let mut in_string = false;
+    let mut escape = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            _ if escape => escape = false,
+            '\\' if in_string => escape = true,
+            '"' => in_string = !in_string,
+            _ if in_string => (),
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+/// a position or a length stored in ScannerData. The sources of 4 GiB or more are not supported
+fn compact(value: usize) -> u32 {
+    u32::try_from(value).unwrap_or(u32::MAX)
+}
+
+/// whether a slash following a token starts a regex literal rather than a division
+fn regex_may_follow(kind: TokenKind, text: &str) -> bool {
+    match kind {
+        TokenKind::Symbol | TokenKind::Punctuation => {
+            !matches!(text, ")" | "]" | "}" | "++" | "--")
+        }
+        TokenKind::Keyword | TokenKind::InterpolationStart | TokenKind::Attribute => true,
+        _ => false,
+    }
+}
+
+#[cfg(feature = "json")]
+fn kind_from_name(name: &str) -> Option<TokenKind> {
+    match name.strip_prefix("Custom:") {
+        Some(class) => registered_name(class).map(TokenKind::register),
+        None => TOKEN_KINDS.into_iter().find(|kind| kind_name(*kind) == name),
+    }
+}
+
+/// Position of a token in the source code, in bytes.
+/// `&source[span.range()]` is the token text.
+/// Stored on 32 bits, so the source code is limited to 4 GB, see ScanLimits::max_input_len
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    start: u32,
+    len: u32,
+}
+
+impl Span {
+    pub fn new(start: usize, len: usize) -> Self {
+        Self {
+            start: compact(start),
+            len: compact(len),
+        }
+    }
+    /// offset of the first byte of the token
+    pub fn start(&self) -> usize {
+        self.start as usize
+    }
+    /// token length in bytes
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// offset of the byte following the token
+    pub fn end(&self) -> usize {
+        self.start() + self.len()
+    }
+    pub fn range(&self) -> Range<usize> {
+        self.start()..self.end()
+    }
+}
+
+/// The index of a token in ScannerData, stored on 32 bits like the other token positions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct TokenIndex(u32);
+
+impl TokenIndex {
+    pub fn new(index: usize) -> Self {
+        TokenIndex(compact(index))
+    }
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl From<TokenIndex> for usize {
+    fn from(index: TokenIndex) -> Self {
+        index.index()
+    }
+}
+
+/// A token produced by Scanner::tokens or read from ScannerData::get
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// token position in the source code in bytes
+    pub span: Span,
     /// token start line in the source code
-    pub token_lines: Vec<usize>,
-    /// token start offset from its line beginning
-    pub token_start: Vec<usize>,
-    /// token length in characters (not in bytes!)
-    /// not always = token value's length.
-    /// For example for TokenType::StringLiteral("aa") the value length is 2 but the token length including the quotes is 4
-    /// Also when using unicode,  the length of "à" in bytes is 4, but the token_len is 3
-    pub token_len: Vec<usize>,
+    pub line: usize,
 }
 
-impl ScannerData {
-    pub fn dump(&self, out: &mut dyn Write) {
-        for (i, token) in self.token_types.iter().enumerate() {
-            writeln!(out, "[#{:03} line {}] {:?}", i, self.token_lines[i], *token).ok();
+impl Token {
+    /// the token text in the source code
+    pub fn text<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.span.range()]
+    }
+    /// the token length in the source code, in bytes
+    pub fn len_bytes(&self) -> usize {
+        self.span.len()
+    }
+    /// the token length in the source code, in characters
+    pub fn len_chars(&self, source: &str) -> usize {
+        self.text(source).chars().count()
+    }
+    /// the token text in a source code which may be stored in several chunks
+    pub fn text_in<'a, T: TextSource + ?Sized>(&self, source: &'a T) -> Cow<'a, str> {
+        source.slice(self.span.range())
+    }
+}
+
+/// The part of a token on one line, see ScannerData::line_tokens
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenSlice<'a> {
+    /// index of the token in the ScannerData
+    pub index: usize,
+    pub kind: TokenKind,
+    /// position of the part in the source code in bytes, without the line ending
+    pub span: Span,
+    /// column of the part in its line, 0 for the continuation lines of a multi-line token
+    pub column: usize,
+    /// text of the part
+    pub text: &'a str,
+}
+
+/// The spaces, new lines and comments surrounding a token, when using Scanner::with_trivia.
+/// The trailing trivia goes until the end of the token line, the new line included.
+/// The leading trivia is everything else between the previous token and this one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Trivia {
+    /// position of the trivia before the token, in bytes
+    pub leading: Span,
+    /// position of the trivia after the token, in bytes
+    pub trailing: Span,
+}
+
+/// The memory used by a ScannerData in bytes, see ScannerData::memory_usage.
+/// Values are approximate for the hash table of the interned symbols
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MemoryUsage {
+    /// kinds, spans, lines and columns of the tokens, and the unterminated strings
+    pub tokens: usize,
+    /// token values with their strings
+    pub values: usize,
+    /// line index
+    pub lines: usize,
+    /// trivia, symbols and languages of the tokens, and interned texts
+    pub extra: usize,
+}
+
+impl MemoryUsage {
+    pub fn total(&self) -> usize {
+        self.tokens + self.values + self.lines + self.extra
+    }
+}
+
+/// A phase of a scan, reported to a ScanObserver
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScanPhase {
+    /// compiling the configuration, almost instant for a CompiledConfig
+    Compile,
+    /// indexing the lines of the source code
+    LineIndex,
+    /// scanning the tokens
+    Scan,
+    /// computing the trivia, only with Scanner::with_trivia
+    Trivia,
+}
+
+/// Receives the duration of each phase of a scan run by Scanner::run_observed,
+/// for example to forward them to a tracing or metrics system.
+/// ScanStats is an observer summing the durations
+pub trait ScanObserver {
+    /// called at the end of each phase
+    fn phase(&mut self, phase: ScanPhase, duration: Duration);
+}
+
+/// Measures of a scan returned by Scanner::run_with_stats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ScanStats {
+    /// number of tokens produced
+    pub tokens: usize,
+    /// length of the source code in bytes
+    pub bytes: usize,
+    /// number of lines of the source code
+    pub lines: usize,
+    /// time spent compiling the configuration, almost zero for a CompiledConfig
+    pub compile_time: Duration,
+    /// time spent indexing the lines of the source code
+    pub line_index_time: Duration,
+    /// time spent scanning the tokens
+    pub scan_time: Duration,
+    /// time spent computing the trivia, zero unless Scanner::with_trivia is used
+    pub trivia_time: Duration,
+}
+
+impl ScanObserver for ScanStats {
+    fn phase(&mut self, phase: ScanPhase, duration: Duration) {
+        match phase {
+            ScanPhase::Compile => self.compile_time += duration,
+            ScanPhase::LineIndex => self.line_index_time += duration,
+            ScanPhase::Scan => self.scan_time += duration,
+            ScanPhase::Trivia => self.trivia_time += duration,
         }
     }
 }
 
-#[derive(Default)]
-pub struct Scanner {
-    // start of parsing position
-    start: usize,
-    // position during parsing of current token
-    current: usize,
-    // current line in file
-    line: usize,
+impl ScanStats {
+    /// time spent in all the phases of the scan
+    pub fn total_time(&self) -> Duration {
+        self.compile_time + self.line_index_time + self.scan_time + self.trivia_time
+    }
+    /// scanning speed in tokens per second, over the whole scan
+    pub fn tokens_per_sec(&self) -> f64 {
+        self.rate(self.tokens)
+    }
+    /// scanning speed in bytes per second, over the whole scan
+    pub fn bytes_per_sec(&self) -> f64 {
+        self.rate(self.bytes)
+    }
+    fn rate(&self, count: usize) -> f64 {
+        let seconds = self.total_time().as_secs_f64();
+        if seconds == 0.0 {
+            0.0
+        } else {
+            count as f64 / seconds
+        }
+    }
 }
 
-pub struct ScannerConfig {
-    /// list of keywords, ordered by descending length
-    pub keywords: &'static [&'static str],
-    /// list of symbols, ordered by descending length
-    pub symbols: &'static [&'static str],
-    /// token starting a single line comment
-    pub single_line_cmt: Option<&'static str>,
-    /// token starting a multi line comment
-    pub multi_line_cmt_start: Option<&'static str>,
-    /// token ending a multi line comment
-    pub multi_line_cmt_end: Option<&'static str>,
+/// What the scanner does with a string literal without its closing delimiter,
+/// see ScannerConfig::unterminated_strings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnterminatedStringPolicy {
+    /// stop the scan with an UnexpectedEof or UnterminatedString error (default)
+    #[default]
+    Error,
+    /// the string ends at the end of its first line and the scan continues on the next line.
+    /// Keeps an unclosed quote from turning the rest of the file into a string in an editor
+    RecoverAtLineEnd,
+    /// the string goes until the end of the source code
+    RecoverAtEof,
 }
 
-impl Scanner {
-    /// scan the provided source code and return a list of tokens in the ScannerData structure.
-    /// The ScannerData is not returned in the Result because we want it even when there is a scan error.
-    /// We don't return an iterator because the parser needs to easily move back and forth in the token list
-    pub fn run(
-        &mut self,
-        source: &str,
-        config: &ScannerConfig,
-        data: &mut ScannerData,
-    ) -> Result<(), ScanError> {
-        data.source = source.chars().collect();
-        self.current = 0;
-        self.line = 1;
-        self.start = self.current;
-        let mut exit = false;
-        while !exit {
-            let token = self.scan_token(data, config)?;
-            match token {
-                TokenType::Eof => exit = true,
-                TokenType::Ignore => self.start = self.current,
-                TokenType::NewLine => (),
-                _ => self.add_token(token, data),
-            }
-        }
-        Ok(())
-    }
-    fn add_token(&mut self, token: TokenType, data: &mut ScannerData) {
-        data.token_start.push(self.start);
-        data.token_len.push(self.current - self.start);
-        data.token_types.push(token);
-        data.token_lines.push(self.line);
-        self.start = self.current;
-    }
-    fn scan_token(
-        &mut self,
-        data: &mut ScannerData,
-        config: &ScannerConfig,
-    ) -> Result<TokenType, ScanError> {
-        if self.current >= data.source.len() {
-            return Ok(TokenType::Eof);
+/// What the scanner does with a control character other than tabulations and new lines
+/// outside of a token, see ScannerConfig::control_chars
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ControlCharPolicy {
+    /// the character is an unknown token (default)
+    #[default]
+    Error,
+    /// the character is skipped like a space, with a ControlCharacter warning
+    /// in ScannerData::warnings
+    Warning,
+    /// the character is skipped like a space
+    PassThrough,
+}
+
+/// What is missing at the end of a source code being typed, see Scanner::run_partial
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IncompleteKind {
+    /// a string literal, or another quoted token, without its closing delimiter
+    String,
+    /// a multi-line comment without its end marker
+    Comment,
+    /// an opening bracket without its closing bracket
+    Bracket,
+    /// a block opened by a keyword, like "do" in Lua, without its closing keyword.
+    /// See IndentRules::is_input_complete
+    Block,
+}
+
+/// The result of Scanner::run_partial
+pub struct PartialScan<'a> {
+    /// the tokens of the source code, including the truncated last token if any
+    pub data: ScannerData<'a>,
+    /// None when the source code is complete. Otherwise a REPL should ask for more input
+    pub incomplete: Option<IncompleteKind>,
+}
+
+/// How the lines are numbered in ScannerData::token_lines, Token::line and the scan errors,
+/// see Scanner::with_positions. Columns and offsets are always counted from 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PositionConvention {
+    /// the first line is 1, for humans and compiler messages (default)
+    #[default]
+    OneBased,
+    /// the first line is 0, like in the Language Server Protocol
+    ZeroBased,
+}
+
+impl PositionConvention {
+    /// the number of the first line
+    pub fn first_line(self) -> usize {
+        match self {
+            PositionConvention::OneBased => 1,
+            PositionConvention::ZeroBased => 0,
         }
-        if let Some(token) = self.scan_comment(config, data) {
-            return Ok(token);
+    }
+}
+
+/// number of tokens scanned between two checks of the cancellation flag
+const CANCEL_CHECK_INTERVAL: usize = 1024;
+
+/// Limits protecting a server scanning untrusted source code, see Scanner::with_limits.
+/// A scan exceeding a limit stops with a ScanError::LimitExceeded error, even with Scanner::run_lossy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScanLimits {
+    /// maximum length of the source code in bytes. Sources longer than 4 GB are always
+    /// rejected since the token spans are stored on 32 bits
+    pub max_input_len: usize,
+    /// maximum number of tokens. With Scanner::run_parallel, the limit applies to each chunk
+    pub max_tokens: usize,
+    /// maximum length of a string literal, string part or comment in bytes
+    pub max_literal_len: usize,
+    /// maximum depth of nested comments, see ScannerConfig::nested_comments
+    pub max_comment_nesting: usize,
+}
+
+impl ScanLimits {
+    /// no limit, the default
+    pub const NONE: ScanLimits = ScanLimits {
+        max_input_len: usize::MAX,
+        max_tokens: usize::MAX,
+        max_literal_len: usize::MAX,
+        max_comment_nesting: usize::MAX,
+    };
+}
+
+impl Default for ScanLimits {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+/// Lazy iterator over the tokens of a source code. See Scanner::tokens
+pub struct Tokens<'a> {
+    scanner: Scanner,
+    source: &'a str,
+    config: Cow<'a, CompiledConfig<'a>>,
+    done: bool,
+}
+
+impl<'a> Tokens<'a> {
+    /// the state of the scanner after the last returned token.
+    /// Resume the scan later with Tokens::with_state
+    pub fn state(&self) -> ScannerState {
+        self.scanner.state()
+    }
+    /// resume a scan from a saved state.
+    /// The source code must be the same as the scanned one up to the state offset,
+    /// but it can be longer, for example when the source code is received in chunks
+    pub fn with_state(mut self, state: &ScannerState) -> Self {
+        self.scanner.set_state(state);
+        self
+    }
+    /// build the value of a token produced by this iterator (allocates a String)
+    pub fn value(&self, token: &Token) -> TokenType {
+        self.config.token_value(token.kind, token.text(self.source))
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Result<Token, ScanError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
         }
-        if let Some(token) = self.scan_newline(data) {
-            return Ok(token);
+        match self.scanner.scan_next(self.source, &self.config) {
+            Ok(TokenKind::Eof) => {
+                self.done = true;
+                None
+            }
+            Ok(kind) => Some(Ok(self.scanner.token(kind))),
+            Err((_, error)) => {
+                self.done = true;
+                Some(Err(error))
+            }
         }
-        if let Some(token) = self.scan_space(data) {
-            return Ok(token);
+    }
+}
+
+#[derive(Default)]
+pub struct ScannerData<'a> {
+    /// complete source code, borrowed from the caller of Scanner::run
+    pub source: &'a str,
+    /// resulting list of tokens.
+    /// Empty if the scanner was configured with `with_values(false)`
+    pub token_types: Vec<TokenType>,
+    /// type of each token, without its value. Always filled.
+    pub token_kinds: Vec<TokenKind>,
+    /// token position in the source code in bytes.
+    /// `&data.source[data.token_spans[i].range()]` is the token text
+    pub token_spans: Vec<Span>,
+    /// token start line in the source code, in the convention of positions, see token_line
+    pub token_lines: Vec<u32>,
+    /// line of the last character of each token, see token_end_line.
+    /// Different from the start line for the multi-line comments and strings
+    pub token_end_lines: Vec<u32>,
+    /// token start column in characters from the start of its line, see token_col.
+    /// The tabulations are expanded, see Scanner::with_tab_width
+    pub token_cols: Vec<u32>,
+    /// the string literals missing their closing delimiter, in the order of the source code.
+    /// Their length in characters counts the missing delimiter, see token_len_chars
+    pub unterminated: Vec<TokenIndex>,
+    /// start offset of each line of the source code
+    pub line_index: LineIndex,
+    /// spaces and comments around each token.
+    /// Empty unless the scanner was configured with `with_trivia(true)`
+    pub token_trivia: Vec<Trivia>,
+    /// symbol of each identifier and keyword token, None for the other tokens.
+    /// Empty unless the scanner was configured with `with_interning(true)`
+    pub token_symbols: Vec<Option<Symbol>>,
+    /// name of the embedded language of each token, None for the main language.
+    /// Empty unless the configuration has embedded languages, see CompiledConfig::with_embedded
+    pub token_languages: Vec<Option<&'static str>>,
+    /// length in bytes of the string prefix of each token, like 1 for `b"bytes"`,
+    /// 0 for a token without prefix. Empty until a literal has one of the ScannerConfig::string_prefixes
+    pub token_prefixes: Vec<u32>,
+    /// the texts of the symbols. Unlike the tokens, it is not cleared between two scans
+    /// so that a name keeps the same symbol when the source code is scanned again
+    pub symbols: Interner,
+    /// problems which did not stop the scan, like the control characters
+    /// with ControlCharPolicy::Warning, in the order of the source code
+    pub warnings: Vec<ScanError>,
+    /// the scanner state to resume the scan of each line, the first line at index 0.
+    /// A line starting inside a multi-line token is resumed from the start of that token.
+    /// Empty unless the scanner was configured with `with_line_states(true)`
+    pub line_states: Vec<ScannerState>,
+    /// how the lines are numbered, in the token lines and in the lines given to
+    /// or returned by the methods. Set by the scan, see Scanner::with_positions
+    pub positions: PositionConvention,
+}
+
+impl<'a> ScannerData<'a> {
+    /// remove all the tokens and the source code, keeping the allocated memory.
+    /// Called at the start of each scan, so a ScannerData can be reused
+    /// to scan a source code many times (at every key stroke in an editor) without reallocating
+    pub fn clear(&mut self) {
+        self.source = "";
+        self.token_types.clear();
+        self.token_kinds.clear();
+        self.token_spans.clear();
+        self.token_lines.clear();
+        self.token_end_lines.clear();
+        self.token_cols.clear();
+        self.unterminated.clear();
+        self.token_trivia.clear();
+        self.token_symbols.clear();
+        self.token_languages.clear();
+        self.token_prefixes.clear();
+        self.warnings.clear();
+        self.line_states.clear();
+        self.line_index = LineIndex::default();
+    }
+    /// number of tokens
+    pub fn len(&self) -> usize {
+        self.token_kinds.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.token_kinds.is_empty()
+    }
+    /// the kind, position and line of a token, or None if the index is out of range.
+    /// The value is in token_types
+    pub fn get(&self, index: usize) -> Option<Token> {
+        Some(Token {
+            kind: *self.token_kinds.get(index)?,
+            span: self.token_spans[index],
+            line: self.token_lines[index] as usize,
+        })
+    }
+    /// iterate over the tokens
+    pub fn iter(&self) -> impl Iterator<Item = Token> + '_ {
+        (0..self.len()).filter_map(|i| self.get(i))
+    }
+    /// the exact text of a token in the source code, including delimiters and escape sequences.
+    /// The decoded value is in token_types
+    pub fn raw_text(&self, index: usize) -> &str {
+        &self.source[self.token_spans[index].range()]
+    }
+    /// the text of a token in the source code. Same as raw_text
+    pub fn token_text(&self, index: usize) -> &str {
+        self.raw_text(index)
+    }
+    /// the prefix of a string or character literal, like "b" for `b"bytes"`,
+    /// see ScannerConfig::string_prefixes. None for other tokens or a literal without prefix
+    pub fn string_prefix(&self, index: usize) -> Option<&'a str> {
+        let len = *self.token_prefixes.get(index)? as usize;
+        let start = self.token_spans[index].start();
+        (len > 0).then(|| &self.source[start..start + len])
+    }
+    /// the length of a token in the source code, in bytes
+    pub fn token_len_bytes(&self, index: usize) -> usize {
+        self.token_spans[index].len()
+    }
+    /// the length of a token in the source code, in characters (not in bytes!).
+    /// Not always the length of the token value: for TokenType::StringLiteral("aa")
+    /// the value length is 2 but the token length including the quotes is 4.
+    /// An unterminated string counts its missing closing delimiter
+    pub fn token_len_chars(&self, index: usize) -> usize {
+        let missing = self.unterminated.binary_search(&TokenIndex::new(index)).is_ok();
+        self.raw_text(index).chars().count() + usize::from(missing)
+    }
+    /// the length in characters of each token, see token_len_chars
+    pub fn token_lens_chars(&self) -> Vec<usize> {
+        (0..self.len()).map(|index| self.token_len_chars(index)).collect()
+    }
+    /// the memory used by the scanned data, without the source code
+    pub fn memory_usage(&self) -> MemoryUsage {
+        fn bytes<T>(list: &Vec<T>) -> usize {
+            list.capacity() * std::mem::size_of::<T>()
         }
-        if let Some(token) = self.scan_symbol(data, config) {
-            return Ok(token);
+        MemoryUsage {
+            tokens: bytes(&self.token_kinds)
+                + bytes(&self.token_spans)
+                + bytes(&self.token_lines)
+                + bytes(&self.token_end_lines)
+                + bytes(&self.token_cols)
+                + bytes(&self.unterminated),
+            values: bytes(&self.token_types)
+                + self.token_types.iter().map(TokenType::heap_size).sum::<usize>(),
+            lines: self.line_index.memory_usage(),
+            extra: bytes(&self.token_trivia)
+                + bytes(&self.token_symbols)
+                + bytes(&self.token_languages)
+                + bytes(&self.token_prefixes)
+                + bytes(&self.warnings)
+                + bytes(&self.line_states)
+                + self.symbols.memory_usage(),
         }
-        if let Some(token) = self.scan_keyword(data, config) {
-            return Ok(token);
+    }
+    /// the line of the start of a token, in the convention of positions
+    pub fn token_line(&self, index: usize) -> usize {
+        self.token_lines[index] as usize
+    }
+    /// the line of the last character of a token, in the same convention as token_line
+    pub fn token_end_line(&self, index: usize) -> usize {
+        self.token_end_lines[index] as usize
+    }
+    /// the position of a token in the source code, in characters.
+    /// Computed from the start of its line, see LineIndex::char_offset
+    pub fn token_start_char(&self, index: usize) -> usize {
+        self.line_index.char_offset(self.source, self.token_spans[index].start())
+    }
+    /// the position in characters of each token, see token_start_char
+    pub fn token_starts_chars(&self) -> Vec<usize> {
+        (0..self.len()).map(|index| self.token_start_char(index)).collect()
+    }
+    /// the column of a token in its line, starting at 0
+    pub fn token_col(&self, index: usize) -> usize {
+        self.token_cols[index] as usize
+    }
+    /// the position of a token in the source code, in bytes
+    pub fn token_range(&self, index: usize) -> Range<usize> {
+        self.token_spans[index].range()
+    }
+    /// the index of the token containing a byte offset, None between two tokens.
+    /// Uses a binary search over the token starts
+    pub fn token_at(&self, offset: usize) -> Option<usize> {
+        let index = self.token_spans.partition_point(|span| span.start() <= offset).checked_sub(1)?;
+        (offset < self.token_spans[index].end()).then_some(index)
+    }
+    /// the index of the token containing a position, with a line in the convention of positions
+    /// and a column in bytes
+    pub fn token_at_line_col(&self, line: usize, col: usize) -> Option<usize> {
+        self.token_at(self.line_index.line_col_to_offset(self.index_line(line)?, col)?)
+    }
+    /// convert a line in the convention of positions to a line of the LineIndex, starting at 1
+    pub fn index_line(&self, line: usize) -> Option<usize> {
+        (line + 1).checked_sub(self.positions.first_line())
+    }
+    /// the line containing a byte offset, in the convention of positions
+    pub fn offset_line(&self, offset: usize) -> usize {
+        self.line_index.offset_to_line_col(offset).0 - 1 + self.positions.first_line()
+    }
+    /// the indices and spans of the tokens of a kind
+    pub fn find_all(&self, kind: TokenKind) -> impl Iterator<Item = (usize, Span)> + '_ {
+        (0..self.len())
+            .filter(move |i| self.token_kinds[*i] == kind)
+            .map(|i| (i, self.token_spans[i]))
+    }
+    /// the indices and spans of the tokens with the given text, whatever their kind
+    pub fn find_text<'s>(&'s self, text: &'s str) -> impl Iterator<Item = (usize, Span)> + 's {
+        (0..self.len())
+            .filter(move |i| self.raw_text(*i) == text)
+            .map(|i| (i, self.token_spans[i]))
+    }
+    /// the identifiers and keywords starting with a prefix, sorted and without duplicates,
+    /// for a basic completion in an editor. The word being typed, i.e. the token containing
+    /// or ending at cursor_offset, is not a candidate, but its other occurrences are
+    pub fn completion_candidates(&self, prefix: &str, cursor_offset: usize) -> Vec<&'a str> {
+        let source = self.source;
+        let mut candidates: Vec<&'a str> = (0..self.len())
+            .filter(|i| matches!(self.token_kinds[*i], TokenKind::Identifier | TokenKind::Keyword))
+            .filter(|i| {
+                let span = self.token_spans[*i];
+                cursor_offset < span.start() || cursor_offset > span.end()
+            })
+            .map(|i| &source[self.token_spans[i].range()])
+            .filter(|text| text.starts_with(prefix))
+            .collect();
+        candidates.sort_unstable();
+        candidates.dedup();
+        candidates
+    }
+    /// the indices and spans of the tokens overlapping a part of the source code.
+    /// With an empty span, the token containing its position, if any
+    pub fn tokens_in_range(&self, span: Span) -> impl Iterator<Item = (usize, Span)> + '_ {
+        let first = self.token_spans.partition_point(|t| t.end() <= span.start());
+        (first..self.len())
+            .map(|i| (i, self.token_spans[i]))
+            .take_while(move |(_, t)| t.start() < span.end() || (t.start() == span.start() && span.is_empty()))
+    }
+    /// the parts of the tokens on a line, in the same convention as token_lines.
+    /// A multi-line comment or string is split in one part per line, so that a text widget
+    /// can paint a single visible line. The line endings and the empty parts are skipped
+    pub fn line_tokens(&self, line: usize) -> impl Iterator<Item = TokenSlice<'a>> + '_ {
+        let mut first = self.token_lines.partition_point(|l| (*l as usize) < line);
+        if first > 0 && self.token_end_line(first - 1) >= line {
+            // a multi-line token started on a previous line
+            first -= 1;
         }
-        if let Some(token) = self.scan_string(data)? {
-            return Ok(token);
+        let end = self.token_lines.partition_point(|l| *l as usize <= line);
+        let source = self.source;
+        (first..end).filter_map(move |index| {
+            let span = self.token_spans[index];
+            let skipped = line - self.token_line(index);
+            let mut start = span.start();
+            for _ in 0..skipped {
+                start += source[start..span.end()].find('\n')? + 1;
+            }
+            let rest = &source[start..span.end()];
+            let text = match rest.find('\n') {
+                Some(pos) => rest[..pos].strip_suffix('\r').unwrap_or(&rest[..pos]),
+                None => rest,
+            };
+            (!text.is_empty()).then(|| TokenSlice {
+                index,
+                kind: self.token_kinds[index],
+                span: Span::new(start, text.len()),
+                column: if skipped == 0 { self.token_col(index) } else { 0 },
+                text,
+            })
+        })
+    }
+    /// the byte offset of the first character of each line, the first line at index 0
+    /// whatever the convention of positions. Computed once per scan, see LineIndex
+    pub fn line_starts(&self) -> &[usize] {
+        self.line_index.line_starts()
+    }
+    /// the text of a line without its line ending, in the convention of positions.
+    /// None if the line does not exist
+    pub fn line_text(&self, line: usize) -> Option<&'a str> {
+        let source = self.source;
+        let range = self.line_index.line_range(source, self.index_line(line)?)?;
+        source.get(range)
+    }
+    /// convert a byte offset in the source code to a line and a column in UTF-16 code units,
+    /// both starting at 0, as expected by the Language Server Protocol.
+    /// An offset after the end of the source code is moved to its end,
+    /// and an offset inside a character to the start of the character
+    pub fn utf16_position(&self, offset: usize) -> (usize, usize) {
+        let mut offset = offset.min(self.source.len());
+        while !self.source.is_char_boundary(offset) {
+            offset -= 1;
         }
-        if let Some(token) = self.scan_identifier(data) {
-            return Ok(token);
+        let (line, column) = self.line_index.offset_to_line_col(offset);
+        let column = self.source[offset - column..offset]
+            .chars()
+            .map(char::len_utf16)
+            .sum();
+        (line - 1, column)
+    }
+    /// the UTF-16 positions of the start and the end of a token. See utf16_position
+    pub fn token_utf16_range(&self, index: usize) -> ((usize, usize), (usize, usize)) {
+        let span = self.token_spans[index];
+        (self.utf16_position(span.start()), self.utf16_position(span.end()))
+    }
+    /// the symbol of an identifier or keyword token. Requires `Scanner::with_interning(true)`
+    pub fn token_symbol(&self, index: usize) -> Option<Symbol> {
+        self.token_symbols.get(index).copied().flatten()
+    }
+    /// the text of a symbol
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        self.symbols.resolve(symbol)
+    }
+    /// the spaces and comments before a token. Requires `Scanner::with_trivia(true)`
+    pub fn leading_trivia(&self, index: usize) -> &str {
+        &self.source[self.token_trivia[index].leading.range()]
+    }
+    /// the spaces and comments after a token, until the end of its line.
+    /// Requires `Scanner::with_trivia(true)`
+    pub fn trailing_trivia(&self, index: usize) -> &str {
+        &self.source[self.token_trivia[index].trailing.range()]
+    }
+    /// the source code rebuilt from the text of the tokens, and from their trivia when computed.
+    /// Gives back the exact source code when scanned with `Scanner::with_whitespace(true)`,
+    /// or with `Scanner::with_trivia(true)` if there is at least one token,
+    /// so that rewriting tools can edit the tokens and write the result
+    pub fn reconstruct(&self) -> String {
+        let mut out = String::with_capacity(self.source.len());
+        for index in 0..self.len() {
+            let trivia = self.token_trivia.get(index);
+            if let Some(trivia) = trivia {
+                out.push_str(&self.source[trivia.leading.range()]);
+            }
+            out.push_str(self.raw_text(index));
+            if let Some(trivia) = trivia {
+                out.push_str(&self.source[trivia.trailing.range()]);
+            }
+        }
+        out
+    }
+    /// add the tokens of a chunk scanned separately by Scanner::run_parallel
+    fn append(&mut self, mut chunk: ScannerData) {
+        if !self.token_prefixes.is_empty() || !chunk.token_prefixes.is_empty() {
+            self.token_prefixes.resize(self.len(), 0);
+            chunk.token_prefixes.resize(chunk.len(), 0);
+            self.token_prefixes.append(&mut chunk.token_prefixes);
+        }
+        self.token_types.append(&mut chunk.token_types);
+        self.token_kinds.append(&mut chunk.token_kinds);
+        self.token_spans.append(&mut chunk.token_spans);
+        self.token_lines.append(&mut chunk.token_lines);
+        self.token_end_lines.append(&mut chunk.token_end_lines);
+        self.token_cols.append(&mut chunk.token_cols);
+        let offset = self.len();
+        self.unterminated.extend(chunk.unterminated.iter().map(|index| TokenIndex::new(offset + index.index())));
+        self.token_languages.append(&mut chunk.token_languages);
+        self.warnings.append(&mut chunk.warnings);
+        for symbol in chunk.token_symbols {
+            let symbol = symbol.map(|symbol| self.symbols.intern(chunk.symbols.resolve(symbol)));
+            self.token_symbols.push(symbol);
+        }
+    }
+    /// compute the trivia of the tokens added since the last call.
+    /// The text after the last token is its trailing trivia
+    fn attach_trivia(&mut self) {
+        let mut leading_start = match self.token_trivia.last() {
+            Some(trivia) => trivia.trailing.end(),
+            None => 0,
+        };
+        for i in self.token_trivia.len()..self.token_spans.len() {
+            let span = self.token_spans[i];
+            let next_start = self
+                .token_spans
+                .get(i + 1)
+                .map_or(self.source.len(), |next| next.start());
+            let gap = &self.source[span.end()..next_start];
+            let trailing_len = match gap.find('\n') {
+                Some(pos) if i + 1 < self.token_spans.len() => pos + 1,
+                _ => gap.len(),
+            };
+            self.token_trivia.push(Trivia {
+                leading: Span::new(leading_start, span.start() - leading_start),
+                trailing: Span::new(span.end(), trailing_len),
+            });
+            leading_start = span.end() + trailing_len;
+        }
+    }
+    #[cfg(feature = "json")]
+    /// the tokens as a JSON-like value, with the same fields as this structure,
+    /// except the source code and the line index. Symbols are stored as indices in the "symbols" list.
+    /// To cache the tokens on disk or send them to another process
+    pub fn to_value(&self) -> Value {
+        let numbers = |list: &[u32]| Value::Array(list.iter().map(|n| (*n as usize).into()).collect());
+        let span = |span: &Span| Value::from(vec![span.start(), span.len()]);
+        // so that the data can be read back
+        self.token_languages.iter().flatten().for_each(|name| {
+            TokenKind::register(name);
+        });
+        Value::Object(vec![
+            (
+                "token_types".to_owned(),
+                Value::Array(self.token_types.iter().map(TokenType::to_value).collect()),
+            ),
+            (
+                "token_kinds".to_owned(),
+                Value::from(self.token_kinds.iter().map(|k| kind_name(*k)).collect::<Vec<_>>()),
+            ),
+            (
+                "token_spans".to_owned(),
+                Value::Array(self.token_spans.iter().map(span).collect()),
+            ),
+            ("token_lines".to_owned(), numbers(&self.token_lines)),
+            ("token_end_lines".to_owned(), numbers(&self.token_end_lines)),
+            ("token_cols".to_owned(), numbers(&self.token_cols)),
+            (
+                "unterminated".to_owned(),
+                Value::from(self.unterminated.iter().map(|index| index.index()).collect::<Vec<_>>()),
+            ),
+            (
+                "token_trivia".to_owned(),
+                Value::Array(
+                    self.token_trivia
+                        .iter()
+                        .map(|t| Value::Array(vec![span(&t.leading), span(&t.trailing)]))
+                        .collect(),
+                ),
+            ),
+            (
+                "token_symbols".to_owned(),
+                Value::from(
+                    self.token_symbols
+                        .iter()
+                        .map(|s| Value::from(s.map(|s| s.index())))
+                        .collect::<Vec<_>>(),
+                ),
+            ),
+            (
+                "token_languages".to_owned(),
+                Value::from(self.token_languages.to_vec()),
+            ),
+            ("token_prefixes".to_owned(), numbers(&self.token_prefixes)),
+            (
+                "symbols".to_owned(),
+                Value::from(self.symbols.iter().collect::<Vec<_>>()),
+            ),
+            (
+                "warnings".to_owned(),
+                Value::Array(self.warnings.iter().map(ScanError::to_value).collect()),
+            ),
+            (
+                "line_states".to_owned(),
+                Value::Array(self.line_states.iter().map(ScannerState::to_value).collect()),
+            ),
+            ("first_line".to_owned(), self.positions.first_line().into()),
+        ])
+    }
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> String {
+        self.to_value().to_string()
+    }
+    #[cfg(feature = "json")]
+    /// rebuild the tokens of a source code from the output of to_value.
+    /// Returns None if the value is not valid for this source code, for example
+    /// with a token outside of the source code, so that reading the tokens never panics.
+    /// The custom token classes and embedded languages must be known, see TokenKind::register
+    pub fn from_value(value: &Value, source: &'a str) -> Option<Self> {
+        let list = |key| value.get(key).and_then(Value::as_array);
+        let numbers = |key| {
+            list(key)?
+                .iter()
+                .map(|n| n.as_usize().and_then(|n| u32::try_from(n).ok()))
+                .collect::<Option<Vec<_>>>()
+        };
+        let span = |value: &Value| match value.as_array()? {
+            [start, len] => Some(Span::new(start.as_usize()?, len.as_usize()?)),
+            _ => None,
+        };
+        let mut symbols = Interner::new();
+        for text in list("symbols")? {
+            symbols.intern(text.as_str()?);
+        }
+        let token_symbols = list("token_symbols")?
+            .iter()
+            .map(|s| match s {
+                Value::Null => Some(None),
+                _ => Some(Some(symbols.symbol(s.as_usize()?)?)),
+            })
+            .collect::<Option<_>>()?;
+        let token_trivia = list("token_trivia")?
+            .iter()
+            .map(|t| match t.as_array()? {
+                [leading, trailing] => Some(Trivia {
+                    leading: span(leading)?,
+                    trailing: span(trailing)?,
+                }),
+                _ => None,
+            })
+            .collect::<Option<_>>()?;
+        // missing in the data saved before embedded languages were supported
+        let token_languages = match list("token_languages") {
+            Some(languages) => languages
+                .iter()
+                .map(|l| match l {
+                    Value::Null => Some(None),
+                    _ => Some(Some(registered_name(l.as_str()?)?)),
+                })
+                .collect::<Option<_>>()?,
+            None => Vec::new(),
+        };
+        // missing in the data saved before the warnings were added
+        let warnings = match list("warnings") {
+            Some(warnings) => warnings.iter().map(ScanError::from_value).collect::<Option<_>>()?,
+            None => Vec::new(),
+        };
+        let line_states = match list("line_states") {
+            Some(states) => states.iter().map(ScannerState::from_value).collect::<Option<_>>()?,
+            None => Vec::new(),
+        };
+        // missing in the data saved before the position conventions were added
+        let positions = match value.get("first_line").map(Value::as_usize) {
+            None | Some(Some(1)) => PositionConvention::OneBased,
+            Some(Some(0)) => PositionConvention::ZeroBased,
+            Some(_) => return None,
+        };
+        let token_spans: Vec<Span> = list("token_spans")?.iter().map(span).collect::<Option<_>>()?;
+        let line_index = LineIndex::new(source);
+        // missing in the data saved before the columns were added. Computed without tab expansion
+        let token_cols = match numbers("token_cols") {
+            Some(cols) => cols,
+            None => token_spans
+                .iter()
+                .map(|span| {
+                    let line = line_index.offset_to_line_col(span.start()).0;
+                    let line_start = line_index.line_start(line).unwrap_or(0);
+                    let before = source.get(line_start..span.start()).unwrap_or("");
+                    compact(before.chars().count())
+                })
+                .collect(),
+        };
+        let token_lines = numbers("token_lines")?;
+        // missing in the data saved before the end lines were added
+        let token_end_lines = match numbers("token_end_lines") {
+            Some(lines) => lines,
+            None => token_spans
+                .iter()
+                .zip(&token_lines)
+                .map(|(span, line)| {
+                    let text = source.get(span.range()).unwrap_or("");
+                    let text = text.strip_suffix('\n').unwrap_or(text);
+                    compact(*line as usize + text.matches('\n').count())
+                })
+                .collect(),
+        };
+        let data = Self {
+            source,
+            token_types: list("token_types")?
+                .iter()
+                .map(TokenType::from_value)
+                .collect::<Option<_>>()?,
+            token_kinds: list("token_kinds")?
+                .iter()
+                .map(|kind| kind_from_name(kind.as_str()?))
+                .collect::<Option<_>>()?,
+            token_spans,
+            token_lines,
+            token_end_lines,
+            token_cols,
+            // missing in the data saved before the lengths were computed on demand
+            unterminated: numbers("unterminated")
+                .unwrap_or_default()
+                .into_iter()
+                .map(|index| TokenIndex::new(index as usize))
+                .collect(),
+            line_index,
+            token_trivia,
+            token_symbols,
+            token_languages,
+            // missing in the data saved before the string prefixes were added
+            token_prefixes: numbers("token_prefixes").unwrap_or_default(),
+            symbols,
+            warnings,
+            line_states,
+            positions,
+        };
+        data.is_valid().then_some(data)
+    }
+    /// whether the lists have the same length and the spans are inside the source code
+    #[cfg(feature = "json")]
+    fn is_valid(&self) -> bool {
+        let len = self.len();
+        let span_valid = |span: &Span| {
+            span.start()
+                .checked_add(span.len())
+                .is_some_and(|end| self.source.get(span.start()..end).is_some())
+        };
+        let optional = [
+            self.token_types.len(),
+            self.token_trivia.len(),
+            self.token_symbols.len(),
+            self.token_languages.len(),
+            self.token_prefixes.len(),
+        ];
+        let required = [
+            self.token_spans.len(),
+            self.token_lines.len(),
+            self.token_end_lines.len(),
+            self.token_cols.len(),
+        ];
+        required.iter().all(|l| *l == len)
+            && optional.iter().all(|l| *l == 0 || *l == len)
+            && self.token_spans.iter().all(span_valid)
+            && self.token_prefixes.iter().zip(&self.token_spans).all(|(len, span)| {
+                *len as usize <= span.len() && span_valid(&Span::new(span.start(), *len as usize))
+            })
+            && self
+                .token_trivia
+                .iter()
+                .all(|trivia| span_valid(&trivia.leading) && span_valid(&trivia.trailing))
+            && self.warnings.iter().all(|warning| span_valid(&warning.span().span))
+            && self
+                .line_states
+                .iter()
+                .all(|state| self.source.is_char_boundary(state.offset))
+    }
+    #[cfg(feature = "json")]
+    pub fn from_json(text: &str, source: &'a str) -> Option<Self> {
+        Self::from_value(&Value::from_json(text).ok()?, source)
+    }
+    pub fn dump(&self, out: &mut dyn Write) {
+        for (i, kind) in self.token_kinds.iter().enumerate() {
+            match self.token_types.get(i) {
+                Some(token) => writeln!(out, "[#{:03} line {}] {:?}", i, self.token_lines[i], *token),
+                None => writeln!(
+                    out,
+                    "[#{:03} line {}] {:?}({:?})",
+                    i,
+                    self.token_lines[i],
+                    kind,
+                    self.raw_text(i)
+                ),
+            }
+            .ok();
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Scanner {
+    // start of parsing position (in bytes)
+    start: usize,
+    // start of parsing position (in characters)
+    start_char: usize,
+    // line of the parsing start position
+    start_line: usize,
+    // position during parsing of current token (in bytes)
+    current: usize,
+    // position during parsing of current token (in characters)
+    current_char: usize,
+    // current line in file
+    line: usize,
+    // whether to build the token values in ScannerData::token_types
+    values: bool,
+    // whether to produce the comment tokens
+    comments: bool,
+    // whether to produce the new line tokens
+    newlines: bool,
+    // whether to produce the space and new line tokens
+    whitespace: bool,
+    // whether to compute the trivia of each token
+    trivia: bool,
+    // whether to intern the identifiers and keywords in ScannerData::symbols
+    interning: bool,
+    // stack of lexer modes for interpolated strings and embedded languages
+    modes: Vec<Mode>,
+    // value of the last token produced by a TokenRule
+    rule_value: Option<TokenType>,
+    // embedded language of the last token
+    token_language: Option<&'static str>,
+    // length of the string prefix of the last token
+    prefix_len: usize,
+    // whether a slash would start a regex literal, depending on the last significant token
+    regex_allowed: bool,
+    // whether the last multi-line comment reached the end of the source code without its end marker
+    unclosed_comment: bool,
+    // warnings of the last token, moved to ScannerData::warnings when the token is accepted
+    warnings: Vec<ScanError>,
+    // limits for untrusted source code
+    limits: ScanLimits,
+    // flag stopping the scan when set by another thread
+    cancel: Option<Arc<AtomicBool>>,
+    // numbering of the lines in the results. The line fields above always start at 1
+    positions: PositionConvention,
+    // number of columns between two tab stops
+    tab_width: usize,
+    // byte offset and column of the last token added, to compute the next column incrementally
+    last_column: Option<(usize, usize)>,
+    // whether to record the state at the start of each line in ScannerData::line_states
+    record_line_states: bool,
+    // states of the lines started since the scan start, moved to ScannerData::line_states
+    line_states: Vec<ScannerState>,
+    // state at the last position between two tokens
+    boundary: ScannerState,
+}
+
+/// The position of the scanner and its lexer modes between two tokens,
+/// to pause a scan and resume it later, possibly after saving it with to_json.
+/// See Scanner::state and Tokens::with_state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScannerState {
+    /// position in the source code in bytes
+    pub offset: usize,
+    /// position in the source code in characters
+    pub char_offset: usize,
+    /// current line
+    pub line: usize,
+    /// nested interpolated strings, interpolations and embedded languages
+    modes: Vec<Mode>,
+    /// whether a slash would start a regex literal
+    regex_allowed: bool,
+}
+
+impl Default for ScannerState {
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            char_offset: 0,
+            line: 1,
+            modes: Vec::new(),
+            regex_allowed: true,
+        }
+    }
+}
+
+impl ScannerState {
+    #[cfg(feature = "json")]
+    /// the state as a JSON object :
+    /// `{"offset":12,"char_offset":10,"line":2,"modes":[{"embedded":0},"string",0],"regex":true}`
+    pub fn to_json(&self) -> String {
+        self.to_value().to_string()
+    }
+    #[cfg(feature = "json")]
+    /// the state as a Value, see to_json
+    pub fn to_value(&self) -> Value {
+        let modes = self
+            .modes
+            .iter()
+            .map(|mode| match mode {
+                Mode::InterpolatedString => Value::from("string"),
+                Mode::Interpolation(depth) => Value::from(*depth),
+                Mode::Embedded(index) => {
+                    Value::Object(vec![("embedded".to_owned(), Value::from(*index))])
+                }
+            })
+            .collect::<Vec<_>>();
+        Value::Object(vec![
+            ("offset".to_owned(), self.offset.into()),
+            ("char_offset".to_owned(), self.char_offset.into()),
+            ("line".to_owned(), self.line.into()),
+            ("modes".to_owned(), modes.into()),
+            ("regex".to_owned(), self.regex_allowed.into()),
+        ])
+    }
+    #[cfg(feature = "json")]
+    /// read a state saved with to_value. Returns None if the value is not a valid state
+    pub fn from_value(value: &Value) -> Option<Self> {
+        let modes = value
+            .get("modes")?
+            .as_array()?
+            .iter()
+            .map(|mode| match mode {
+                Value::String(s) if s == "string" => Some(Mode::InterpolatedString),
+                Value::Object(_) => mode.get("embedded")?.as_usize().map(Mode::Embedded),
+                _ => mode.as_usize().map(Mode::Interpolation),
+            })
+            .collect::<Option<_>>()?;
+        Some(Self {
+            offset: value.get("offset")?.as_usize()?,
+            char_offset: value.get("char_offset")?.as_usize()?,
+            line: value.get("line")?.as_usize()?,
+            modes,
+            regex_allowed: match value.get("regex") {
+                Some(regex) => regex.as_bool()?,
+                None => true,
+            },
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// inside an interpolated string
+    InterpolatedString,
+    /// inside an interpolation, with the number of open brackets
+    Interpolation(usize),
+    /// inside a region of an embedded language, with its index in the embedding language
+    Embedded(usize),
+}
+
+impl Default for Scanner {
+    fn default() -> Self {
+        Self {
+            start: 0,
+            start_char: 0,
+            start_line: 1,
+            current: 0,
+            current_char: 0,
+            line: 1,
+            values: true,
+            comments: true,
+            newlines: false,
+            whitespace: false,
+            trivia: false,
+            interning: false,
+            modes: Vec::new(),
+            rule_value: None,
+            token_language: None,
+            prefix_len: 0,
+            regex_allowed: true,
+            unclosed_comment: false,
+            warnings: Vec::new(),
+            limits: ScanLimits::NONE,
+            cancel: None,
+            positions: PositionConvention::OneBased,
+            tab_width: 1,
+            last_column: None,
+            record_line_states: false,
+            line_states: Vec::new(),
+            boundary: ScannerState::default(),
+        }
+    }
+}
+
+/// The language definition used by the scanner.
+/// The default type parameters allow to declare a configuration as a constant.
+/// Use OwnedScannerConfig to build a configuration at runtime.
+#[derive(Debug, Clone, PartialEq)]
+// the character predicates are compared by address. Good enough to compare configurations
+// using the default predicates
+#[allow(unpredictable_function_pointer_comparisons)]
+pub struct ScannerConfig<L = &'static [&'static str], S = &'static str> {
+    /// list of keywords, in any order
+    pub keywords: L,
+    /// list of symbols, in any order. The longest symbol matching at a position wins,
+    /// so "..." is recognized even if "." comes first
+    pub symbols: L,
+    /// symbols producing TokenType::Punctuation instead of TokenType::Symbol, like "(" or ",",
+    /// to tell them from the operators. They must also be in the symbols list
+    pub punctuation: L,
+    /// words producing TokenType::BoolLiteral(true), like "true". They take precedence over the keywords
+    pub true_literals: L,
+    /// words producing TokenType::BoolLiteral(false), like "false"
+    pub false_literals: L,
+    /// words producing TokenType::NilLiteral, like "nil" or "null"
+    pub nil_literals: L,
+    /// token starting a single line comment
+    pub single_line_cmt: Option<S>,
+    /// token starting a multi line comment
+    pub multi_line_cmt_start: Option<S>,
+    /// token ending a multi line comment
+    pub multi_line_cmt_end: Option<S>,
+    /// whether multi line comments can be nested (default true).
+    /// For example `/* a /* b */ c */` is a single comment in Rust, but not in C
+    pub nested_comments: bool,
+    /// tokens starting a preprocessor directive when they are the first token of a line,
+    /// for example "#" in C. The directive ends at the end of the line
+    pub directive_prefixes: L,
+    /// tokens starting an attribute, producing a TokenType::Attribute with the whole construct.
+    /// When the prefix contains opening brackets, like "#[" in Rust or "[[" in C++,
+    /// the attribute ends at the matching closing brackets.
+    /// When it ends with an identifier character, like "#pragma", it ends at the end of the line.
+    /// Otherwise, like "@" in Python or Java, it is followed by a dotted name
+    /// and optional arguments between parentheses : `@app.route("/")`.
+    /// An attribute starts a line or follows a token which can't end an operand,
+    /// so that `a @b` is still the `@` operator applied to `a` and `b`
+    pub attribute_prefixes: L,
+    /// character joining a line with the next one when it is placed just before the new line,
+    /// for example '\\' in C or Python. Directives can span several lines this way
+    pub line_continuation: Option<char>,
+    /// tokens delimiting a string literal, for example "\"" and "'" in Lua or JavaScript.
+    /// An empty list means the double quote only. Delimiters longer than one character,
+    /// like "\"\"\"" in Python, may always span several lines
+    pub string_delimiters: L,
+    /// character delimiting a character literal, for example '\'' in C
+    pub char_delimiter: Option<char>,
+    /// whether the char delimiter followed by an identifier and no closing delimiter
+    /// is a lifetime or a label, like `'a` in Rust, producing a TokenType::Lifetime
+    pub lifetimes: bool,
+    /// prefixes that can be glued to a string or character literal, like "b" and "f" in Python
+    /// ( `b"bytes"` ) or "u8" in C++ ( `u8"text"` ). A prefix can also precede a raw string,
+    /// like "b" in Rust ( `br"bytes"` ). The prefix belongs to the literal token,
+    /// see ScannerData::string_prefix. With a prefix containing 'r' or 'R', like "r" or "br"
+    /// in Python, the escape sequences are not decoded in the value
+    pub string_prefixes: L,
+    /// token starting a raw string, in which escape sequences are not decoded.
+    /// For example "[[" in Lua or "r\"" in Rust
+    pub raw_string_start: Option<S>,
+    /// token ending a raw string, for example "]]" in Lua or "\"" in Rust
+    pub raw_string_end: Option<S>,
+    /// character that can be repeated after the first character of the raw string markers.
+    /// The start and end markers must have the same level.
+    /// For example '=' in Lua ( `[==[ ... ]==]` ) or '#' in Rust ( `r#" ... "#` )
+    pub raw_string_level: Option<char>,
+    /// token starting a heredoc string, for example "<<" in shell scripts.
+    /// It must be immediately followed by the terminator identifier, optionally quoted.
+    /// The string content starts on the next line and ends before the line containing only the terminator.
+    /// The end of the opening line belongs to the heredoc token.
+    pub heredoc_start: Option<S>,
+    /// character delimiting an interpolated string, for example '`' in JavaScript
+    pub interpolated_string: Option<char>,
+    /// token starting an interpolation inside an interpolated string, for example "${"
+    pub interpolation_start: Option<S>,
+    /// token ending an interpolation, for example "}".
+    /// The last character of interpolation_start is considered as an opening bracket
+    /// to handle nested brackets inside the interpolation.
+    pub interpolation_end: Option<S>,
+    /// whether a string literal can contain a new line (default true).
+    /// When false, a new line inside a string literal produces an UnterminatedString error.
+    pub strings_may_span_lines: bool,
+    /// what to do with a string literal, raw string, heredoc or quoted identifier without its
+    /// closing delimiter (default UnterminatedStringPolicy::Error). With a recover policy,
+    /// the truncated literal is produced without error
+    pub unterminated_strings: UnterminatedStringPolicy,
+    /// what to do with a control character like '\0' or '\x1B' between two tokens
+    /// (default ControlCharPolicy::Error). Tabulations and new lines are never control characters.
+    pub control_chars: ControlCharPolicy,
+    /// whether the escape sequences of the string literals and string parts are decoded
+    /// in their value (default true). When false, the value is the exact text between the quotes,
+    /// for formatters or minifiers. Character literals are always decoded
+    pub decode_escapes: bool,
+    /// whether `/pattern/flags` is a regular expression literal, like in JavaScript.
+    /// A slash starts a regex only where a division is impossible : at the start of the code
+    /// and after a keyword or a symbol other than ")", "]", "}", "++" and "--".
+    /// The regex ends at the first slash outside of a character class ( `[...]` ) on the same line,
+    /// otherwise the slash is scanned as a symbol
+    pub regex_literals: bool,
+    /// whether decimal numbers can have an exponent, like 1e10 or 2.5E-3
+    pub scientific_notation: bool,
+    /// character that can be used to separate digits in number literals, for example '_' ( `1_000_000` ).
+    /// It is kept in the token text but ignored in the number value
+    pub number_separator: Option<char>,
+    /// whether hexadecimal numbers can have a fractional part and a binary exponent,
+    /// like 0x1.8p3 in C or Lua
+    pub hex_float: bool,
+    /// whether a number starting with 0 like 0777 is an octal number, like in C.
+    /// Octal numbers with the 0o prefix ( `0o777` ) are always recognized
+    pub legacy_octal: bool,
+    /// list of type suffixes that can follow a number literal, in any order.
+    /// For example "u32" ( `42u32` ) in Rust or "L" ( `42L` ) in C
+    pub number_suffixes: L,
+    /// delimiters of quoted identifiers, like "\"" in SQL ( `"first name"` ) or "`" in MySQL.
+    /// A quoted identifier produces a TokenType::Identifier with the name between the delimiters.
+    /// The delimiter is escaped by doubling it : `"a ""quoted"" name"`.
+    /// They take precedence over the strings
+    pub quoted_identifiers: L,
+    /// characters that can start an identifier (default is_alpha)
+    pub identifier_start: fn(char) -> bool,
+    /// characters that can continue an identifier (default is_alphanum).
+    /// For example `|c| is_alphanum(c) || c == '-'` for Lisp
+    pub identifier_char: fn(char) -> bool,
+    /// characters ignored between the tokens, except the new line (default is_space)
+    pub space_char: fn(char) -> bool,
+}
+
+impl ScannerConfig {
+    /// an empty configuration, to be used as base for constant configurations :
+    /// `ScannerConfig { keywords: &["if"], ..ScannerConfig::DEFAULT }`
+    pub const DEFAULT: ScannerConfig = ScannerConfig {
+        keywords: &[],
+        symbols: &[],
+        punctuation: &[],
+        true_literals: &[],
+        false_literals: &[],
+        nil_literals: &[],
+        single_line_cmt: None,
+        multi_line_cmt_start: None,
+        multi_line_cmt_end: None,
+        nested_comments: true,
+        directive_prefixes: &[],
+        attribute_prefixes: &[],
+        line_continuation: None,
+        string_delimiters: &[],
+        char_delimiter: None,
+        lifetimes: false,
+        string_prefixes: &[],
+        raw_string_start: None,
+        raw_string_end: None,
+        raw_string_level: None,
+        heredoc_start: None,
+        interpolated_string: None,
+        interpolation_start: None,
+        interpolation_end: None,
+        strings_may_span_lines: true,
+        unterminated_strings: UnterminatedStringPolicy::Error,
+        control_chars: ControlCharPolicy::Error,
+        decode_escapes: true,
+        regex_literals: false,
+        scientific_notation: false,
+        number_separator: None,
+        hex_float: false,
+        legacy_octal: false,
+        number_suffixes: &[],
+        quoted_identifiers: &[],
+        identifier_start: is_alpha,
+        identifier_char: is_alphanum,
+        space_char: is_space,
+    };
+}
+
+impl<L: Default, S> Default for ScannerConfig<L, S> {
+    fn default() -> Self {
+        Self {
+            keywords: L::default(),
+            symbols: L::default(),
+            punctuation: L::default(),
+            true_literals: L::default(),
+            false_literals: L::default(),
+            nil_literals: L::default(),
+            single_line_cmt: None,
+            multi_line_cmt_start: None,
+            multi_line_cmt_end: None,
+            nested_comments: true,
+            directive_prefixes: L::default(),
+            attribute_prefixes: L::default(),
+            line_continuation: None,
+            string_delimiters: L::default(),
+            char_delimiter: None,
+            lifetimes: false,
+            string_prefixes: L::default(),
+            raw_string_start: None,
+            raw_string_end: None,
+            raw_string_level: None,
+            heredoc_start: None,
+            interpolated_string: None,
+            interpolation_start: None,
+            interpolation_end: None,
+            strings_may_span_lines: true,
+            unterminated_strings: UnterminatedStringPolicy::Error,
+            control_chars: ControlCharPolicy::Error,
+            decode_escapes: true,
+            regex_literals: false,
+            scientific_notation: false,
+            number_separator: None,
+            hex_float: false,
+            legacy_octal: false,
+            number_suffixes: L::default(),
+            quoted_identifiers: L::default(),
+            identifier_start: is_alpha,
+            identifier_char: is_alphanum,
+            space_char: is_space,
+        }
+    }
+}
+
+/// A scanner configuration that can be built at runtime
+pub type OwnedScannerConfig = ScannerConfig<Vec<String>, String>;
+
+impl OwnedScannerConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_keywords<T: Into<String>>(mut self, keywords: impl IntoIterator<Item = T>) -> Self {
+        self.keywords = keywords.into_iter().map(Into::into).collect();
+        self
+    }
+    pub fn with_symbols<T: Into<String>>(mut self, symbols: impl IntoIterator<Item = T>) -> Self {
+        self.symbols = symbols.into_iter().map(Into::into).collect();
+        self
+    }
+    pub fn with_single_line_cmt(mut self, start: impl Into<String>) -> Self {
+        self.single_line_cmt = Some(start.into());
+        self
+    }
+    pub fn with_multi_line_cmt(mut self, start: impl Into<String>, end: impl Into<String>) -> Self {
+        self.multi_line_cmt_start = Some(start.into());
+        self.multi_line_cmt_end = Some(end.into());
+        self
+    }
+}
+
+impl<L: AsRef<[S]>, S: AsRef<str>> ScannerConfig<L, S> {
+    /// prepare the config for scanning. Empty symbols and keywords are ignored
+    pub fn compile(&self) -> CompiledConfig<'_> {
+        CompiledConfig::new(self)
+    }
+    /// check the configuration for entries that can never match or that conflict with each other.
+    /// Returns an empty list for a sound configuration. See ConfigIssue::is_error
+    pub fn validate(&self) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+        fn list<'a, L: AsRef<[S]>, S: AsRef<str> + 'a>(list: &'a L) -> Vec<&'a str> {
+            list.as_ref().iter().map(AsRef::as_ref).collect()
+        }
+        let (keywords, symbols) = (list(&self.keywords), list(&self.symbols));
+        let punctuation = list(&self.punctuation);
+        let lists = [
+            ("keywords", &keywords),
+            ("symbols", &symbols),
+            ("punctuation", &punctuation),
+            ("true_literals", &list(&self.true_literals)),
+            ("false_literals", &list(&self.false_literals)),
+            ("nil_literals", &list(&self.nil_literals)),
+            ("directive_prefixes", &list(&self.directive_prefixes)),
+            ("attribute_prefixes", &list(&self.attribute_prefixes)),
+            ("number_suffixes", &list(&self.number_suffixes)),
+            ("quoted_identifiers", &list(&self.quoted_identifiers)),
+            ("string_delimiters", &list(&self.string_delimiters)),
+            ("string_prefixes", &list(&self.string_prefixes)),
+        ];
+        for (field, entries) in lists {
+            if entries.iter().any(|entry| entry.is_empty()) {
+                issues.push(ConfigIssue::EmptyEntry { field });
+            }
+        }
+        let markers = [
+            ("single_line_cmt", &self.single_line_cmt),
+            ("multi_line_cmt_start", &self.multi_line_cmt_start),
+            ("multi_line_cmt_end", &self.multi_line_cmt_end),
+            ("raw_string_start", &self.raw_string_start),
+            ("raw_string_end", &self.raw_string_end),
+            ("heredoc_start", &self.heredoc_start),
+            ("interpolation_start", &self.interpolation_start),
+            ("interpolation_end", &self.interpolation_end),
+        ];
+        for (field, marker) in markers {
+            if marker.as_ref().is_some_and(|marker| marker.as_ref().is_empty()) {
+                issues.push(ConfigIssue::EmptyEntry { field });
+            }
+        }
+        for symbol in &symbols {
+            if keywords.contains(symbol) {
+                issues.push(ConfigIssue::KeywordIsSymbol(symbol.to_string()));
+            }
+            let comments = [&self.single_line_cmt, &self.multi_line_cmt_start];
+            for marker in comments.into_iter().flatten().map(AsRef::as_ref) {
+                if !marker.is_empty() && symbol.starts_with(marker) {
+                    issues.push(ConfigIssue::CommentCollision {
+                        symbol: symbol.to_string(),
+                        marker: marker.to_owned(),
+                    });
+                }
+            }
+        }
+        for symbol in punctuation {
+            if !symbol.is_empty() && !symbols.contains(&symbol) {
+                issues.push(ConfigIssue::PunctuationNotSymbol(symbol.to_owned()));
+            }
+        }
+        issues
+    }
+}
+
+/// A problem in a ScannerConfig found by ScannerConfig::validate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigIssue {
+    /// an empty keyword, symbol, prefix, suffix or delimiter in a field
+    EmptyEntry { field: &'static str },
+    /// a word both in the keywords and the symbols. The symbol wins
+    KeywordIsSymbol(String),
+    /// a symbol never matched because it starts with a comment marker,
+    /// like "-->" when comments start with "--"
+    CommentCollision { symbol: String, marker: String },
+    /// a punctuation missing from the symbols list
+    PunctuationNotSymbol(String),
+}
+
+impl ConfigIssue {
+    /// whether the issue makes a part of the configuration useless.
+    /// The other issues are warnings
+    pub fn is_error(&self) -> bool {
+        !matches!(self, ConfigIssue::KeywordIsSymbol(_))
+    }
+}
+
+impl std::fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigIssue::EmptyEntry { field } => write!(f, "empty entry in {}", field),
+            ConfigIssue::KeywordIsSymbol(word) => write!(f, "'{}' is both a keyword and a symbol", word),
+            ConfigIssue::CommentCollision { symbol, marker } => write!(
+                f,
+                "symbol '{}' is never matched because it starts a comment ('{}')",
+                symbol, marker
+            ),
+            ConfigIssue::PunctuationNotSymbol(symbol) => {
+                write!(f, "punctuation '{}' is not in the symbols", symbol)
+            }
+        }
+    }
+}
+
+/// A configuration accepted by Scanner::run : a ScannerConfig or a CompiledConfig
+pub trait Compile {
+    /// the compiled config, built on the fly for a ScannerConfig
+    fn compiled(&self) -> Cow<'_, CompiledConfig<'_>>;
+}
+
+impl<L: AsRef<[S]>, S: AsRef<str>> Compile for ScannerConfig<L, S> {
+    fn compiled(&self) -> Cow<'_, CompiledConfig<'_>> {
+        Cow::Owned(self.compile())
+    }
+}
+
+impl Compile for CompiledConfig<'_> {
+    fn compiled(&self) -> Cow<'_, CompiledConfig<'_>> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl<L: AsRef<[S]>, S: AsRef<str>> From<&ScannerConfig<L, S>> for OwnedScannerConfig {
+    fn from(config: &ScannerConfig<L, S>) -> Self {
+        let strings = |list: &L| list.as_ref().iter().map(|s| s.as_ref().to_owned()).collect();
+        let string = |s: &Option<S>| s.as_ref().map(|s| s.as_ref().to_owned());
+        Self {
+            keywords: strings(&config.keywords),
+            symbols: strings(&config.symbols),
+            punctuation: strings(&config.punctuation),
+            true_literals: strings(&config.true_literals),
+            false_literals: strings(&config.false_literals),
+            nil_literals: strings(&config.nil_literals),
+            single_line_cmt: string(&config.single_line_cmt),
+            multi_line_cmt_start: string(&config.multi_line_cmt_start),
+            multi_line_cmt_end: string(&config.multi_line_cmt_end),
+            nested_comments: config.nested_comments,
+            directive_prefixes: strings(&config.directive_prefixes),
+            attribute_prefixes: strings(&config.attribute_prefixes),
+            line_continuation: config.line_continuation,
+            string_delimiters: strings(&config.string_delimiters),
+            char_delimiter: config.char_delimiter,
+            lifetimes: config.lifetimes,
+            string_prefixes: strings(&config.string_prefixes),
+            raw_string_start: string(&config.raw_string_start),
+            raw_string_end: string(&config.raw_string_end),
+            raw_string_level: config.raw_string_level,
+            heredoc_start: string(&config.heredoc_start),
+            interpolated_string: config.interpolated_string,
+            interpolation_start: string(&config.interpolation_start),
+            interpolation_end: string(&config.interpolation_end),
+            strings_may_span_lines: config.strings_may_span_lines,
+            unterminated_strings: config.unterminated_strings,
+            control_chars: config.control_chars,
+            decode_escapes: config.decode_escapes,
+            regex_literals: config.regex_literals,
+            scientific_notation: config.scientific_notation,
+            number_separator: config.number_separator,
+            hex_float: config.hex_float,
+            legacy_octal: config.legacy_octal,
+            number_suffixes: strings(&config.number_suffixes),
+            quoted_identifiers: strings(&config.quoted_identifiers),
+            identifier_start: config.identifier_start,
+            identifier_char: config.identifier_char,
+            space_char: config.space_char,
+        }
+    }
+}
+
+/// A ScannerConfig prepared for scanning, with its symbols and keywords in lookup tables.
+/// Scanner::run compiles the ScannerConfig at each call. Build it once with
+/// ScannerConfig::compile and pass it to Scanner::run instead when scanning many times,
+/// for example at every key stroke in an editor.
+#[derive(Clone)]
+pub struct CompiledConfig<'a> {
+    punctuation: Vec<&'a str>,
+    true_literals: Vec<&'a str>,
+    false_literals: Vec<&'a str>,
+    nil_literals: Vec<&'a str>,
+    single_line_cmt: Option<&'a str>,
+    multi_line_cmt_start: Option<&'a str>,
+    multi_line_cmt_end: Option<&'a str>,
+    nested_comments: bool,
+    directive_prefixes: Vec<&'a str>,
+    attribute_prefixes: Vec<&'a str>,
+    line_continuation: Option<char>,
+    /// the string delimiters, longest first
+    string_delimiters: Vec<&'a str>,
+    char_delimiter: Option<char>,
+    lifetimes: bool,
+    string_prefixes: Vec<&'a str>,
+    raw_string_start: Option<&'a str>,
+    raw_string_end: Option<&'a str>,
+    raw_string_level: Option<char>,
+    heredoc_start: Option<&'a str>,
+    interpolated_string: Option<char>,
+    interpolation_start: Option<&'a str>,
+    interpolation_end: Option<&'a str>,
+    strings_may_span_lines: bool,
+    unterminated_strings: UnterminatedStringPolicy,
+    control_chars: ControlCharPolicy,
+    decode_escapes: bool,
+    regex_literals: bool,
+    scientific_notation: bool,
+    number_separator: Option<char>,
+    hex_float: bool,
+    legacy_octal: bool,
+    number_suffixes: Vec<&'a str>,
+    quoted_identifiers: Vec<&'a str>,
+    symbol_trie: Trie,
+    keyword_trie: Trie,
+    rules: Vec<Arc<dyn TokenRule + 'a>>,
+    fallback_rules: Vec<Arc<dyn TokenRule + 'a>>,
+    embedded: Vec<Arc<Embedded<'a>>>,
+    identifier_start: fn(char) -> bool,
+    identifier_char: fn(char) -> bool,
+    /// the ASCII identifier characters, see simd::ascii_set
+    #[cfg(feature = "simd")]
+    identifier_set: u128,
+    space_char: fn(char) -> bool,
+}
+
+impl<'a> CompiledConfig<'a> {
+    /// add a custom token rule, tried before the built-in scanners in the order of registration
+    pub fn with_rule(mut self, rule: impl TokenRule + 'a) -> Self {
+        self.rules.push(Arc::new(rule));
+        self
+    }
+    /// add a rule producing TokenType::Custom tokens of the given class from a regular expression,
+    /// tried before the built-in scanners. See the regex module for the supported syntax
+    #[cfg(feature = "regex")]
+    pub fn with_regex_rule(
+        self,
+        class: &'static str,
+        pattern: &str,
+    ) -> Result<Self, crate::regex::RegexError> {
+        let regex = crate::regex::Regex::new(pattern)?;
+        TokenKind::register(class);
+        Ok(self.with_rule(move |cursor: &mut Cursor| {
+            let len = regex.match_len(cursor.rest())?;
+            cursor.eat(&cursor.rest()[..len]);
+            Some(TokenType::Custom(class, cursor.text().to_owned()))
+        }))
+    }
+    /// add a custom token rule, tried when no built-in scanner recognizes a token,
+    /// before reporting an unknown token
+    pub fn with_fallback_rule(mut self, rule: impl TokenRule + 'a) -> Self {
+        self.fallback_rules.push(Arc::new(rule));
+        self
+    }
+    /// add a language embedded in the regions of the source code between the start and end markers,
+    /// for example JavaScript between `<script>` and `</script>` in HTML,
+    /// or Lua between "```lua" and "```" in Markdown.
+    /// The markers are recognized between two tokens and produce Symbol tokens of the embedding language.
+    /// The embedded configuration may have its own embedded languages.
+    /// The tokens of each region are tagged with the language name in ScannerData::token_languages
+    pub fn with_embedded(
+        mut self,
+        name: &'static str,
+        start: &'a str,
+        end: &'a str,
+        config: CompiledConfig<'a>,
+    ) -> Self {
+        TokenKind::register(name);
+        self.embedded.push(Arc::new(Embedded {
+            name,
+            start,
+            end,
+            config,
+        }));
+        self
+    }
+    fn new<L: AsRef<[S]>, S: AsRef<str>>(config: &'a ScannerConfig<L, S>) -> Self {
+        let strs = |list: &'a L| list.as_ref().iter().map(AsRef::as_ref).collect();
+        let trie = |list: &'a L| Trie::new(list.as_ref().iter().map(AsRef::as_ref));
+        Self {
+            punctuation: strs(&config.punctuation),
+            true_literals: strs(&config.true_literals),
+            false_literals: strs(&config.false_literals),
+            nil_literals: strs(&config.nil_literals),
+            single_line_cmt: config.single_line_cmt.as_ref().map(AsRef::as_ref),
+            multi_line_cmt_start: config.multi_line_cmt_start.as_ref().map(AsRef::as_ref),
+            multi_line_cmt_end: config.multi_line_cmt_end.as_ref().map(AsRef::as_ref),
+            nested_comments: config.nested_comments,
+            directive_prefixes: strs(&config.directive_prefixes),
+            attribute_prefixes: strs(&config.attribute_prefixes),
+            line_continuation: config.line_continuation,
+            string_delimiters: string_delimiters(config.string_delimiters.as_ref()),
+            char_delimiter: config.char_delimiter,
+            lifetimes: config.lifetimes,
+            string_prefixes: strs(&config.string_prefixes),
+            raw_string_start: config.raw_string_start.as_ref().map(AsRef::as_ref),
+            raw_string_end: config.raw_string_end.as_ref().map(AsRef::as_ref),
+            raw_string_level: config.raw_string_level,
+            heredoc_start: config.heredoc_start.as_ref().map(AsRef::as_ref),
+            interpolated_string: config.interpolated_string,
+            interpolation_start: config.interpolation_start.as_ref().map(AsRef::as_ref),
+            interpolation_end: config.interpolation_end.as_ref().map(AsRef::as_ref),
+            strings_may_span_lines: config.strings_may_span_lines,
+            unterminated_strings: config.unterminated_strings,
+            control_chars: config.control_chars,
+            decode_escapes: config.decode_escapes,
+            regex_literals: config.regex_literals,
+            scientific_notation: config.scientific_notation,
+            number_separator: config.number_separator,
+            hex_float: config.hex_float,
+            legacy_octal: config.legacy_octal,
+            number_suffixes: strs(&config.number_suffixes),
+            quoted_identifiers: strs(&config.quoted_identifiers),
+            symbol_trie: trie(&config.symbols),
+            keyword_trie: trie(&config.keywords),
+            rules: Vec::new(),
+            fallback_rules: Vec::new(),
+            embedded: Vec::new(),
+            identifier_start: config.identifier_start,
+            identifier_char: config.identifier_char,
+            #[cfg(feature = "simd")]
+            identifier_set: crate::simd::ascii_set(config.identifier_char),
+            space_char: config.space_char,
+        }
+    }
+}
+
+/// the non empty string delimiters sorted longest first, or the double quote for an empty list
+fn string_delimiters<S: AsRef<str>>(list: &[S]) -> Vec<&str> {
+    let mut delimiters: Vec<&str> =
+        list.iter().map(AsRef::as_ref).filter(|d| !d.is_empty()).collect();
+    if delimiters.is_empty() {
+        delimiters.push("\"");
+    }
+    delimiters.sort_by_key(|delimiter| std::cmp::Reverse(delimiter.len()));
+    delimiters
+}
+
+/// A language embedded in the regions of another one. See CompiledConfig::with_embedded
+struct Embedded<'a> {
+    name: &'static str,
+    start: &'a str,
+    end: &'a str,
+    config: CompiledConfig<'a>,
+}
+
+/// a scan error along with the kind of the partial token that caused it
+type ScanFailure = (TokenKind, ScanError);
+
+impl Scanner {
+    /// Whether the scanner should build the token values (default true).
+    /// When disabled, ScannerData::token_types is left empty and no allocation is done per token.
+    /// Use ScannerData::token_kinds and ScannerData::token_spans to get the tokens.
+    pub fn with_values(mut self, values: bool) -> Self {
+        self.values = values;
+        self
+    }
+    /// Whether the scanner should produce the comment tokens (default true).
+    /// When disabled, comments are skipped like spaces.
+    pub fn with_comments(mut self, comments: bool) -> Self {
+        self.comments = comments;
+        self
+    }
+    /// Whether the scanner should produce the new line tokens (default false),
+    /// for languages where line breaks are significant.
+    /// New lines inside comments, strings or after a line continuation are not reported.
+    pub fn with_newlines(mut self, newlines: bool) -> Self {
+        self.newlines = newlines;
+        self
+    }
+    /// Whether the scanner should produce the space (TokenType::Ignore) and new line tokens (default false).
+    /// When enabled, the token spans cover the whole source code :
+    /// concatenating the text of all the tokens gives back the source code.
+    /// Comments must not be disabled for this to work.
+    pub fn with_whitespace(mut self, whitespace: bool) -> Self {
+        self.whitespace = whitespace;
+        self
+    }
+    /// Whether Scanner::run should attach the surrounding spaces and comments to each token
+    /// in ScannerData::token_trivia (default false).
+    /// When enabled, comments are part of the trivia and are not produced as tokens.
+    pub fn with_trivia(mut self, trivia: bool) -> Self {
+        self.trivia = trivia;
+        self
+    }
+    /// Whether Scanner::run should store a Symbol for each identifier and keyword
+    /// in ScannerData::token_symbols (default false).
+    /// Each distinct name is allocated only once in ScannerData::symbols.
+    pub fn with_interning(mut self, interning: bool) -> Self {
+        self.interning = interning;
+        self
+    }
+    /// Limits of the size of the source code, the number of tokens, the length of the literals
+    /// and the depth of the nested comments (default ScanLimits::NONE).
+    /// Protects a server scanning untrusted source code from memory exhaustion.
+    /// The limits do not apply to the lazy iterator of Scanner::tokens
+    pub fn with_limits(mut self, limits: ScanLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+    /// A flag checked regularly during the scan. When another thread sets it to true,
+    /// the scan stops with a ScanError::Cancelled error. An editor can abort the scan
+    /// of a huge file when a newer edit arrives.
+    /// Does not apply to the lazy iterator of Scanner::tokens
+    pub fn with_cancellation(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+    /// Whether the lines of the tokens and errors start at 1 or 0
+    /// (default PositionConvention::OneBased). The line numbers given to the functions
+    /// reading a ScannerData, like IndentRules::line_level, follow the same convention.
+    /// ScannerState lines and the LineIndex always start at 1
+    pub fn with_positions(mut self, positions: PositionConvention) -> Self {
+        self.positions = positions;
+        self
+    }
+    /// The number of columns between two tab stops in ScannerData::token_cols
+    /// and the error columns (default 1, a tabulation is one character).
+    /// Use the tab width of the editor so that the columns match the displayed text
+    pub fn with_tab_width(mut self, tab_width: usize) -> Self {
+        self.tab_width = tab_width.max(1);
+        self
+    }
+    /// Whether Scanner::run should store the state of the scanner at the start of each line
+    /// in ScannerData::line_states (default false). After an edit, an editor can scan again
+    /// the edited line only with Scanner::run_range, starting at the state of the line,
+    /// and continue with the next lines until the state at a line start is unchanged.
+    /// Not supported by Scanner::run_range
+    pub fn with_line_states(mut self, line_states: bool) -> Self {
+        self.record_line_states = line_states;
+        self
+    }
+    /// scan the provided source code and return a list of tokens in the ScannerData structure.
+    /// The previous content of the ScannerData is removed, but its memory is reused.
+    /// The ScannerData is not returned in the Result because we want it even when there is a scan error.
+    /// This is what a parser needs to easily move back and forth in the token list.
+    /// See Scanner::tokens to process the tokens lazily instead.
+    /// The scan never panics, whatever the source code, errors are returned instead
+    pub fn run<'a, C: Compile + ?Sized>(
+        &mut self,
+        source: &'a str,
+        config: &C,
+        data: &mut ScannerData<'a>,
+    ) -> Result<(), ScanError> {
+        let mut errors = Vec::new();
+        self.reset();
+        self.scan_all(source, &config.compiled(), data, &mut errors, false, source.len(), None);
+        match errors.pop() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+    /// same as run, measuring the time spent in each phase of the scan
+    /// to profile the scanner in an application
+    pub fn run_with_stats<'a, C: Compile + ?Sized>(
+        &mut self,
+        source: &'a str,
+        config: &C,
+        data: &mut ScannerData<'a>,
+    ) -> Result<ScanStats, ScanError> {
+        let mut stats = ScanStats {
+            bytes: source.len(),
+            ..ScanStats::default()
+        };
+        let result = self.run_observed(source, config, data, &mut stats);
+        stats.tokens = data.len();
+        stats.lines = data.line_index.line_count();
+        result.map(|()| stats)
+    }
+    /// same as run, reporting the duration of each phase of the scan to an observer
+    pub fn run_observed<'a, C: Compile + ?Sized>(
+        &mut self,
+        source: &'a str,
+        config: &C,
+        data: &mut ScannerData<'a>,
+        observer: &mut dyn ScanObserver,
+    ) -> Result<(), ScanError> {
+        let start = Instant::now();
+        let config = config.compiled();
+        observer.phase(ScanPhase::Compile, start.elapsed());
+        let mut errors = Vec::new();
+        self.reset();
+        self.scan_all(source, &config, data, &mut errors, false, source.len(), Some(observer));
+        match errors.pop() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+    /// scan the content of a file. See SourceFile::open
+    pub fn run_file<'a, C: Compile + ?Sized>(
+        &mut self,
+        file: &'a SourceFile,
+        config: &C,
+        data: &mut ScannerData<'a>,
+    ) -> Result<(), FileError> {
+        Ok(self.run(file.as_str()?, config, data)?)
+    }
+    /// scan a text which may be stored in several chunks, like the rope of a text editor.
+    /// A text stored in a single string is scanned in place, the other ones are copied in the buffer,
+    /// which can be reused between two scans. The token spans are byte offsets in the text,
+    /// see Token::text_in to get the text of a token from the TextSource
+    pub fn run_text<'a, T: TextSource + ?Sized, C: Compile + ?Sized>(
+        &mut self,
+        text: &'a T,
+        config: &C,
+        buffer: &'a mut String,
+        data: &mut ScannerData<'a>,
+    ) -> Result<(), ScanError> {
+        let source = match text.as_str() {
+            Some(source) => source,
+            None => {
+                buffer.clear();
+                buffer.reserve(text.len());
+                text.chunks().for_each(|chunk| buffer.push_str(chunk));
+                buffer
+            }
+        };
+        self.run(source, config, data)
+    }
+    /// scan only a part of the source code, for example the visible part in an editor.
+    /// The scan starts at the given state, for example a state saved during a previous scan
+    /// with Scanner::state or Tokens::state, and stops before the first token starting at or after end.
+    /// Returns the state after the last token, to scan the next part later.
+    pub fn run_range<'a, C: Compile + ?Sized>(
+        &mut self,
+        source: &'a str,
+        config: &C,
+        data: &mut ScannerData<'a>,
+        state: &ScannerState,
+        end: usize,
+    ) -> Result<ScannerState, ScanError> {
+        let mut errors = Vec::new();
+        self.set_state(state);
+        self.scan_all(source, &config.compiled(), data, &mut errors, false, end, None);
+        // the states of the lines before the range are unknown
+        data.line_states.clear();
+        match errors.pop() {
+            Some(error) => Err(error),
+            None => Ok(self.state()),
+        }
+    }
+    /// scan the provided source code without stopping at the first error.
+    /// The partial token causing an error is recorded (an Unknown token for an unknown character,
+    /// a truncated literal for an unterminated string) and the scan continues after it.
+    /// Useful for editors which need all the tokens even when the code is being typed.
+    pub fn run_lossy<'a, C: Compile + ?Sized>(
+        &mut self,
+        source: &'a str,
+        config: &C,
+    ) -> (ScannerData<'a>, Vec<ScanError>) {
+        let mut data = ScannerData::default();
+        let mut errors = Vec::new();
+        self.reset();
+        self.scan_all(source, &config.compiled(), &mut data, &mut errors, true, source.len(), None);
+        (data, errors)
+    }
+    /// scan a source code which may be incomplete, like the input of a REPL or the code being typed.
+    /// A missing closing delimiter at the end of the source code is not an error:
+    /// PartialScan::incomplete tells whether a string, a comment or a bracket is still open,
+    /// for example to display a "continue input" prompt.
+    /// The other errors are returned as with Scanner::run
+    pub fn run_partial<'a, C: Compile + ?Sized>(
+        &mut self,
+        source: &'a str,
+        config: &C,
+    ) -> Result<PartialScan<'a>, ScanError> {
+        let mut data = ScannerData::default();
+        let mut errors = Vec::new();
+        self.reset();
+        self.scan_all(source, &config.compiled(), &mut data, &mut errors, false, source.len(), None);
+        let incomplete = match errors.pop() {
+            Some(ScanError::UnexpectedEof(_)) => Some(IncompleteKind::String),
+            Some(error) => return Err(error),
+            // the scan stopped inside an interpolation
+            None if self.modes.contains(&Mode::InterpolatedString) => Some(IncompleteKind::String),
+            None if self.unclosed_comment => Some(IncompleteKind::Comment),
+            None => Brackets::new(&data, DEFAULT_PAIRS)
+                .errors()
+                .iter()
+                .any(|error| matches!(error, BracketError::Unclosed { .. }))
+                .then_some(IncompleteKind::Bracket),
+        };
+        Ok(PartialScan { data, incomplete })
+    }
+    /// scan the source code into data, from the current position to the last token starting before end.
+    /// Stops at the first error unless lossy is true
+    #[allow(clippy::too_many_arguments)]
+    fn scan_all<'a>(
+        &mut self,
+        source: &'a str,
+        config: &CompiledConfig,
+        data: &mut ScannerData<'a>,
+        errors: &mut Vec<ScanError>,
+        lossy: bool,
+        end: usize,
+        mut observer: Option<&mut dyn ScanObserver>,
+    ) {
+        // the phases are only timed for an observer
+        let phase = |observer: &mut Option<&mut dyn ScanObserver>, phase, start: Option<Instant>| {
+            if let (Some(observer), Some(start)) = (observer, start) {
+                observer.phase(phase, start.elapsed());
+            }
+        };
+        data.clear();
+        data.source = source;
+        data.positions = self.positions;
+        let start = observer.is_some().then(Instant::now);
+        data.line_index = LineIndex::new(source);
+        phase(&mut observer, ScanPhase::LineIndex, start);
+        let start = observer.is_some().then(Instant::now);
+        self.scan_tokens(source, config, data, errors, lossy, end);
+        phase(&mut observer, ScanPhase::Scan, start);
+        if self.trivia {
+            let start = observer.is_some().then(Instant::now);
+            data.attach_trivia();
+            phase(&mut observer, ScanPhase::Trivia, start);
+        }
+    }
+    /// the scanning loop of scan_all, adding the tokens to data without computing the trivia
+    fn scan_tokens(
+        &mut self,
+        source: &str,
+        config: &CompiledConfig,
+        data: &mut ScannerData<'_>,
+        errors: &mut Vec<ScanError>,
+        lossy: bool,
+        end: usize,
+    ) {
+        if source.len() > self.limits.max_input_len || source.len() > u32::MAX as usize {
+            errors.push(ScanError::LimitExceeded(ErrorSpan {
+                span: Span::new(0, source.len()),
+                line: self.positions.first_line(),
+                offset: 0,
+                column: 0,
+            }));
+            return;
+        }
+        let bounded = end < source.len();
+        for count in 0usize.. {
+            if count % CANCEL_CHECK_INTERVAL == 0 && self.is_cancelled() {
+                self.start = self.current;
+                self.start_char = self.current_char;
+                errors.push(self.error(source, ScanError::Cancelled));
+                break;
+            }
+            let previous = bounded.then(|| self.state());
+            let next = self.scan_next(source, config);
+            if let Some(previous) = previous.filter(|_| self.start >= end) {
+                // this token is outside the range
+                self.set_state(&previous);
+                break;
+            }
+            data.warnings.append(&mut self.warnings);
+            match next {
+                Ok(TokenKind::Eof) => break,
+                Ok(_) if data.len() >= self.limits.max_tokens => {
+                    errors.push(self.error(source, ScanError::LimitExceeded));
+                    break;
+                }
+                Ok(kind) => self.add_token(kind, source, config, data),
+                Err((_, error @ ScanError::LimitExceeded(_))) => {
+                    errors.push(error);
+                    break;
+                }
+                Err((kind, error)) => {
+                    self.add_token(kind, source, config, data);
+                    if kind == TokenKind::StringLiteral {
+                        // the token length includes the missing closing quote
+                        data.unterminated.push(TokenIndex::new(data.len() - 1));
+                    }
+                    errors.push(error);
+                    if !lossy {
+                        break;
+                    }
+                    if self.current == self.start {
+                        // make sure the scan progresses
+                        if self.advance(source).is_none() {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        data.line_states.append(&mut self.line_states);
+    }
+    /// scan a large source code using several threads. The source code is split in chunks
+    /// at line starts, and the chunks are scanned in parallel.
+    /// When a chunk does not start between two tokens, for example inside a multi-line comment or string,
+    /// the code after the previous chunk is scanned again sequentially,
+    /// so the result is always the same as with Scanner::run.
+    pub fn run_parallel<'a, C: Compile + ?Sized>(
+        &mut self,
+        source: &'a str,
+        config: &C,
+        data: &mut ScannerData<'a>,
+        threads: usize,
+    ) -> Result<(), ScanError> {
+        let config = config.compiled();
+        let config: &CompiledConfig = &config;
+        data.clear();
+        data.source = source;
+        data.positions = self.positions;
+        data.line_index = LineIndex::new(source);
+        // quick pre-pass computing the scanner state at the start of each chunk
+        let mut states = vec![ScannerState::default()];
+        let mut previous = 0;
+        for i in 1..threads {
+            let target = source.len() * i / threads;
+            let Some(split) = source.as_bytes()[target..]
+                .iter()
+                .position(|b| *b == b'\n')
+                .map(|pos| target + pos + 1)
+            else {
+                break;
+            };
+            if split <= previous || split >= source.len() {
+                continue;
+            }
+            let char_offset = states.last().map_or(0, |state| state.char_offset);
+            states.push(ScannerState {
+                offset: split,
+                char_offset: char_offset + source[previous..split].chars().count(),
+                line: data.line_index.offset_to_line_col(split).0,
+                modes: Vec::new(),
+                regex_allowed: true,
+            });
+            previous = split;
+        }
+        let ends: Vec<usize> = states[1..]
+            .iter()
+            .map(|state| state.offset)
+            .chain([source.len()])
+            .collect();
+        let chunks: Vec<_> = std::thread::scope(|scope| {
+            let handles: Vec<_> = states
+                .iter()
+                .zip(&ends)
+                .map(|(state, end)| {
+                    let mut scanner = self.clone();
+                    scope.spawn(move || {
+                        let mut chunk = ScannerData::default();
+                        let mut errors = Vec::new();
+                        scanner.set_state(state);
+                        scanner.scan_tokens(source, config, &mut chunk, &mut errors, false, *end);
+                        (chunk, errors.pop(), scanner.state())
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+                .collect()
+        });
+        let mut result = Ok(());
+        // the state at the end of the previous chunk, when the current chunk must be scanned again
+        let mut rescan: Option<ScannerState> = None;
+        for (i, (chunk, error, state)) in chunks.into_iter().enumerate() {
+            let (chunk, error, state) = match rescan.take() {
+                Some(start) => {
+                    // the chunk was scanned from a wrong state
+                    let mut chunk = ScannerData::default();
+                    let mut errors = Vec::new();
+                    self.set_state(&start);
+                    self.scan_tokens(source, config, &mut chunk, &mut errors, false, ends[i]);
+                    (chunk, errors.pop(), self.state())
+                }
+                None => (chunk, error, state),
+            };
+            Self::append_chunk(data, chunk);
+            self.set_state(&state);
+            if let Some(error) = error {
+                result = Err(error);
+                break;
+            }
+            if i + 1 == ends.len() {
+                break;
+            }
+            if !self.reaches(source, config, ends[i]) {
+                // the next chunk starts inside a token. scan the rest of the code sequentially
+                let mut rest = ScannerData::default();
+                let mut errors = Vec::new();
+                self.set_state(&state);
+                self.scan_tokens(source, config, &mut rest, &mut errors, false, source.len());
+                Self::append_chunk(data, rest);
+                if let Some(error) = errors.pop() {
+                    result = Err(error);
+                }
+                break;
+            }
+            if config.regex_literals && self.regex_allowed != states[i + 1].regex_allowed {
+                // a slash at the start of the next chunk may be a regex or a division
+                rescan = Some(state);
+            }
+        }
+        if self.trivia {
+            data.attach_trivia();
+        }
+        result
+    }
+    /// add the tokens and the line states of a chunk to the result of the previous chunks.
+    /// A chunk records meaningless states for the lines before its start. They are skipped since
+    /// the previous chunk recorded the states up to the first line of the next chunk
+    fn append_chunk<'a>(data: &mut ScannerData<'a>, mut chunk: ScannerData<'a>) {
+        let known = data.line_states.len();
+        data.line_states.extend(chunk.line_states.drain(..).skip(known));
+        data.append(chunk);
+    }
+    /// the column of the current token, counted from the previous token when it is on the same line
+    /// so that long lines are not read again for each token
+    fn start_column(&mut self, source: &str) -> usize {
+        let (from, column) = match self.last_column {
+            Some((offset, column)) if offset <= self.start => (offset, column),
+            _ => (source[..self.start].rfind('\n').map_or(0, |pos| pos + 1), 0),
+        };
+        let text = &source[from..self.start];
+        let column = match text.rfind('\n') {
+            Some(pos) => expand_column(0, &text[pos + 1..], self.tab_width),
+            None => expand_column(column, text, self.tab_width),
+        };
+        self.last_column = Some((self.start, column));
+        column
+    }
+    /// record the states of the lines started since the previous position between two tokens.
+    /// A line starting exactly at the current position is resumed from here,
+    /// the other lines from the previous position
+    fn record_line_states(&mut self, source: &str) {
+        let at_line_start = self.current == 0 || source[..self.current].ends_with('\n');
+        let state = self.state();
+        while self.line_states.len() < self.line {
+            let line = self.line_states.len() + 1;
+            let line_state = if line == self.line && at_line_start {
+                state.clone()
+            } else {
+                self.boundary.clone()
+            };
+            self.line_states.push(line_state);
+        }
+        self.boundary = state;
+    }
+    /// a line number counted from 1 in the position convention of the results
+    fn line_number(&self, line: usize) -> usize {
+        line - 1 + self.positions.first_line()
+    }
+    fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+    }
+    /// whether the scanner goes from its current position to offset
+    /// without meeting anything but skipped spaces, new lines and comments
+    fn reaches(&mut self, source: &str, config: &CompiledConfig, offset: usize) -> bool {
+        while self.current < offset && self.modes.is_empty() {
+            self.start = self.current;
+            self.start_char = self.current_char;
+            self.start_line = self.line;
+            match self.scan_token(source, config) {
+                Ok(TokenKind::Ignore | TokenKind::NewLine | TokenKind::Comment) => (),
+                _ => return false,
+            }
+        }
+        self.current == offset && self.modes.is_empty()
+    }
+    /// scan the provided source code lazily.
+    /// The iterator stops after the first error.
+    pub fn tokens<'a, C: Compile + ?Sized>(
+        mut self,
+        source: &'a str,
+        config: &'a C,
+    ) -> Tokens<'a> {
+        self.reset();
+        // the iterator has no ScannerData to store them
+        self.record_line_states = false;
+        Tokens {
+            scanner: self,
+            source,
+            config: config.compiled(),
+            done: false,
+        }
+    }
+    fn reset(&mut self) {
+        self.set_state(&ScannerState::default());
+        self.line_states.clear();
+        self.boundary = ScannerState::default();
+    }
+    /// the current position of the scanner and its lexer modes
+    pub fn state(&self) -> ScannerState {
+        ScannerState {
+            offset: self.current,
+            char_offset: self.current_char,
+            line: self.line,
+            modes: self.modes.clone(),
+            regex_allowed: self.regex_allowed,
+        }
+    }
+    /// restore a state returned by Scanner::state
+    pub fn set_state(&mut self, state: &ScannerState) {
+        self.current = state.offset;
+        self.current_char = state.char_offset;
+        self.line = state.line;
+        self.modes.clone_from(&state.modes);
+        self.regex_allowed = state.regex_allowed;
+        self.unclosed_comment = false;
+        self.warnings.clear();
+        self.last_column = None;
+    }
+    fn token(&self, kind: TokenKind) -> Token {
+        Token {
+            kind,
+            span: Span::new(self.start, self.current - self.start),
+            line: self.line_number(self.start_line),
+        }
+    }
+    fn add_token(&mut self, kind: TokenKind, source: &str, config: &CompiledConfig, data: &mut ScannerData<'_>) {
+        let span = Span::new(self.start, self.current - self.start);
+        let rule_value = self.rule_value.take();
+        if self.prefix_len > 0 || !data.token_prefixes.is_empty() {
+            // no prefix for the tokens before the first literal with a prefix
+            data.token_prefixes.resize(data.token_kinds.len(), 0);
+            data.token_prefixes.push(compact(self.prefix_len));
+        }
+        if self.values {
+            let language = self.embedded_language(config).0;
+            data.token_types.push(
+                rule_value.unwrap_or_else(|| language.token_value(kind, &source[span.range()])),
+            );
+        }
+        data.token_kinds.push(kind);
+        data.token_spans.push(span);
+        let column = self.start_column(source);
+        data.token_cols.push(compact(column));
+        data.token_lines.push(compact(self.line_number(self.start_line)));
+        // a token ending with a new line, like a NewLine token, ends on the previous line
+        let end_line = self.line - usize::from(source[span.range()].ends_with('\n'));
+        data.token_end_lines.push(compact(self.line_number(end_line.max(self.start_line))));
+        if self.interning {
+            let symbol = match kind {
+                TokenKind::Identifier | TokenKind::Keyword => {
+                    Some(data.symbols.intern(&source[span.range()]))
+                }
+                _ => None,
+            };
+            data.token_symbols.push(symbol);
+        }
+        if !config.embedded.is_empty() {
+            data.token_languages.push(self.token_language);
+        }
+    }
+    /// the configuration of the innermost embedded language at the current position
+    /// along with its definition, or the main configuration
+    fn embedded_language<'c, 'a>(
+        &self,
+        config: &'c CompiledConfig<'a>,
+    ) -> (&'c CompiledConfig<'a>, Option<&'c Embedded<'a>>) {
+        let mut language = (config, None);
+        if config.embedded.is_empty() {
+            // no need to go through the modes of the nested interpolations
+            return language;
+        }
+        for mode in &self.modes {
+            if let Mode::Embedded(index) = mode {
+                if let Some(embedded) = language.0.embedded.get(*index) {
+                    language = (&embedded.config, Some(&**embedded));
+                }
+            }
+        }
+        language
+    }
+    /// scan the next significant token, skipping spaces, new lines and comments if they are disabled
+    fn scan_next(&mut self, source: &str, config: &CompiledConfig) -> Result<TokenKind, ScanFailure> {
+        loop {
+            self.rule_value = None;
+            self.prefix_len = 0;
+            if self.record_line_states {
+                self.record_line_states(source);
+            }
+            self.start = self.current;
+            self.start_char = self.current_char;
+            self.start_line = self.line;
+            let kind = self.scan_token(source, config)?;
+            let literal = matches!(
+                kind,
+                TokenKind::StringLiteral | TokenKind::StringPart | TokenKind::Comment
+            );
+            if literal && self.current - self.start > self.limits.max_literal_len {
+                return Err((kind, self.error(source, ScanError::LimitExceeded)));
+            }
+            if !matches!(kind, TokenKind::Ignore | TokenKind::NewLine | TokenKind::Comment) {
+                self.regex_allowed = regex_may_follow(kind, &source[self.start..self.current]);
+            }
+            match kind {
+                TokenKind::Ignore if !self.whitespace => (),
+                TokenKind::NewLine if !self.newlines && !self.whitespace => (),
+                TokenKind::Comment if !self.comments || self.trivia => (),
+                kind => return Ok(kind),
+            }
+        }
+    }
+    fn scan_token(&mut self, source: &str, root: &CompiledConfig) -> Result<TokenKind, ScanFailure> {
+        let (config, embedded) = self.embedded_language(root);
+        self.token_language = embedded.map(|embedded| embedded.name);
+        if let (Some(Mode::Embedded(_)), Some(embedded)) = (self.modes.last(), embedded) {
+            if self.matches(embedded.end, source) {
+                // the end marker belongs to the embedding language
+                self.advance_str(embedded.end);
+                self.modes.pop();
+                self.token_language = self.embedded_language(root).1.map(|embedded| embedded.name);
+                return Ok(TokenKind::Symbol);
+            }
+        }
+        if self.modes.last() == Some(&Mode::InterpolatedString) {
+            return self.scan_string_part(source, config);
+        }
+        if self.current >= source.len() {
+            return Ok(TokenKind::Eof);
+        }
+        if let Some(token) = self.scan_interpolation_end(source, config) {
+            return Ok(token);
+        }
+        if config.interpolated_string.is_some() && self.peek(source) == config.interpolated_string {
+            self.advance(source);
+            self.modes.push(Mode::InterpolatedString);
+            return self.scan_string_part(source, config);
+        }
+        if let Some(index) = config
+            .embedded
+            .iter()
+            .position(|e| !e.start.is_empty() && self.matches(e.start, source)) {
+            self.advance_str(config.embedded[index].start);
+            self.modes.push(Mode::Embedded(index));
+            return Ok(TokenKind::Symbol);
+        }
+        if let Some(token) = self.scan_rules(source, &config.rules) {
+            return Ok(token);
+        }
+        if let Some(token) = self.scan_attribute(source, config) {
+            return Ok(token);
+        }
+        if let Some(token) = self.scan_directive(source, config) {
+            return Ok(token);
+        }
+        if let Some(token) = self.scan_comment(source, config)? {
+            return Ok(token);
+        }
+        if let Some(token) = self.scan_newline(source) {
+            return Ok(token);
+        }
+        if let Some(token) = self.scan_space(source, config) {
+            return Ok(token);
+        }
+        if let Some(token) = self.scan_raw_string(source, config)? {
+            return Ok(token);
+        }
+        if let Some(token) = self.scan_heredoc(source, config)? {
+            return Ok(token);
+        }
+        if let Some(token) = self.scan_regex(source, config) {
+            return Ok(token);
+        }
+        if let Some(token) = self.scan_symbol(source, config) {
+            return Ok(token);
+        }
+        if let Some(token) = self.scan_prefixed_string(source, config)? {
+            return Ok(token);
+        }
+        if let Some(token) = self.scan_literal_word(source, config) {
+            return Ok(token);
+        }
+        if let Some(token) = self.scan_keyword(source, config) {
+            return Ok(token);
+        }
+        if let Some(token) = self.scan_quoted_identifier(source, config)? {
+            return Ok(token);
+        }
+        if let Some(token) = self.scan_string(source, config)? {
+            return Ok(token);
+        }
+        if let Some(token) = self.scan_char(source, config) {
+            return Ok(token);
+        }
+        if let Some(token) = self.scan_lifetime(source, config) {
+            return Ok(token);
+        }
+        if let Some(token) = self.scan_identifier(source, config) {
+            return Ok(token);
+        }
+        if let Some(token) = self.scan_number(source, config) {
+            return Ok(token);
+        }
+        if let Some(token) = self.scan_rules(source, &config.fallback_rules) {
+            return Ok(token);
+        }
+        self.advance(source);
+        Err((TokenKind::Unknown, self.error(source, ScanError::UnknownToken)))
+    }
+    fn scan_rules(&mut self, source: &str, rules: &[Arc<dyn TokenRule + '_>]) -> Option<TokenKind> {
+        for rule in rules {
+            let mut cursor = Cursor::new(source, self.current);
+            if let Some(value) = rule.try_scan(&mut cursor) {
+                if cursor.offset() > self.current {
+                    self.advance_str(cursor.text());
+                    let kind = value.kind();
+                    self.rule_value = Some(value);
+                    return Some(kind);
+                }
+            }
+        }
+        None
+    }
+    fn scan_comment(
+        &mut self,
+        source: &str,
+        config: &CompiledConfig,
+    ) -> Result<Option<TokenKind>, ScanFailure> {
+        if let Some(multi_start) = config.multi_line_cmt_start {
+            if self.matches(multi_start, source) {
+                if let Some(multi_end) = config.multi_line_cmt_end {
+                    return self
+                        .scan_multi_line_comment(multi_start, multi_end, source, config)
+                        .map(Some);
+                }
+            }
+        }
+        if let Some(single_start) = config.single_line_cmt {
+            if self.matches(single_start, source) {
+                return Ok(Some(self.scan_single_line_comment(source)));
+            }
+        }
+        Ok(None)
+    }
+    /// whether only spaces precede the current position in its line
+    fn at_line_start(&self, source: &str, config: &CompiledConfig) -> bool {
+        source[..self.current]
+            .chars()
+            .rev()
+            .take_while(|c| *c != '\n')
+            .all(config.space_char)
+    }
+    fn scan_directive(&mut self, source: &str, config: &CompiledConfig) -> Option<TokenKind> {
+        if !config.directive_prefixes.iter().any(|prefix| self.matches(prefix, source)) {
+            return None;
+        }
+        if !self.at_line_start(source, config) {
+            return None;
         }
-        if let Some(token) = self.scan_number(data) {
-            return Ok(token);
+        loop {
+            self.advance_while(source, |c| c != '\n' && Some(c) != config.line_continuation);
+            if !self.advance_line_continuation(source, config) {
+                if self.peek(source).is_some_and(|c| c != '\n') {
+                    // a continuation character not followed by a new line
+                    self.advance(source);
+                    continue;
+                }
+                break;
+            }
         }
-        data.token_len.push(1);
-        data.token_start.push(self.current);
-        data.token_types.push(TokenType::Unknown);
-        data.token_lines.push(self.line);
-        let token_id = data.token_len.len() - 1;
-        Err(ScanError::UnknownToken(
-            self.line,
-            data.token_start[token_id],
-        ))
+        Some(TokenKind::Directive)
     }
-    fn scan_comment(
-        &mut self,
-        config: &ScannerConfig,
-        data: &mut ScannerData,
-    ) -> Option<TokenType> {
-        if let Some(multi_start) = config.multi_line_cmt_start {
-            if self.matches(multi_start, data) {
-                if let Some(multi_end) = config.multi_line_cmt_end {
-                    return self.scan_multi_line_comment(multi_start, multi_end, data);
+    fn scan_attribute(&mut self, source: &str, config: &CompiledConfig) -> Option<TokenKind> {
+        let text = &source[self.current..];
+        let prefix = config
+            .attribute_prefixes
+            .iter()
+            .filter(|prefix| !prefix.is_empty() && text.starts_with(**prefix))
+            .max_by_key(|prefix| prefix.len())?;
+        // after an operand, the prefix is an operator
+        if !self.regex_allowed && !self.at_line_start(source, config) {
+            return None;
+        }
+        let rest = &text[prefix.len()..];
+        let brackets = prefix.chars().filter(|c| "([{".contains(*c)).count();
+        let len = if brackets > 0 {
+            bracketed_len(rest, brackets)?
+        } else if prefix.ends_with(config.identifier_char) {
+            if rest.starts_with(config.identifier_char) {
+                return None;
+            }
+            rest.find(['\r', '\n']).unwrap_or(rest.len())
+        } else {
+            // a dotted name
+            let mut end = 0;
+            while rest[end..].starts_with(config.identifier_start) {
+                end += rest[end..]
+                    .char_indices()
+                    .skip(1)
+                    .find(|(_, c)| !(config.identifier_char)(*c))
+                    .map_or(rest.len() - end, |(i, _)| i);
+                if !(rest[end..].starts_with('.')
+                    && rest[end + 1..].starts_with(config.identifier_start))
+                {
+                    break;
                 }
+                end += 1;
             }
-        }
-        if let Some(single_start) = config.single_line_cmt {
-            if self.matches(single_start, data) {
-                return self.scan_single_line_comment(data);
+            if end == 0 {
+                return None;
             }
+            if rest[end..].starts_with('(') {
+                end += bracketed_len(&rest[end + 1..], 1).map_or(0, |len| len + 1);
+            }
+            end
+        };
+        self.advance_str(&text[..prefix.len() + len]);
+        Some(TokenKind::Attribute)
+    }
+    /// consume the line continuation character and the following new line
+    fn advance_line_continuation(&mut self, source: &str, config: &CompiledConfig) -> bool {
+        let Some(continuation) = config.line_continuation else {
+            return false;
+        };
+        let rest = &source[self.current..];
+        let Some(rest) = rest.strip_prefix(continuation) else {
+            return false;
+        };
+        if !(rest.starts_with('\n') || rest.starts_with("\r\n")) {
+            return false;
         }
-        None
+        self.advance(source);
+        if self.peek(source) == Some('\r') {
+            self.advance(source);
+        }
+        self.advance(source);
+        true
     }
-    fn scan_single_line_comment(&mut self, data: &mut ScannerData) -> Option<TokenType> {
-        let source_len = data.source.len();
-        while self.current < source_len && data.source[self.current] != '\n' {
-            self.current += 1;
+    fn scan_single_line_comment(&mut self, source: &str) -> TokenKind {
+        #[cfg(feature = "simd")]
+        {
+            let rest = &source.as_bytes()[self.current..];
+            let len = crate::simd::find_byte(rest, b'\n').unwrap_or(rest.len());
+            self.current_char += crate::simd::char_count(&rest[..len]);
+            self.current += len;
         }
-        let end=self.current;
-        if self.current < source_len {
-            self.current += 1;
-            self.line += 1;
+        while let Some(c) = self.peek(source) {
+            if c == '\n' {
+                break;
+            }
+            self.advance(source);
         }
-        return Some(TokenType::Comment(
-            data.source[self.start..end]
-                .iter()
-                .cloned()
-                .collect::<String>(),
-        ));
+        TokenKind::Comment
     }
     fn scan_multi_line_comment(
         &mut self,
         multi_start: &str,
         multi_end: &str,
-        data: &mut ScannerData,
-    ) -> Option<TokenType> {
-        let mut level = 0;
+        source: &str,
+        config: &CompiledConfig,
+    ) -> Result<TokenKind, ScanFailure> {
+        self.advance_str(multi_start);
+        let mut level = 1;
         let mut in_string = false;
         let mut escape = false;
-        while self.current < data.source.len() {
-            let c = data.source[self.current];
-            if c == '\n' {
-                self.line += 1;
-            } else if c == '\\' && !escape {
+        while let Some(c) = self.peek(source) {
+            if c == '\\' && !escape {
                 escape = true;
             } else {
-                if c == '\"' && !escape {
+                let escaped = escape;
+                escape = false;
+                if c == '\"' && !escaped {
                     in_string = !in_string;
                 } else if !in_string {
-                    if self.matches(multi_end, data) {
+                    if self.matches(multi_end, source) {
+                        self.advance_str(multi_end);
                         level -= 1;
-                        self.current += multi_end.len() - 1;
                         if level == 0 {
-                            self.current += 1;
-                            return Some(TokenType::Comment(
-                                data.source[self.start..self.current]
-                                    .iter()
-                                    .cloned()
-                                    .collect::<String>(),
-                            ));
+                            return Ok(TokenKind::Comment);
                         }
-                    } else if self.matches(multi_start, data) {
-                        self.current += multi_start.len() - 1;
+                        continue;
+                    } else if config.nested_comments && self.matches(multi_start, source) {
+                        self.advance_str(multi_start);
                         level += 1;
+                        if level > self.limits.max_comment_nesting {
+                            return Err((
+                                TokenKind::Comment,
+                                self.error(source, ScanError::LimitExceeded),
+                            ));
+                        }
+                        continue;
                     }
                 }
-                escape = false;
             }
-            self.current += 1;
+            self.advance(source);
         }
-        self.add_token(
-            TokenType::Comment(
-                data.source[self.start..self.current - 1]
-                    .iter()
-                    .cloned()
-                    .collect::<String>(),
-            ),
-            data,
-        );
-        Some(TokenType::Eof)
-    }
-    fn scan_number(&mut self, data: &mut ScannerData) -> Option<TokenType> {
-        if is_digit(data.source[self.current]) {
-            let source_len = data.source.len();
-            if self.current < source_len - 2 {
-                if data.source[self.current + 1] == 'x' || data.source[self.current + 1] == 'X' {
-                    self.current += 2;
-                    return self.scan_hex_number(data);
-                } else if data.source[self.current + 1] == 'b'
-                    || data.source[self.current + 1] == 'B'
-                {
-                    self.current += 2;
-                    return self.scan_binary_number(data);
+        // unterminated comment
+        self.unclosed_comment = true;
+        Ok(TokenKind::Comment)
+    }
+    fn scan_number(&mut self, source: &str, config: &CompiledConfig) -> Option<TokenKind> {
+        if !self.peek(source).is_some_and(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        self.advance_number(source, config);
+        let rest = &source[self.current..];
+        if let Some(suffix) = config.number_suffixes.iter().find(|suffix| {
+            rest.starts_with(**suffix) && !rest[suffix.len()..].starts_with(config.identifier_char)
+        }) {
+            self.advance_str(suffix);
+        }
+        Some(TokenKind::NumberLiteral)
+    }
+    /// consume a number literal without its suffix
+    fn advance_number(&mut self, source: &str, config: &CompiledConfig) {
+        if self.peek(source) == Some('0') {
+            let base = self.peek_nth(source, 1);
+            let digit = self.peek_nth(source, 2);
+            if matches!(base, Some('x' | 'X')) && digit.is_some_and(|c| c.is_ascii_hexdigit()) {
+                self.advance_str("0x");
+                self.advance_digits(source, 16, config);
+                if config.hex_float {
+                    self.advance_fraction(source, 16, config);
+                    self.advance_exponent(source, ('p', 'P'));
                 }
+                return;
             }
-            let mut number = 0.0;
-            let mut value = String::new();
-            while self.current < source_len && is_digit(data.source[self.current]) {
-                let c = data.source[self.current];
-                value.push(c);
-                number = number * 10.0 + Number::from((c as u8) - b'0');
-                self.current += 1;
+            if matches!(base, Some('b' | 'B')) && matches!(digit, Some('0' | '1')) {
+                self.advance_str("0b");
+                self.advance_digits(source, 2, config);
+                return;
             }
-            if self.current < source_len - 1
-                && data.source[self.current] == '.'
-                && is_digit(data.source[self.current + 1])
-            {
-                self.current += 1;
-                value.push('.');
-                let mut div = 1.0;
-                while self.current < source_len && is_digit(data.source[self.current]) {
-                    let c = data.source[self.current];
-                    value.push(c);
-                    number = number * 10.0 + Number::from((c as u8) - b'0');
-                    self.current += 1;
-                    div *= 10.0;
+            if matches!(base, Some('o' | 'O')) && digit.is_some_and(|c| c.is_digit(8)) {
+                self.advance_str("0o");
+                self.advance_digits(source, 8, config);
+                return;
+            }
+        }
+        self.advance_digits(source, 10, config);
+        self.advance_fraction(source, 10, config);
+        if config.scientific_notation {
+            self.advance_exponent(source, ('e', 'E'));
+        }
+    }
+    /// consume the fractional part of a number : a dot followed by digits
+    fn advance_fraction(&mut self, source: &str, radix: u32, config: &CompiledConfig) {
+        if self.peek(source) == Some('.') && self.peek_nth(source, 1).is_some_and(|c| c.is_digit(radix)) {
+            self.advance(source);
+            self.advance_digits(source, radix, config);
+        }
+    }
+    /// consume the exponent of a number : a marker, an optional sign and decimal digits
+    fn advance_exponent(&mut self, source: &str, markers: (char, char)) {
+        if self.peek(source).is_some_and(|c| c == markers.0 || c == markers.1) {
+            let sign = usize::from(matches!(self.peek_nth(source, 1), Some('+' | '-')));
+            if self.peek_nth(source, 1 + sign).is_some_and(|c| c.is_ascii_digit()) {
+                for _ in 0..=sign {
+                    self.advance(source);
                 }
-                number /= div;
+                self.advance_while(source, |c| c.is_ascii_digit());
+            }
+        }
+    }
+    /// consume digits and the digit separators followed by a digit
+    fn advance_digits(&mut self, source: &str, radix: u32, config: &CompiledConfig) {
+        loop {
+            self.advance_while(source, |c| c.is_digit(radix));
+            let Some(separator) = config.number_separator else {
+                return;
+            };
+            let separators = source[self.current..]
+                .chars()
+                .take_while(|c| *c == separator)
+                .count();
+            if separators == 0
+                || !self
+                    .peek_nth(source, separators)
+                    .is_some_and(|c| c.is_digit(radix))
+            {
+                return;
             }
-            return Some(TokenType::NumberLiteral(value, number));
+            for _ in 0..separators {
+                self.advance(source);
+            }
+        }
+    }
+    fn scan_identifier(&mut self, source: &str, config: &CompiledConfig) -> Option<TokenKind> {
+        if self.peek(source).is_some_and(config.identifier_start) {
+            self.advance(source);
+            #[cfg(feature = "simd")]
+            {
+                let len = crate::simd::ascii_set_len(&source.as_bytes()[self.current..], config.identifier_set);
+                self.current += len;
+                self.current_char += len;
+            }
+            self.advance_while(source, config.identifier_char);
+            return Some(TokenKind::Identifier);
         }
         None
     }
-    fn scan_binary_number(&mut self, data: &mut ScannerData) -> Option<TokenType> {
-        let mut number = 0.0;
-        let mut value = String::new();
+    fn scan_space(&mut self, source: &str, config: &CompiledConfig) -> Option<TokenKind> {
+        let start = self.current;
+        let skip_controls = config.control_chars != ControlCharPolicy::Error;
         loop {
-            let c = data.source[self.current];
-            match c {
-                '0' | '1' => {
-                    number = number * 2.0 + Number::from((c as u8) - b'0');
-                    value.push(c);
+            self.advance_while(source, |c| {
+                c != '\n' && ((config.space_char)(c) || (skip_controls && is_control_char(c)))
+            });
+            if !self.advance_line_continuation(source, config) {
+                break;
+            }
+        }
+        if start == self.current {
+            return None;
+        }
+        if config.control_chars == ControlCharPolicy::Warning {
+            self.control_char_warnings(source);
+        }
+        Some(TokenKind::Ignore)
+    }
+    /// add a warning for each control character of the current token
+    fn control_char_warnings(&mut self, source: &str) {
+        let mut line = self.line_number(self.start_line);
+        let mut line_start = source[..self.start].rfind('\n').map_or(0, |pos| pos + 1);
+        let text = &source[self.start..self.current];
+        for (index, (pos, c)) in text.char_indices().enumerate() {
+            let pos = self.start + pos;
+            if c == '\n' {
+                line += 1;
+                line_start = pos + 1;
+            } else if is_control_char(c) {
+                self.warnings.push(ScanError::ControlCharacter(ErrorSpan {
+                    span: Span::new(pos, c.len_utf8()),
+                    line,
+                    offset: self.start_char + index,
+                    column: expand_column(0, &source[line_start..pos], self.tab_width),
+                }));
+            }
+        }
+    }
+    fn scan_quoted_identifier(
+        &mut self,
+        source: &str,
+        config: &CompiledConfig,
+    ) -> Result<Option<TokenKind>, ScanFailure> {
+        let Some(delimiter) = config
+            .quoted_identifiers
+            .iter()
+            .find(|delimiter| !delimiter.is_empty() && self.matches(delimiter, source))
+        else {
+            return Ok(None);
+        };
+        self.advance_str(delimiter);
+        while self.current < source.len() {
+            if self.matches(delimiter, source) {
+                self.advance_str(delimiter);
+                if !self.matches(delimiter, source) {
+                    return Ok(Some(TokenKind::Identifier));
                 }
-                _ => break,
+                // a doubled delimiter
+                self.advance_str(delimiter);
+            } else {
+                self.advance(source);
             }
-            self.current += 1;
-            if self.current == data.source.len() {
-                break;
+        }
+        self.unterminated_string(source, config, TokenKind::Identifier)
+    }
+    fn scan_string(
+        &mut self,
+        source: &str,
+        config: &CompiledConfig,
+    ) -> Result<Option<TokenKind>, ScanFailure> {
+        let Some(delimiter) = config
+            .string_delimiters
+            .iter()
+            .find(|delimiter| self.matches(delimiter, source))
+        else {
+            return Ok(None);
+        };
+        self.advance_str(delimiter);
+        let may_span_lines = config.strings_may_span_lines || delimiter.chars().nth(1).is_some();
+        let mut escape = false;
+        while let Some(c) = self.peek(source) {
+            if c == '\n' && !escape && !may_span_lines {
+                match config.unterminated_strings {
+                    UnterminatedStringPolicy::Error => {
+                        return Err((
+                            TokenKind::StringLiteral,
+                            self.error(source, ScanError::UnterminatedString),
+                        ))
+                    }
+                    UnterminatedStringPolicy::RecoverAtLineEnd => {
+                        return Ok(Some(TokenKind::StringLiteral))
+                    }
+                    UnterminatedStringPolicy::RecoverAtEof => (),
+                }
+            }
+            if !escape && self.matches(delimiter, source) {
+                self.advance_str(delimiter);
+                return Ok(Some(TokenKind::StringLiteral));
+            }
+            self.advance(source);
+            escape = c == '\\' && !escape;
+        }
+        self.unterminated_string(source, config, TokenKind::StringLiteral)
+    }
+    fn scan_raw_string(
+        &mut self,
+        source: &str,
+        config: &CompiledConfig,
+    ) -> Result<Option<TokenKind>, ScanFailure> {
+        let (Some(start), Some(end)) = (config.raw_string_start, config.raw_string_end) else {
+            return Ok(None);
+        };
+        let level_char = config.raw_string_level;
+        let Some((start_len, level)) = raw_marker(&source[self.current..], start, level_char) else {
+            return Ok(None);
+        };
+        self.advance_str(&source[self.current..self.current + start_len]);
+        while self.current < source.len() {
+            if let Some((end_len, end_level)) = raw_marker(&source[self.current..], end, level_char) {
+                if end_level == level {
+                    self.advance_str(&source[self.current..self.current + end_len]);
+                    return Ok(Some(TokenKind::StringLiteral));
+                }
+            }
+            self.advance(source);
+        }
+        self.unterminated_string(source, config, TokenKind::StringLiteral)
+    }
+    /// a string literal or a quoted identifier reaching the end of the source code,
+    /// handled according to ScannerConfig::unterminated_strings
+    fn unterminated_string(
+        &mut self,
+        source: &str,
+        config: &CompiledConfig,
+        kind: TokenKind,
+    ) -> Result<Option<TokenKind>, ScanFailure> {
+        match config.unterminated_strings {
+            UnterminatedStringPolicy::Error => Err((kind, self.error(source, ScanError::UnexpectedEof))),
+            UnterminatedStringPolicy::RecoverAtLineEnd => {
+                if let Some(len) = source[self.start..self.current].find('\n') {
+                    // go back to the end of the first line of the string
+                    self.current = self.start + len;
+                    let first_line = &source[self.start..self.current];
+                    self.current_char = self.start_char + first_line.chars().count();
+                    self.line = self.start_line;
+                }
+                Ok(Some(kind))
             }
+            UnterminatedStringPolicy::RecoverAtEof => Ok(Some(kind)),
         }
-        Some(TokenType::NumberLiteral(format!("0b{}", value), number))
     }
-    fn scan_hex_number(&mut self, data: &mut ScannerData) -> Option<TokenType> {
-        let mut number = 0.0;
-        let mut value = String::new();
+    /// scan the text of an interpolated string up to the closing delimiter or the next interpolation
+    fn scan_string_part(&mut self, source: &str, config: &CompiledConfig) -> Result<TokenKind, ScanFailure> {
+        let mut escape = false;
         loop {
-            let c = data.source[self.current];
-            match c {
-                '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' => {
-                    number = number * 16.0 + Number::from((c as u8) - b'0');
-                    value.push(c);
+            if let Some(start) = config.interpolation_start {
+                if !escape && self.matches(start, source) {
+                    if self.current > self.start {
+                        return Ok(TokenKind::StringPart);
+                    }
+                    self.advance_str(start);
+                    self.modes.push(Mode::Interpolation(0));
+                    return Ok(TokenKind::InterpolationStart);
                 }
-                'a' | 'b' | 'c' | 'd' | 'e' | 'f' => {
-                    number = number * 16.0 + Number::from((c as u8) - b'a' + 10);
-                    value.push(c);
+            }
+            match self.advance(source) {
+                None => {
+                    return Err((
+                        TokenKind::StringPart,
+                        self.error(source, ScanError::UnexpectedEof),
+                    ))
                 }
-                'A' | 'B' | 'C' | 'D' | 'E' | 'F' => {
-                    number = number * 16.0 + Number::from((c as u8) - b'A' + 10);
-                    value.push(c);
+                Some('\\') if !escape => escape = true,
+                Some(c) => {
+                    if Some(c) == config.interpolated_string && !escape {
+                        self.modes.pop();
+                        return Ok(TokenKind::StringPart);
+                    }
+                    escape = false;
                 }
-                _ => break,
             }
-            self.current += 1;
-            if self.current == data.source.len() {
-                break;
+        }
+    }
+    /// inside an interpolation, detect the end of the interpolation and count nested brackets
+    fn scan_interpolation_end(&mut self, source: &str, config: &CompiledConfig) -> Option<TokenKind> {
+        let Some(Mode::Interpolation(depth)) = self.modes.last().copied() else {
+            return None;
+        };
+        let end = config.interpolation_end?;
+        let opening = config.interpolation_start.and_then(|start| start.chars().last());
+        let depth = if self.matches(end, source) {
+            if depth == 0 {
+                self.advance_str(end);
+                self.modes.pop();
+                return Some(TokenKind::InterpolationEnd);
+            }
+            depth - 1
+        } else if opening.is_some() && self.peek(source) == opening {
+            depth + 1
+        } else {
+            return None;
+        };
+        if let Some(mode) = self.modes.last_mut() {
+            *mode = Mode::Interpolation(depth);
+        }
+        None
+    }
+    fn scan_heredoc(
+        &mut self,
+        source: &str,
+        config: &CompiledConfig,
+    ) -> Result<Option<TokenKind>, ScanFailure> {
+        let Some(start) = config.heredoc_start else {
+            return Ok(None);
+        };
+        let Some((_, tag)) = heredoc_tag(&source[self.current..], start) else {
+            return Ok(None);
+        };
+        // skip the end of the opening line
+        self.advance_while(source, |c| c != '\n');
+        while self.advance(source).is_some() {
+            let line_end = source[self.current..]
+                .find('\n')
+                .map_or(source.len(), |pos| self.current + pos);
+            let line = &source[self.current..line_end];
+            self.advance_str(line);
+            if line.trim() == tag {
+                return Ok(Some(TokenKind::StringLiteral));
             }
         }
-        Some(TokenType::NumberLiteral(format!("0x{}", value), number))
+        self.unterminated_string(source, config, TokenKind::StringLiteral)
+    }
+    /// a string or character literal with one of the ScannerConfig::string_prefixes
+    fn scan_prefixed_string(
+        &mut self,
+        source: &str,
+        config: &CompiledConfig,
+    ) -> Result<Option<TokenKind>, ScanFailure> {
+        let Some(prefix) = config.string_prefix(&source[self.current..]) else {
+            return Ok(None);
+        };
+        let (current, current_char) = (self.current, self.current_char);
+        self.advance_str(prefix);
+        self.prefix_len = prefix.len();
+        let token = match self.scan_raw_string(source, config)? {
+            Some(token) => Some(token),
+            None => match self.scan_string(source, config)? {
+                Some(token) => Some(token),
+                None => self.scan_char(source, config),
+            },
+        };
+        if token.is_none() {
+            // not a character literal, like `b'` followed by a lifetime
+            self.current = current;
+            self.current_char = current_char;
+            self.prefix_len = 0;
+        }
+        Ok(token)
     }
-    fn scan_identifier(&mut self, data: &mut ScannerData) -> Option<TokenType> {
-        if is_alpha(data.source[self.current]) {
-            let mut value = String::new();
-            while self.current < data.source.len() && is_alphanum(data.source[self.current]) {
-                value.push(data.source[self.current]);
-                self.current += 1;
+    /// a single character or escape sequence between two delimiters
+    fn scan_char(&mut self, source: &str, config: &CompiledConfig) -> Option<TokenKind> {
+        let delimiter = config.char_delimiter?;
+        if self.peek(source) != Some(delimiter) {
+            return None;
+        }
+        let len = match self.peek_nth(source, 1)? {
+            '\\' => {
+                // the escape sequence ends at the closing delimiter
+                let escaped = source[self.current..].chars().skip(3).take_while(|c| *c != delimiter && *c != '\n');
+                3 + escaped.count()
             }
-            return Some(TokenType::Identifier(value));
+            c if c == delimiter || c == '\n' => return None,
+            _ => 2,
+        };
+        if self.peek_nth(source, len) != Some(delimiter) {
+            return None;
+        }
+        for _ in 0..=len {
+            self.advance(source);
+        }
+        Some(TokenKind::CharLiteral)
+    }
+    fn scan_lifetime(&mut self, source: &str, config: &CompiledConfig) -> Option<TokenKind> {
+        let delimiter = config.char_delimiter.filter(|_| config.lifetimes)?;
+        let rest = source[self.current..].strip_prefix(delimiter)?;
+        if !rest.starts_with(config.identifier_start) {
+            return None;
+        }
+        let len = rest
+            .char_indices()
+            .skip(1)
+            .find(|(_, c)| !(config.identifier_char)(*c))
+            .map_or(rest.len(), |(i, _)| i);
+        if rest[len..].starts_with(delimiter) {
+            return None;
+        }
+        self.advance(source);
+        self.advance_str(&rest[..len]);
+        Some(TokenKind::Lifetime)
+    }
+    fn scan_newline(&mut self, source: &str) -> Option<TokenKind> {
+        if self.peek(source) == Some('\n') {
+            self.advance(source);
+            return Some(TokenKind::NewLine);
         }
         None
     }
-    fn scan_space(&mut self, data: &mut ScannerData) -> Option<TokenType> {
-        let start = self.current;
-        while self.current < data.source.len() && is_space(data.source[self.current]) {
-            self.current += 1;
+    fn scan_symbol(&mut self, source: &str, config: &CompiledConfig) -> Option<TokenKind> {
+        let text = &source[self.current..];
+        let len = config.symbol_trie.find(text, |_| true)?;
+        self.advance_str(&text[..len]);
+        if config.punctuation.contains(&&text[..len]) {
+            return Some(TokenKind::Punctuation);
         }
-        if start == self.current {
+        Some(TokenKind::Symbol)
+    }
+    fn scan_regex(&mut self, source: &str, config: &CompiledConfig) -> Option<TokenKind> {
+        if !config.regex_literals || !self.regex_allowed {
             return None;
         }
-        Some(TokenType::Ignore)
-    }
-    fn scan_string(&mut self, data: &mut ScannerData) -> Result<Option<TokenType>, ScanError> {
-        if data.source[self.current] == '\"' {
-            self.current += 1;
-            let mut escape = false;
-            let mut value = String::new();
-            while self.current < data.source.len() {
-                let c = data.source[self.current];
-                if c == '\\' && !escape {
-                    escape = true;
-                } else {
-                    if c == '\"' && !escape {
-                        self.current += 1;
-                        return Ok(Some(TokenType::StringLiteral(value)));
-                    } else if c == 'n' && escape {
-                        value.push('\n');
-                    } else if c == 't' && escape {
-                        value.push('\t');
-                    } else {
-                        value.push(c);
-                        if c == '\n' {
-                            self.line += 1;
-                        }
-                    }
-                    escape = false;
+        let text = &source[self.current..];
+        if !text.starts_with('/') || text.starts_with("//") || text.starts_with("/*") {
+            return None;
+        }
+        let mut escaped = false;
+        let mut in_class = false;
+        for (i, c) in text.char_indices().skip(1) {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '[' => in_class = true,
+                ']' => in_class = false,
+                '/' if !in_class => {
+                    let flags = text[i + 1..]
+                        .find(|c: char| !c.is_ascii_alphabetic())
+                        .unwrap_or(text.len() - i - 1);
+                    self.advance_str(&text[..i + 1 + flags]);
+                    return Some(TokenKind::RegexLiteral);
                 }
-                self.current += 1;
-            }
-            data.token_len.push(data.source.len() - self.start + 1);
-            data.token_start.push(self.start);
-            data.token_types.push(TokenType::StringLiteral(value));
-            data.token_lines.push(self.line);
-            let token_id = data.token_len.len() - 1;
-            return Err(ScanError::UnexpectedEof(
-                self.line,
-                data.token_start[token_id],
-            ));
+                '\n' | '\r' => return None,
+                _ => (),
+            }
         }
-        Ok(None)
+        None
+    }
+    fn scan_literal_word(&mut self, source: &str, config: &CompiledConfig) -> Option<TokenKind> {
+        if config.true_literals.is_empty()
+            && config.false_literals.is_empty()
+            && config.nil_literals.is_empty()
+        {
+            return None;
+        }
+        let rest = &source[self.current..];
+        if !rest.starts_with(config.identifier_start) {
+            return None;
+        }
+        let start_len = rest.chars().next().map_or(0, char::len_utf8);
+        let word = &rest[..start_len + config.identifier_len(&rest[start_len..])];
+        let kind = if config.true_literals.contains(&word) || config.false_literals.contains(&word) {
+            TokenKind::BoolLiteral
+        } else if config.nil_literals.contains(&word) {
+            TokenKind::NilLiteral
+        } else {
+            return None;
+        };
+        self.advance_str(word);
+        Some(kind)
+    }
+    fn scan_keyword(&mut self, source: &str, config: &CompiledConfig) -> Option<TokenKind> {
+        let text = &source[self.current..];
+        let len = config.keyword_trie.find(text, |len| {
+            !text[len..].chars().next().is_some_and(config.identifier_char)
+        })?;
+        self.advance_str(&text[..len]);
+        Some(TokenKind::Keyword)
+    }
+    fn matches(&self, s: &str, source: &str) -> bool {
+        source[self.current..].starts_with(s)
+    }
+    fn peek(&self, source: &str) -> Option<char> {
+        source[self.current..].chars().next()
+    }
+    fn peek_nth(&self, source: &str, n: usize) -> Option<char> {
+        source[self.current..].chars().nth(n)
+    }
+    /// build an error for the current token
+    fn error(&mut self, source: &str, error: fn(ErrorSpan) -> ScanError) -> ScanError {
+        error(ErrorSpan {
+            span: Span::new(self.start, self.current - self.start),
+            line: self.line_number(self.line),
+            offset: self.start_char,
+            column: self.start_column(source),
+        })
     }
-    fn scan_newline(&mut self, data: &ScannerData) -> Option<TokenType> {
-        if data.source[self.current] == '\n' {
-            self.current += 1;
+    /// consume the current character
+    fn advance(&mut self, source: &str) -> Option<char> {
+        let c = self.peek(source)?;
+        self.current += c.len_utf8();
+        self.current_char += 1;
+        if c == '\n' {
             self.line += 1;
-            return Some(TokenType::NewLine);
         }
-        None
+        Some(c)
     }
-    fn scan_symbol(&mut self, data: &ScannerData, config: &ScannerConfig) -> Option<TokenType> {
-        for s in config.symbols.iter() {
-            if self.matches(s, data) {
-                self.current += s.len();
-                return Some(TokenType::Symbol((*s).to_owned()));
+    /// consume a string that is known to match the source at current position
+    fn advance_str(&mut self, s: &str) {
+        self.current += s.len();
+        for c in s.chars() {
+            self.current_char += 1;
+            if c == '\n' {
+                self.line += 1;
             }
         }
-        None
     }
-    fn scan_keyword(&mut self, data: &ScannerData, config: &ScannerConfig) -> Option<TokenType> {
-        let source_len = data.source.len();
-        for s in config.keywords.iter() {
-            let keyword_len = s.len();
-            if self.matches(s, data)
-                && (self.current + keyword_len >= source_len
-                    || !is_alphanum(data.source[self.current + keyword_len]))
-            {
-                self.current += s.len();
-                return Some(TokenType::Keyword((*s).to_owned()));
+    fn advance_while(&mut self, source: &str, pred: impl Fn(char) -> bool) {
+        #[cfg(feature = "simd")]
+        {
+            let rest = &source.as_bytes()[self.current..];
+            let len = crate::simd::ascii_prefix_len(rest, &pred);
+            if pred('\n') {
+                self.line += rest[..len].iter().filter(|b| **b == b'\n').count();
             }
+            self.current += len;
+            self.current_char += len;
+        }
+        while self.peek(source).is_some_and(&pred) {
+            self.advance(source);
+        }
+    }
+}
+
+impl<'a> CompiledConfig<'a> {
+    /// length in bytes of the identifier characters at the start of a text
+    fn identifier_len(&self, text: &str) -> usize {
+        #[cfg(feature = "simd")]
+        let ascii_len = crate::simd::ascii_set_len(text.as_bytes(), self.identifier_set);
+        #[cfg(not(feature = "simd"))]
+        let ascii_len = 0;
+        text[ascii_len..]
+            .char_indices()
+            .find(|(_, c)| !(self.identifier_char)(*c))
+            .map_or(text.len(), |(i, _)| ascii_len + i)
+    }
+    /// build the value of a token from its text in the source code
+    fn token_value(&self, kind: TokenKind, text: &str) -> TokenType {
+        match kind {
+            TokenKind::Symbol => TokenType::Symbol(text.to_owned()),
+            TokenKind::Punctuation => TokenType::Punctuation(text.to_owned()),
+            TokenKind::Identifier => TokenType::Identifier(self.identifier_value(text)),
+            TokenKind::Keyword => TokenType::Keyword(text.to_owned()),
+            TokenKind::Comment => TokenType::Comment(text.to_owned()),
+            TokenKind::Directive => TokenType::Directive(text.to_owned()),
+            TokenKind::Attribute => TokenType::Attribute(text.to_owned()),
+            TokenKind::Custom(_) => TokenType::Custom(kind.class().unwrap_or_default(), text.to_owned()),
+            TokenKind::StringLiteral => {
+                let (prefix, literal) = self.split_string_prefix(text);
+                if prefix.contains(['r', 'R']) {
+                    TokenType::StringLiteral(self.quoted_value(literal, false))
+                } else {
+                    TokenType::StringLiteral(self.string_value(literal))
+                }
+            }
+            TokenKind::StringPart => TokenType::StringPart(self.string_part_value(text)),
+            TokenKind::InterpolationStart => TokenType::InterpolationStart,
+            TokenKind::InterpolationEnd => TokenType::InterpolationEnd,
+            TokenKind::CharLiteral => {
+                let (_, literal) = self.split_string_prefix(text);
+                TokenType::CharLiteral(unescape(literal).chars().next().unwrap_or('\0'))
+            }
+            TokenKind::Lifetime => TokenType::Lifetime(text.chars().skip(1).collect()),
+            TokenKind::NumberLiteral => {
+                let (number, suffix) = self.split_number_suffix(text);
+                let (radix, digits) = self.number_base(number);
+                TokenType::NumberLiteral(
+                    text.to_owned(),
+                    number_value(digits, radix),
+                    suffix.map(str::to_owned),
+                    radix,
+                )
+            }
+            TokenKind::BoolLiteral => TokenType::BoolLiteral(self.true_literals.contains(&text)),
+            TokenKind::NilLiteral => TokenType::NilLiteral,
+            TokenKind::RegexLiteral => {
+                let end = text.rfind('/').unwrap_or(0).max(1);
+                TokenType::RegexLiteral(
+                    text.get(1..end).unwrap_or_default().to_owned(),
+                    text.get(end..).unwrap_or_default().trim_start_matches('/').to_owned(),
+                )
+            }
+            TokenKind::Ignore => TokenType::Ignore,
+            TokenKind::NewLine => TokenType::NewLine,
+            TokenKind::Eof => TokenType::Eof,
+            TokenKind::Unknown => TokenType::Unknown,
+        }
+    }
+    /// the longest string prefix starting a text, if it is followed by a string
+    /// or a character delimiter
+    fn string_prefix<'t>(&self, text: &'t str) -> Option<&'t str> {
+        self.string_prefixes
+            .iter()
+            .filter(|prefix| {
+                !prefix.is_empty()
+                    && text.starts_with(**prefix)
+                    && (text[prefix.len()..].chars().next().is_some_and(|c| {
+                        Some(c) == self.char_delimiter
+                            || self.string_delimiters.iter().any(|d| d.starts_with(c))
+                    }) || self.raw_string_start.is_some_and(|start| {
+                        raw_marker(&text[prefix.len()..], start, self.raw_string_level).is_some()
+                    }))
+            })
+            .max_by_key(|prefix| prefix.len())
+            .map(|prefix| &text[..prefix.len()])
+    }
+    /// separate the prefix of a string or character literal from the delimited literal
+    fn split_string_prefix<'t>(&self, text: &'t str) -> (&'t str, &'t str) {
+        self.string_prefix(text).map_or(("", text), |prefix| text.split_at(prefix.len()))
+    }
+    /// the name of an identifier, without the delimiters and the escaping of a quoted identifier
+    fn identifier_value(&self, text: &str) -> String {
+        for delimiter in self.quoted_identifiers.iter().filter(|d| !d.is_empty()) {
+            if let Some(name) = text.strip_prefix(delimiter) {
+                // the closing delimiter is missing if the identifier is unterminated
+                let name = name.strip_suffix(delimiter).unwrap_or(name);
+                return name.replace(&delimiter.repeat(2), delimiter);
+            }
+        }
+        text.to_owned()
+    }
+    /// separate a number literal from its type suffix.
+    /// The suffix cannot start with a digit of the literal base since those belong to the number,
+    /// except after the decimal exponent of a hexadecimal float
+    fn split_number_suffix<'t>(&self, text: &'t str) -> (&'t str, Option<&'t str>) {
+        let (radix, digits) = number_prefix(text);
+        let radix = if radix == 16 && self.hex_float && digits.contains(['p', 'P']) {
+            10
+        } else {
+            radix
+        };
+        self.number_suffixes
+            .iter()
+            .filter(|suffix| {
+                text.len() > suffix.len()
+                    && text.ends_with(**suffix)
+                    && !suffix.starts_with(|c: char| c.is_digit(radix))
+            })
+            .max_by_key(|suffix| suffix.len())
+            .map_or((text, None), |suffix| {
+                let (number, suffix) = text.split_at(text.len() - suffix.len());
+                (number, Some(suffix))
+            })
+    }
+    /// the base of a number literal and its digits without the base prefix
+    fn number_base<'t>(&self, text: &'t str) -> (u32, &'t str) {
+        let (radix, digits) = number_prefix(text);
+        let legacy_octal = radix == 10
+            && self.legacy_octal
+            && digits.len() > 1
+            && digits.starts_with('0')
+            && digits
+                .chars()
+                .all(|c| c.is_digit(8) || Some(c) == self.number_separator);
+        if legacy_octal {
+            (8, digits)
+        } else {
+            (radix, digits)
+        }
+    }
+    fn string_part_value(&self, text: &str) -> String {
+        let delimiter = self.interpolated_string;
+        let content = match delimiter {
+            Some(delimiter) => text.strip_prefix(delimiter).unwrap_or(text),
+            None => text,
+        };
+        if self.decode_escapes {
+            decode_escapes(content, delimiter)
+        } else {
+            raw_content(content, delimiter).to_owned()
+        }
+    }
+    fn string_value(&self, text: &str) -> String {
+        if let Some(start) = self.heredoc_start {
+            if let Some((_, tag)) = heredoc_tag(text, start) {
+                let Some((_, content)) = text.split_once('\n') else {
+                    return String::new();
+                };
+                let body = match content.rfind('\n') {
+                    Some(pos) if content[pos + 1..].trim() == tag => &content[..pos],
+                    None if content.trim() == tag => "",
+                    // unterminated heredoc
+                    _ => content,
+                };
+                return body.to_owned();
+            }
+        }
+        if let (Some(start), Some(end)) = (self.raw_string_start, self.raw_string_end) {
+            if let Some((start_len, level)) = raw_marker(text, start, self.raw_string_level) {
+                let content = &text[start_len..];
+                let level_len = self.raw_string_level.map_or(0, char::len_utf8);
+                let end_len = end.len() + level * level_len;
+                // the end marker is missing if the string is unterminated
+                let terminated = content.len() >= end_len
+                    && content.is_char_boundary(content.len() - end_len)
+                    && raw_marker(&content[content.len() - end_len..], end, self.raw_string_level)
+                        == Some((end_len, level));
+                if terminated {
+                    return content[..content.len() - end_len].to_owned();
+                }
+                return content.to_owned();
+            }
+        }
+        self.quoted_value(text, self.decode_escapes)
+    }
+    /// the content of a string literal between its delimiters,
+    /// with the escape sequences decoded or kept as is
+    fn quoted_value(&self, text: &str, decode: bool) -> String {
+        let Some(delimiter) = self.string_delimiters.iter().find(|d| text.starts_with(**d)) else {
+            return text.to_owned();
+        };
+        let content = &text[delimiter.len()..];
+        if delimiter.chars().nth(1).is_some() {
+            // the content may contain the first character of the delimiter
+            let content = content.strip_suffix(delimiter).unwrap_or(content);
+            return if decode {
+                decode_escapes(content, None)
+            } else {
+                content.to_owned()
+            };
+        }
+        let delimiter = delimiter.chars().next();
+        if decode {
+            decode_escapes(content, delimiter)
+        } else {
+            raw_content(content, delimiter).to_owned()
+        }
+    }
+}
+
+/// check if a text starts with a heredoc marker followed by its terminator identifier.
+/// Returns the length of the opening marker in bytes and the terminator
+fn heredoc_tag<'a>(text: &'a str, marker: &str) -> Option<(usize, &'a str)> {
+    let rest = text.strip_prefix(marker)?;
+    let quote = rest.chars().next().filter(|c| *c == '\'' || *c == '\"');
+    let tag_start = marker.len() + quote.map_or(0, char::len_utf8);
+    let tag_len = text[tag_start..]
+        .find(|c| !is_alphanum(c))
+        .unwrap_or(text.len() - tag_start);
+    if tag_len == 0 {
+        return None;
+    }
+    let tag = &text[tag_start..tag_start + tag_len];
+    let mut len = tag_start + tag_len;
+    if let Some(quote) = quote {
+        if !text[len..].starts_with(quote) {
+            return None;
+        }
+        len += 1;
+    }
+    Some((len, tag))
+}
+
+/// check if a text starts with a raw string marker,
+/// with the level character repeated after its first character.
+/// Returns the marker length in bytes and its level
+fn raw_marker(text: &str, marker: &str, level_char: Option<char>) -> Option<(usize, usize)> {
+    let first = marker.chars().next()?;
+    if !text.starts_with(first) {
+        return None;
+    }
+    let rest = &marker[first.len_utf8()..];
+    let mut len = first.len_utf8();
+    let mut level = 0;
+    if let Some(level_char) = level_char {
+        while text[len..].starts_with(level_char) {
+            len += level_char.len_utf8();
+            level += 1;
         }
-        None
     }
-    fn matches(&self, s: &str, data: &ScannerData) -> bool {
-        let mut check = true;
-        let source_len = data.source.len();
-        for (i, c) in s.chars().enumerate() {
-            if self.current + i >= source_len || data.source[self.current + i] != c {
-                check = false;
+    text[len..].starts_with(rest).then_some((len + rest.len(), level))
+}
+
+/// decode the escape sequences of a string or character literal (delimiting quotes included).
+/// The closing quote may be missing if the string is unterminated
+fn unescape(text: &str) -> String {
+    let delimiter = text.chars().next();
+    decode_escapes(&text[delimiter.map_or(0, char::len_utf8)..], delimiter)
+}
+
+/// decode the escape sequences of a text, up to an optional closing delimiter
+/// Supports \n, \t, \xNN, \uXXXX (with UTF-16 surrogate pairs) and \u{X...}.
+/// Other escaped characters are kept without the backslash.
+fn decode_escapes(text: &str, delimiter: Option<char>) -> String {
+    let mut value = String::new();
+    let mut escape = false;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' && !escape {
+            escape = true;
+        } else {
+            if Some(c) == delimiter && !escape {
                 break;
+            } else if c == 'n' && escape {
+                value.push('\n');
+            } else if c == 't' && escape {
+                value.push('\t');
+            } else if (c == 'x' || c == 'u') && escape {
+                match decode_code_point(c, &mut chars) {
+                    Some(decoded) => value.push(decoded),
+                    None => value.push(c),
+                }
+            } else {
+                value.push(c);
+            }
+            escape = false;
+        }
+    }
+    value
+}
+
+/// a text up to an optional closing delimiter, with its escape sequences kept as is
+fn raw_content(text: &str, delimiter: Option<char>) -> &str {
+    let mut escape = false;
+    for (i, c) in text.char_indices() {
+        if Some(c) == delimiter && !escape {
+            return &text[..i];
+        }
+        escape = c == '\\' && !escape;
+    }
+    text
+}
+
+/// decode the code point following \x or \u.
+/// The iterator is only advanced if the escape sequence is valid
+fn decode_code_point(escape: char, chars: &mut std::str::Chars) -> Option<char> {
+    let rest = chars.as_str();
+    let (code, len) = if escape == 'x' {
+        (hex_value(rest.get(..2)?)?, 2)
+    } else if let Some(braced) = rest.strip_prefix('{') {
+        let end = braced.find('}')?;
+        (hex_value(&braced[..end])?, end + 2)
+    } else {
+        let code = hex_value(rest.get(..4)?)?;
+        // UTF-16 surrogate pair
+        match (code, rest.get(4..6), rest.get(6..10).and_then(hex_value)) {
+            (0xd800..=0xdbff, Some("\\u"), Some(low @ 0xdc00..=0xdfff)) => {
+                (0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00), 10)
+            }
+            _ => (code, 4),
+        }
+    };
+    let c = char::from_u32(code)?;
+    *chars = rest[len..].chars();
+    Some(c)
+}
+
+fn hex_value(digits: &str) -> Option<u32> {
+    if digits.is_empty() || digits.len() > 8 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    u32::from_str_radix(digits, 16).ok()
+}
+
+/// compute the value of a number literal from its digits, without the base prefix.
+/// Literals without a fractional part or an exponent are integers,
+/// unless they don't fit in an i64.
+/// Decimal floats are parsed by the standard library to get the nearest f64.
+/// Hexadecimal exponents (p) are powers of 2
+fn number_value(literal: &str, radix: u32) -> NumberValue {
+    let (markers, base) = match radix {
+        10 => (['e', 'E'], 10.0),
+        16 => (['p', 'P'], 2.0),
+        _ => (['\0', '\0'], 1.0),
+    };
+    let (digits, exponent) = match literal.find(markers) {
+        Some(pos) => (&literal[..pos], Some(literal[pos + 1..].parse().unwrap_or(0))),
+        None => (literal, None),
+    };
+    if exponent.is_none() && !digits.contains('.') {
+        let int = digits
+            .chars()
+            .filter_map(|c| c.to_digit(radix))
+            .try_fold(0i64, |acc, digit| {
+                acc.checked_mul(i64::from(radix))?.checked_add(i64::from(digit))
+            });
+        if let Some(int) = int {
+            return NumberValue::Int(int);
+        }
+    }
+    if radix == 10 {
+        // remove the digit separators
+        let lexeme: String = literal
+            .chars()
+            .filter(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-'))
+            .collect();
+        return NumberValue::Float(lexeme.parse().unwrap_or(f64::NAN));
+    }
+    let mut number = 0.0;
+    let mut div = 1.0;
+    let mut fraction = false;
+    for c in digits.chars() {
+        if c == '.' {
+            fraction = true;
+        } else if let Some(digit) = c.to_digit(radix) {
+            number = number * f64::from(radix) + f64::from(digit);
+            if fraction {
+                div *= f64::from(radix);
             }
         }
-        check
     }
+    NumberValue::Float(number / div * f64::powi(base, exponent.unwrap_or(0)))
+}
+
+/// the base of a number literal from its prefix, and the literal without the prefix
+fn number_prefix(text: &str) -> (u32, &str) {
+    match text.get(..2) {
+        Some("0x" | "0X") => (16, &text[2..]),
+        Some("0b" | "0B") => (2, &text[2..]),
+        Some("0o" | "0O") => (8, &text[2..]),
+        _ => (10, text),
+    }
+}
+
+/// default ScannerConfig::identifier_start : ASCII letters and underscore
+pub fn is_alpha(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
 }
 
-fn is_digit(c: char) -> bool {
-    c >= '0' && c <= '9'
+/// default ScannerConfig::identifier_char : ASCII letters, digits and underscore
+pub fn is_alphanum(c: char) -> bool {
+    c.is_ascii_digit() || is_alpha(c)
 }
 
-fn is_alpha(c: char) -> bool {
-    (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z') || c == '_'
+/// Unicode identifier start : letters from any script and underscore.
+/// An approximation of XID_Start (UAX #31) based on char::is_alphabetic
+pub fn is_unicode_alpha(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
 }
 
-fn is_alphanum(c: char) -> bool {
-    is_digit(c) || is_alpha(c)
+/// Unicode identifier character : letters and digits from any script and underscore.
+/// An approximation of XID_Continue (UAX #31) based on char::is_alphanumeric
+pub fn is_unicode_alphanum(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
 }
 
-fn is_space(c: char) -> bool {
+/// default ScannerConfig::space_char : space, tabulation and carriage return
+pub fn is_space(c: char) -> bool {
     c == ' ' || c == '\t' || c == '\r'
 }
+
+/// the column after a text starting at a column, a tabulation going to the next tab stop
+fn expand_column(column: usize, text: &str, tab_width: usize) -> usize {
+    #[cfg(feature = "simd")]
+    if crate::simd::find_byte(text.as_bytes(), b'\t').is_none() {
+        return column + crate::simd::char_count(text.as_bytes());
+    }
+    text.chars().fold(column, |column, c| match c {
+        '\t' => (column / tab_width + 1) * tab_width,
+        _ => column + 1,
+    })
+}
+
+/// a control character other than the tabulations and new lines, see ControlCharPolicy
+fn is_control_char(c: char) -> bool {
+    c.is_control() && !matches!(c, '\t' | '\n' | '\r')
+}
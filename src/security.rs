@@ -0,0 +1,127 @@
+//! Unicode characters hiding the real meaning of a source code, like in the Trojan Source attacks:
+//! bidirectional controls reordering the displayed text, invisible characters
+//! and identifiers made of homoglyphs of ASCII letters
+use crate::{ScannerData, Span, TokenKind};
+
+/// A suspicious use of Unicode found by find_unicode_issues
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnicodeIssue {
+    /// a bidirectional control character, which can make the displayed code
+    /// differ from the code seen by the compiler
+    BidiControl(char),
+    /// a character without glyph, like a zero width space
+    Invisible(char),
+    /// an identifier which looks like an ASCII identifier but contains other characters,
+    /// like a Cyrillic 'а' instead of a Latin 'a'. skeleton is the ASCII identifier it looks like
+    Confusable { skeleton: String },
+}
+
+impl std::fmt::Display for UnicodeIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnicodeIssue::BidiControl(c) => {
+                write!(f, "bidirectional control character U+{:04X}", *c as u32)
+            }
+            UnicodeIssue::Invisible(c) => write!(f, "invisible character U+{:04X}", *c as u32),
+            UnicodeIssue::Confusable { skeleton } => {
+                write!(f, "identifier confusable with '{}'", skeleton)
+            }
+        }
+    }
+}
+
+/// A suspicious character or identifier in a token
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnicodeWarning {
+    pub issue: UnicodeIssue,
+    /// index of the token containing the issue
+    pub token: usize,
+    /// position of the character, or of the whole identifier for a confusable, in bytes
+    pub span: Span,
+    /// line of the character
+    pub line: usize,
+}
+
+/// the bidirectional control characters, embeddings, overrides, isolates and marks
+const BIDI_CONTROLS: &[char] = &[
+    '\u{061C}', '\u{200E}', '\u{200F}', '\u{202A}', '\u{202B}', '\u{202C}', '\u{202D}', '\u{202E}',
+    '\u{2066}', '\u{2067}', '\u{2068}', '\u{2069}',
+];
+
+/// the characters displayed without glyph
+const INVISIBLES: &[char] = &[
+    '\u{00AD}', '\u{200B}', '\u{200C}', '\u{200D}', '\u{2060}', '\u{FEFF}',
+];
+
+/// Cyrillic and Greek letters displayed like an ASCII letter
+const CONFUSABLES: &[(char, char)] = &[
+    ('а', 'a'), ('е', 'e'), ('о', 'o'), ('р', 'p'), ('с', 'c'), ('у', 'y'), ('х', 'x'),
+    ('і', 'i'), ('ј', 'j'), ('ѕ', 's'), ('һ', 'h'), ('ԁ', 'd'), ('А', 'A'), ('В', 'B'),
+    ('Е', 'E'), ('К', 'K'), ('М', 'M'), ('Н', 'H'), ('О', 'O'), ('Р', 'P'), ('С', 'C'),
+    ('Т', 'T'), ('Х', 'X'), ('І', 'I'), ('Ј', 'J'), ('Ѕ', 'S'), ('α', 'a'), ('ο', 'o'),
+    ('ν', 'v'), ('ι', 'i'), ('Α', 'A'), ('Β', 'B'), ('Ε', 'E'), ('Ζ', 'Z'), ('Η', 'H'),
+    ('Ι', 'I'), ('Κ', 'K'), ('Μ', 'M'), ('Ν', 'N'), ('Ο', 'O'), ('Ρ', 'P'), ('Τ', 'T'),
+    ('Υ', 'Y'), ('Χ', 'X'),
+];
+
+/// the bidirectional controls and invisible characters in all the tokens, and the identifiers
+/// confusable with an ASCII identifier, in the order of the source code.
+/// An identifier is confusable when all its characters are ASCII or homoglyphs of ASCII letters,
+/// so identifiers written in another script are not reported.
+/// Comments must be tokens to be checked, so the source code must be scanned with comments
+/// enabled (the default) and without trivia
+pub fn find_unicode_issues(data: &ScannerData) -> Vec<UnicodeWarning> {
+    let mut warnings = Vec::new();
+    for index in 0..data.len() {
+        let span = data.token_spans[index];
+        let text = data.token_text(index);
+        let mut line = data.token_line(index);
+        for (pos, c) in text.char_indices() {
+            if c == '\n' {
+                line += 1;
+                continue;
+            }
+            let issue = if BIDI_CONTROLS.contains(&c) {
+                UnicodeIssue::BidiControl(c)
+            } else if INVISIBLES.contains(&c) {
+                UnicodeIssue::Invisible(c)
+            } else {
+                continue;
+            };
+            warnings.push(UnicodeWarning {
+                issue,
+                token: index,
+                span: Span::new(span.start() + pos, c.len_utf8()),
+                line,
+            });
+        }
+        if data.token_kinds[index] == TokenKind::Identifier {
+            if let Some(skeleton) = skeleton(text) {
+                warnings.push(UnicodeWarning {
+                    issue: UnicodeIssue::Confusable { skeleton },
+                    token: index,
+                    span,
+                    line: data.token_line(index),
+                });
+            }
+        }
+    }
+    warnings
+}
+
+/// the ASCII identifier looking like an identifier containing homoglyphs, None if the identifier
+/// is pure ASCII or contains a character without ASCII homoglyph
+fn skeleton(text: &str) -> Option<String> {
+    if text.is_ascii() {
+        return None;
+    }
+    text.chars()
+        .map(|c| {
+            if c.is_ascii() {
+                Some(c)
+            } else {
+                CONFUSABLES.iter().find(|(glyph, _)| *glyph == c).map(|(_, ascii)| *ascii)
+            }
+        })
+        .collect()
+}
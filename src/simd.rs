@@ -0,0 +1,80 @@
+//! Fast paths for the long runs of spaces, identifier characters and comment text,
+//! enabled by the `simd` feature. The bytes are tested 8 at a time in a u64
+//! (SIMD within a register), which works on stable Rust without dependencies.
+//! See the bench example for the gain.
+
+const WORD: usize = std::mem::size_of::<u64>();
+const LOW_BITS: u64 = 0x0101_0101_0101_0101;
+const HIGH_BITS: u64 = 0x8080_8080_8080_8080;
+
+fn read_word(bytes: &[u8]) -> u64 {
+    let mut word = [0; WORD];
+    word.copy_from_slice(&bytes[..WORD]);
+    u64::from_le_bytes(word)
+}
+
+/// whether a word contains the byte
+fn has_byte(word: u64, byte: u8) -> bool {
+    let x = word ^ (LOW_BITS * byte as u64);
+    x.wrapping_sub(LOW_BITS) & !x & HIGH_BITS != 0
+}
+
+/// position of the first occurrence of a byte
+pub(crate) fn find_byte(bytes: &[u8], byte: u8) -> Option<usize> {
+    let mut i = 0;
+    while i + WORD <= bytes.len() && !has_byte(read_word(&bytes[i..]), byte) {
+        i += WORD;
+    }
+    bytes[i..].iter().position(|b| *b == byte).map(|pos| i + pos)
+}
+
+/// length of the longest prefix made of ASCII characters matching the predicate.
+/// Only the runs of spaces are skipped 8 bytes at a time: the other characters
+/// have to be tested one by one with the predicate anyway
+pub(crate) fn ascii_prefix_len(bytes: &[u8], pred: impl Fn(char) -> bool) -> usize {
+    let mut i = 0;
+    if pred(' ') {
+        // skip the indentation 8 spaces at a time
+        while i + WORD <= bytes.len() && read_word(&bytes[i..]) == LOW_BITS * b' ' as u64 {
+            i += WORD;
+        }
+    }
+    i + bytes[i..]
+        .iter()
+        .position(|b| !b.is_ascii() || !pred(*b as char))
+        .unwrap_or(bytes.len() - i)
+}
+
+/// the ASCII characters matching a predicate, as a set of bits indexed by the byte value.
+/// The new line is never in the set, so that the lines don't have to be counted
+pub(crate) fn ascii_set(pred: impl Fn(char) -> bool) -> u128 {
+    (0..128u8)
+        .filter(|b| *b != b'\n' && pred(*b as char))
+        .fold(0, |set, b| set | 1 << b)
+}
+
+/// length of the longest prefix made of the characters of an ascii_set.
+/// The 8 bytes of a word are tested without branching, instead of decoding
+/// each character and calling the predicate
+pub(crate) fn ascii_set_len(bytes: &[u8], set: u128) -> usize {
+    let in_set = |b: u8| b.is_ascii() & (set >> (b & 0x7f) & 1 != 0);
+    let mut i = 0;
+    while i + WORD <= bytes.len() && bytes[i..i + WORD].iter().fold(true, |all, b| all & in_set(*b)) {
+        i += WORD;
+    }
+    i + bytes[i..].iter().position(|b| !in_set(*b)).unwrap_or(bytes.len() - i)
+}
+
+/// number of characters in a UTF-8 text (the bytes which are not continuation bytes)
+pub(crate) fn char_count(bytes: &[u8]) -> usize {
+    let mut count = 0;
+    let mut i = 0;
+    while i + WORD <= bytes.len() {
+        let word = read_word(&bytes[i..]);
+        // continuation bytes are 0b10xxxxxx
+        let continuation = word & !(word << 1) & HIGH_BITS;
+        count += WORD - continuation.count_ones() as usize;
+        i += WORD;
+    }
+    count + bytes[i..].iter().filter(|b| (**b as i8) >= -0x40).count()
+}
@@ -0,0 +1,181 @@
+use std::fs::File;
+use std::path::Path;
+use std::str::Utf8Error;
+use std::sync::OnceLock;
+
+use crate::ScanError;
+
+/// Error while scanning a file with Scanner::run_file
+#[derive(Debug)]
+pub enum FileError {
+    Io(std::io::Error),
+    /// the file is not valid UTF-8
+    Utf8(Utf8Error),
+    Scan(ScanError),
+}
+
+impl std::fmt::Display for FileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileError::Io(e) => write!(f, "{}", e),
+            FileError::Utf8(e) => write!(f, "invalid UTF-8 : {}", e),
+            FileError::Scan(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for FileError {}
+
+impl From<std::io::Error> for FileError {
+    fn from(e: std::io::Error) -> Self {
+        FileError::Io(e)
+    }
+}
+
+impl From<Utf8Error> for FileError {
+    fn from(e: Utf8Error) -> Self {
+        FileError::Utf8(e)
+    }
+}
+
+impl From<ScanError> for FileError {
+    fn from(e: ScanError) -> Self {
+        FileError::Scan(e)
+    }
+}
+
+/// The content of a source file, to be scanned with Scanner::run_file.
+/// With the `mmap` feature on 64 bits unix systems, SourceFile::open_mapped maps the file
+/// in memory instead of reading it.
+pub struct SourceFile {
+    bytes: Bytes,
+    /// result of the UTF-8 validation, done on first use
+    utf8: OnceLock<Result<(), Utf8Error>>,
+}
+
+enum Bytes {
+    Owned(Vec<u8>),
+    #[cfg(all(feature = "mmap", unix, target_pointer_width = "64"))]
+    Mapped(map::Mmap),
+}
+
+impl SourceFile {
+    /// read a whole file in memory
+    pub fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file = File::open(path)?;
+        Ok(Self::new(Self::read(file)?))
+    }
+    /// map a file in memory instead of reading it, which is faster for large files.
+    /// Files which are not regular files, like pipes, or are empty are read instead.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated by this process or another one
+    /// while the SourceFile exists, otherwise the text returned by as_str may change
+    /// or become invalid UTF-8, and reading it may crash the process
+    #[cfg(all(feature = "mmap", unix, target_pointer_width = "64"))]
+    pub unsafe fn open_mapped(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file = File::open(path)?;
+        let metadata = file.metadata()?;
+        let len = metadata.len() as usize;
+        if !metadata.is_file() || len == 0 {
+            // empty files cannot be mapped and the size of special files is unknown
+            return Ok(Self::new(Self::read(file)?));
+        }
+        Ok(Self::new(Bytes::Mapped(map::Mmap::new(&file, len)?)))
+    }
+    fn new(bytes: Bytes) -> Self {
+        Self {
+            bytes,
+            utf8: OnceLock::new(),
+        }
+    }
+    fn read(mut file: File) -> std::io::Result<Bytes> {
+        let mut bytes = Vec::new();
+        std::io::Read::read_to_end(&mut file, &mut bytes)?;
+        Ok(Bytes::Owned(bytes))
+    }
+    /// the raw content of the file
+    pub fn as_bytes(&self) -> &[u8] {
+        match &self.bytes {
+            Bytes::Owned(bytes) => bytes,
+            #[cfg(all(feature = "mmap", unix, target_pointer_width = "64"))]
+            Bytes::Mapped(map) => map.as_bytes(),
+        }
+    }
+    /// the content of the file as text. The UTF-8 validation is done on the first call only
+    pub fn as_str(&self) -> Result<&str, Utf8Error> {
+        let bytes = self.as_bytes();
+        (*self
+            .utf8
+            .get_or_init(|| std::str::from_utf8(bytes).map(|_| ())))?;
+        // SAFETY: the bytes were validated above. They are never modified, which the caller
+        // of open_mapped guarantees for a mapped file
+        Ok(unsafe { std::str::from_utf8_unchecked(bytes) })
+    }
+}
+
+#[cfg(all(feature = "mmap", unix, target_pointer_width = "64"))]
+mod map {
+    use std::fs::File;
+    use std::os::raw::{c_int, c_void};
+    use std::os::unix::io::AsRawFd;
+
+    const PROT_READ: c_int = 1;
+    const MAP_PRIVATE: c_int = 2;
+
+    extern "C" {
+        fn mmap(
+            addr: *mut c_void,
+            len: usize,
+            prot: c_int,
+            flags: c_int,
+            fd: c_int,
+            offset: i64,
+        ) -> *mut c_void;
+        fn munmap(addr: *mut c_void, len: usize) -> c_int;
+    }
+
+    /// a read-only memory mapping of a whole file
+    pub(super) struct Mmap {
+        ptr: *mut c_void,
+        len: usize,
+    }
+
+    // the mapping is read-only
+    unsafe impl Send for Mmap {}
+    unsafe impl Sync for Mmap {}
+
+    impl Mmap {
+        pub(super) fn new(file: &File, len: usize) -> std::io::Result<Self> {
+            // SAFETY: mapping a valid file descriptor in read-only mode. len is not 0
+            let ptr = unsafe {
+                mmap(
+                    std::ptr::null_mut(),
+                    len,
+                    PROT_READ,
+                    MAP_PRIVATE,
+                    file.as_raw_fd(),
+                    0,
+                )
+            };
+            if ptr as isize == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(Self { ptr, len })
+        }
+        pub(super) fn as_bytes(&self) -> &[u8] {
+            // SAFETY: the mapping is valid for len bytes until dropped
+            unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.len) }
+        }
+    }
+
+    impl Drop for Mmap {
+        fn drop(&mut self) {
+            // SAFETY: ptr and len come from a successful mmap call
+            unsafe {
+                munmap(self.ptr, self.len);
+            }
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! "did you mean" suggestions : the keywords or symbols close to a misspelled word
+/// number of single character insertions, deletions, substitutions
+/// and transpositions of adjacent characters needed to change a into b
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // rows i-2, i-1 and i of the distance matrix
+    let mut before: Vec<usize> = vec![0; b.len() + 1];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        current[0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (previous[j] + 1).min(current[j - 1] + 1).min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before[j - 2] + 1);
+            }
+        }
+        std::mem::swap(&mut before, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// the maximum edit distance for a word of len characters to be considered a misspelling :
+/// 0 up to 2 characters, 1 up to 5 characters and 2 above
+pub fn max_distance(len: usize) -> usize {
+    match len {
+        0..=2 => 0,
+        3..=5 => 1,
+        _ => 2,
+    }
+}
+
+/// the candidates at an edit distance between 1 and max_distance of word,
+/// closest first, then in the order of the candidates
+pub fn suggestions<'a, S: AsRef<str>>(
+    word: &str,
+    candidates: &'a [S],
+    max_distance: usize,
+) -> Vec<&'a str> {
+    let mut found: Vec<(usize, &str)> = candidates
+        .iter()
+        .map(|candidate| candidate.as_ref())
+        .filter_map(|candidate| {
+            let distance = edit_distance(word, candidate);
+            (distance > 0 && distance <= max_distance).then_some((distance, candidate))
+        })
+        .collect();
+    found.sort_by_key(|(distance, _)| *distance);
+    found.into_iter().map(|(_, candidate)| candidate).collect()
+}
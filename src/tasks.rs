@@ -0,0 +1,69 @@
+//! Task markers like TODO or FIXME found in the comments of a source code
+use crate::{ScannerData, Span, TokenKind};
+
+/// The markers searched by default by find_tasks
+pub const DEFAULT_MARKERS: &[&str] = &["TODO", "FIXME", "HACK"];
+
+/// A task marker found in a comment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Task<'a> {
+    /// the marker, like "TODO"
+    pub marker: &'a str,
+    /// the text after the marker until the end of the line,
+    /// without the separator like ':' and without the comment end delimiter
+    pub text: &'a str,
+    /// position of the marker in the source code, in bytes
+    pub span: Span,
+    /// line of the marker
+    pub line: usize,
+}
+
+/// the task markers in the comment tokens, in the order of the source code.
+/// A marker must be a whole word, so "TODOS" is not a TODO.
+/// The comments must be tokens, so the source code must be scanned with comments enabled
+/// (the default) and without trivia
+pub fn find_tasks<'a>(data: &ScannerData<'a>, markers: &[&'a str]) -> Vec<Task<'a>> {
+    let source = data.source;
+    let mut tasks = Vec::new();
+    for index in 0..data.len() {
+        if data.token_kinds[index] != TokenKind::Comment {
+            continue;
+        }
+        let comment = data.token_spans[index];
+        let text = &source[comment.range()];
+        let word_char = |c: char| c.is_alphanumeric() || c == '_';
+        for (pos, _) in text.match_indices(|c: char| !word_char(c)).chain([(text.len(), "")]) {
+            // the word ending at pos
+            let word_start = text[..pos].rfind(|c: char| !word_char(c)).map_or(0, |p| p + 1);
+            let Some(marker) = markers.iter().find(|m| **m == &text[word_start..pos]) else {
+                continue;
+            };
+            let line_end = text[pos..].find('\n').map_or(text.len(), |p| pos + p);
+            let mut task = text[pos..line_end].trim_start_matches([':', ' ', '\t']);
+            if line_end == text.len() {
+                task = strip_comment_end(task);
+            }
+            let start = comment.start() + word_start;
+            tasks.push(Task {
+                marker,
+                text: task.trim_end(),
+                span: Span::new(start, marker.len()),
+                line: data.token_line(index) + text[..word_start].matches('\n').count(),
+            });
+        }
+    }
+    tasks
+}
+
+/// remove a comment end delimiter like "*/" or "]]" separated from the text by a space
+fn strip_comment_end(text: &str) -> &str {
+    let text = text.trim_end();
+    let delimiter_start = text
+        .rfind(|c: char| c.is_alphanumeric() || c.is_whitespace())
+        .map_or(0, |p| p + text[p..].chars().next().map_or(0, char::len_utf8));
+    if delimiter_start < text.len() && text[..delimiter_start].ends_with(char::is_whitespace) {
+        &text[..delimiter_start]
+    } else {
+        text
+    }
+}
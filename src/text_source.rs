@@ -0,0 +1,80 @@
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// A text that may not be stored in a single string, like the rope of a text editor.
+/// The scanner works on a single string: Scanner::run_text copies the chunks in a buffer,
+/// unless the text is stored in a single string. The offsets are in bytes.
+/// For example with the ropey crate :
+/// ```text
+/// impl TextSource for Rope {
+///     fn len(&self) -> usize {
+///         self.len_bytes()
+///     }
+///     fn chunks(&self) -> impl Iterator<Item = &str> {
+///         Rope::chunks(self)
+///     }
+/// }
+/// ```
+pub trait TextSource {
+    /// length of the text in bytes
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// the parts of the text, in order
+    fn chunks(&self) -> impl Iterator<Item = &str>;
+    /// a part of the text, borrowed when it is stored in a single chunk
+    fn slice(&self, range: Range<usize>) -> Cow<'_, str> {
+        let mut result = Cow::Borrowed("");
+        let mut start = 0;
+        for chunk in self.chunks() {
+            let end = start + chunk.len();
+            if start < range.end && range.start < end {
+                let part = &chunk[range.start.max(start) - start..range.end.min(end) - start];
+                match &mut result {
+                    Cow::Borrowed("") => result = Cow::Borrowed(part),
+                    result => result.to_mut().push_str(part),
+                }
+            }
+            if end >= range.end {
+                break;
+            }
+            start = end;
+        }
+        result
+    }
+    /// the whole text when it is stored in a single string, so it can be scanned without copying it
+    fn as_str(&self) -> Option<&str> {
+        None
+    }
+}
+
+impl TextSource for str {
+    fn len(&self) -> usize {
+        str::len(self)
+    }
+    fn slice(&self, range: Range<usize>) -> Cow<'_, str> {
+        Cow::Borrowed(&self[range])
+    }
+    fn chunks(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self)
+    }
+    fn as_str(&self) -> Option<&str> {
+        Some(self)
+    }
+}
+
+impl TextSource for String {
+    fn len(&self) -> usize {
+        self.as_str().len()
+    }
+    fn slice(&self, range: Range<usize>) -> Cow<'_, str> {
+        Cow::Borrowed(&self[range])
+    }
+    fn chunks(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.as_str())
+    }
+    fn as_str(&self) -> Option<&str> {
+        Some(self)
+    }
+}
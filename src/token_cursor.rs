@@ -0,0 +1,191 @@
+use crate::{ScannerData, Span, Token, TokenKind};
+
+/// A position in the tokens saved with TokenCursor::checkpoint
+/// to backtrack with TokenCursor::rewind or to compute the span of a syntax node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    index: usize,
+    last_end: usize,
+}
+
+/// The error returned by TokenCursor::expect when the next token is not the expected one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnexpectedToken {
+    /// description of the expected token, like "Identifier" or "`=`"
+    pub expected: String,
+    /// the token found instead, None at the end of the tokens
+    pub found: Option<Token>,
+}
+
+impl std::fmt::Display for UnexpectedToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.found {
+            Some(token) => write!(
+                f,
+                "expected {}, found {:?} at line {}",
+                self.expected, token.kind, token.line
+            ),
+            None => write!(f, "expected {}, found end of file", self.expected),
+        }
+    }
+}
+
+impl std::error::Error for UnexpectedToken {}
+
+/// A cursor over the tokens of a ScannerData, for recursive descent parsers.
+/// The cursor only reads the tokens, so checkpoints are cheap and backtracking is free.
+pub struct TokenCursor<'d, 'a> {
+    data: &'d ScannerData<'a>,
+    // index of the current token, never a skipped one
+    index: usize,
+    // end offset of the last consumed token, in bytes
+    last_end: usize,
+    // the tokens ignored by the cursor
+    skip: fn(TokenKind) -> bool,
+}
+
+impl<'d, 'a> TokenCursor<'d, 'a> {
+    pub fn new(data: &'d ScannerData<'a>) -> Self {
+        Self {
+            data,
+            index: 0,
+            last_end: 0,
+            skip: |_| false,
+        }
+    }
+    /// ignore the tokens of the kinds accepted by the predicate, for example the spaces and comments
+    /// when the source code was scanned with Scanner::with_whitespace
+    pub fn with_skip(mut self, skip: fn(TokenKind) -> bool) -> Self {
+        self.skip = skip;
+        self.index = self.skip_from(self.index);
+        self
+    }
+    /// the scanned tokens
+    pub fn data(&self) -> &'d ScannerData<'a> {
+        self.data
+    }
+    /// index of the current token in the ScannerData
+    pub fn index(&self) -> usize {
+        self.index
+    }
+    /// whether all the tokens were consumed
+    pub fn is_at_end(&self) -> bool {
+        self.index >= self.data.len()
+    }
+    /// the n-th token after the current position without consuming it. peek(0) is the current token
+    pub fn peek(&self, n: usize) -> Option<Token> {
+        self.data.get(self.nth_index(n))
+    }
+    /// the kind of the n-th token after the current position
+    pub fn peek_kind(&self, n: usize) -> Option<TokenKind> {
+        self.peek(n).map(|token| token.kind)
+    }
+    /// the text of the n-th token after the current position
+    pub fn peek_text(&self, n: usize) -> Option<&'d str> {
+        let data = self.data;
+        self.peek(n).map(|token| token.text(data.source))
+    }
+    /// consume the current token
+    pub fn bump(&mut self) -> Option<Token> {
+        let token = self.peek(0)?;
+        self.last_end = token.span.end();
+        self.index = self.skip_from(self.index + 1);
+        Some(token)
+    }
+    /// whether the current token is of the given kind
+    pub fn at(&self, kind: TokenKind) -> bool {
+        self.peek_kind(0) == Some(kind)
+    }
+    /// whether the current token is the given symbol, an operator or a punctuation
+    pub fn at_symbol(&self, symbol: &str) -> bool {
+        self.peek_kind(0).is_some_and(TokenKind::is_symbol) && self.peek_text(0) == Some(symbol)
+    }
+    /// whether the current token is the given keyword
+    pub fn at_keyword(&self, keyword: &str) -> bool {
+        self.at_text(TokenKind::Keyword, keyword)
+    }
+    /// consume the current token if it is of the given kind
+    pub fn eat(&mut self, kind: TokenKind) -> Option<Token> {
+        if self.at(kind) {
+            self.bump()
+        } else {
+            None
+        }
+    }
+    /// consume the current token if it is the given symbol
+    pub fn eat_symbol(&mut self, symbol: &str) -> Option<Token> {
+        if self.at_symbol(symbol) {
+            self.bump()
+        } else {
+            None
+        }
+    }
+    /// consume the current token if it is the given keyword
+    pub fn eat_keyword(&mut self, keyword: &str) -> Option<Token> {
+        if self.at_keyword(keyword) {
+            self.bump()
+        } else {
+            None
+        }
+    }
+    /// consume the current token, or return an error if it is not of the given kind
+    pub fn expect(&mut self, kind: TokenKind) -> Result<Token, UnexpectedToken> {
+        self.eat(kind).ok_or_else(|| self.unexpected(format!("{:?}", kind)))
+    }
+    /// consume the current token, or return an error if it is not the given symbol
+    pub fn expect_symbol(&mut self, symbol: &str) -> Result<Token, UnexpectedToken> {
+        self.eat_symbol(symbol)
+            .ok_or_else(|| self.unexpected(format!("`{}`", symbol)))
+    }
+    /// consume the current token, or return an error if it is not the given keyword
+    pub fn expect_keyword(&mut self, keyword: &str) -> Result<Token, UnexpectedToken> {
+        self.eat_keyword(keyword)
+            .ok_or_else(|| self.unexpected(format!("`{}`", keyword)))
+    }
+    /// an error for the current token, with a description of the expected token
+    pub fn unexpected(&self, expected: impl Into<String>) -> UnexpectedToken {
+        UnexpectedToken {
+            expected: expected.into(),
+            found: self.peek(0),
+        }
+    }
+    /// save the current position
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            index: self.index,
+            last_end: self.last_end,
+        }
+    }
+    /// go back to a saved position
+    pub fn rewind(&mut self, checkpoint: Checkpoint) {
+        self.index = checkpoint.index;
+        self.last_end = checkpoint.last_end;
+    }
+    /// the source code span of the tokens consumed since a checkpoint,
+    /// from the start of the first one to the end of the last one
+    pub fn span_since(&self, checkpoint: Checkpoint) -> Span {
+        let start = match self.data.token_spans.get(checkpoint.index) {
+            Some(span) => span.start(),
+            None => self.data.source.len(),
+        };
+        Span::new(start, self.last_end.saturating_sub(start))
+    }
+    fn at_text(&self, kind: TokenKind, text: &str) -> bool {
+        self.at(kind) && self.peek_text(0) == Some(text)
+    }
+    /// index of the n-th token after the current one, skipping the ignored tokens
+    fn nth_index(&self, n: usize) -> usize {
+        let mut index = self.index;
+        for _ in 0..n {
+            index = self.skip_from(index + 1);
+        }
+        index
+    }
+    /// index of the first token not ignored, starting at index
+    fn skip_from(&self, mut index: usize) -> usize {
+        while index < self.data.len() && (self.skip)(self.data.token_kinds[index]) {
+            index += 1;
+        }
+        index
+    }
+}
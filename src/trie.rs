@@ -0,0 +1,58 @@
+/// Prefix tree over the symbols or the keywords of a config,
+/// to find the entries matching at a position without testing each of them.
+#[derive(Debug, Clone)]
+pub(crate) struct Trie {
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Node {
+    /// next byte and child node index, sorted by byte
+    children: Vec<(u8, usize)>,
+    /// whether an entry ends here
+    entry: bool,
+}
+
+impl Trie {
+    pub(crate) fn new<'a>(entries: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut trie = Self {
+            nodes: vec![Node::default()],
+        };
+        for entry in entries {
+            if entry.is_empty() {
+                continue;
+            }
+            let mut node = 0;
+            for byte in entry.bytes() {
+                node = match trie.nodes[node].children.binary_search_by_key(&byte, |c| c.0) {
+                    Ok(pos) => trie.nodes[node].children[pos].1,
+                    Err(pos) => {
+                        let child = trie.nodes.len();
+                        trie.nodes.push(Node::default());
+                        trie.nodes[node].children.insert(pos, (byte, child));
+                        child
+                    }
+                };
+            }
+            trie.nodes[node].entry = true;
+        }
+        trie
+    }
+    /// the length in bytes of the longest entry matching at the start of text,
+    /// among those accepted by the filter
+    pub(crate) fn find(&self, text: &str, accept: impl Fn(usize) -> bool) -> Option<usize> {
+        let mut best = None;
+        let mut node = 0;
+        for (i, byte) in text.bytes().enumerate() {
+            let children = &self.nodes[node].children;
+            node = match children.binary_search_by_key(&byte, |c| c.0) {
+                Ok(pos) => children[pos].1,
+                Err(_) => break,
+            };
+            if self.nodes[node].entry && accept(i + 1) {
+                best = Some(i + 1);
+            }
+        }
+        best
+    }
+}
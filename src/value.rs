@@ -0,0 +1,564 @@
+//! A minimal data model with JSON and TOML readers, used to load language definitions
+//! and to export scanner results without external dependencies.
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    /// key/value pairs, in declaration order
+    Object(Vec<(String, Value)>),
+}
+
+/// Error while parsing a JSON or TOML document
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueError {
+    /// line of the error in the document
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} : {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ValueError {}
+
+impl Value {
+    /// parse a JSON document
+    pub fn from_json(text: &str) -> Result<Value, ValueError> {
+        let mut parser = Parser::new(text);
+        let value = parser.json_value()?;
+        parser.skip_space(false);
+        if parser.peek().is_some() {
+            return Err(parser.error("trailing characters"));
+        }
+        Ok(value)
+    }
+    /// parse a TOML document. Only a subset of TOML is supported :
+    /// `[table]` headers, dotted and quoted keys, basic and literal strings on one line,
+    /// decimal integers and floats, booleans, arrays and inline tables, `#` comments.
+    /// Arrays of tables, multi-line strings, dates and times, hexadecimal, octal and binary
+    /// integers, inf and nan are errors
+    pub fn from_toml(text: &str) -> Result<Value, ValueError> {
+        Parser::new(text).toml_document()
+    }
+    /// value of a key in an object
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+    /// the value of a non negative integer number
+    pub fn as_usize(&self) -> Option<usize> {
+        let n = self.as_f64()?;
+        (n >= 0.0 && n.fract() == 0.0 && n <= usize::MAX as f64).then_some(n as usize)
+    }
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+    pub fn as_object(&self) -> Option<&[(String, Value)]> {
+        match self {
+            Value::Object(fields) => Some(fields),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_owned())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<usize> for Value {
+    fn from(n: usize) -> Self {
+        Value::Number(n as f64)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Self {
+        Value::Number(n)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(v: Option<T>) -> Self {
+        v.map_or(Value::Null, Into::into)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(v: Vec<T>) -> Self {
+        Value::Array(v.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Display writes the value as compact JSON
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "null"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(n) if n.is_finite() => write!(f, "{}", n),
+            Value::Number(_) => write!(f, "null"),
+            Value::String(s) => write_json_string(f, s),
+            Value::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            Value::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_json_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_json_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+/// the maximum nesting of arrays and objects, so that a malicious file cannot overflow the stack
+const MAX_DEPTH: usize = 128;
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+    line: usize,
+    /// number of arrays and objects being parsed
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            pos: 0,
+            line: 1,
+            depth: 0,
+        }
+    }
+    fn error(&self, message: &str) -> ValueError {
+        ValueError {
+            line: self.line,
+            message: message.to_owned(),
+        }
+    }
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+    fn eat(&mut self, s: &str) -> bool {
+        if self.text[self.pos..].starts_with(s) {
+            for _ in s.chars() {
+                self.bump();
+            }
+            return true;
+        }
+        false
+    }
+    fn expect(&mut self, c: char) -> Result<(), ValueError> {
+        if self.peek() == Some(c) {
+            self.bump();
+            return Ok(());
+        }
+        Err(self.error(&format!("expected '{}'", c)))
+    }
+    /// skip spaces, and comments if allowed (TOML). New lines are skipped too
+    fn skip_space(&mut self, comments: bool) {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() {
+                self.bump();
+            } else if comments && c == '#' {
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.bump();
+                }
+            } else {
+                break;
+            }
+        }
+    }
+    /// skip spaces on the current line, and a trailing comment (TOML)
+    fn skip_line_space(&mut self) {
+        while self.peek().is_some_and(|c| c == ' ' || c == '\t' || c == '\r') {
+            self.bump();
+        }
+        if self.peek() == Some('#') {
+            while self.peek().is_some_and(|c| c != '\n') {
+                self.bump();
+            }
+        }
+    }
+    /// parse a value with parse, failing if the values are nested more than MAX_DEPTH times
+    fn nested(
+        &mut self,
+        parse: fn(&mut Self) -> Result<Value, ValueError>,
+    ) -> Result<Value, ValueError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(self.error("values nested too deeply"));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+    fn json_value(&mut self) -> Result<Value, ValueError> {
+        self.nested(Self::json_content)
+    }
+    fn json_content(&mut self) -> Result<Value, ValueError> {
+        self.skip_space(false);
+        match self.peek() {
+            Some('{') => {
+                self.bump();
+                let mut fields = Vec::new();
+                self.skip_space(false);
+                if self.peek() == Some('}') {
+                    self.bump();
+                    return Ok(Value::Object(fields));
+                }
+                loop {
+                    self.skip_space(false);
+                    let key = self.quoted_string()?;
+                    self.skip_space(false);
+                    self.expect(':')?;
+                    let value = self.json_value()?;
+                    fields.push((key, value));
+                    self.skip_space(false);
+                    match self.bump() {
+                        Some(',') => (),
+                        Some('}') => return Ok(Value::Object(fields)),
+                        _ => return Err(self.error("expected ',' or '}'")),
+                    }
+                }
+            }
+            Some('[') => {
+                self.bump();
+                let mut items = Vec::new();
+                self.skip_space(false);
+                if self.peek() == Some(']') {
+                    self.bump();
+                    return Ok(Value::Array(items));
+                }
+                loop {
+                    items.push(self.json_value()?);
+                    self.skip_space(false);
+                    match self.bump() {
+                        Some(',') => (),
+                        Some(']') => return Ok(Value::Array(items)),
+                        _ => return Err(self.error("expected ',' or ']'")),
+                    }
+                }
+            }
+            Some('"') => Ok(Value::String(self.quoted_string()?)),
+            _ => {
+                if self.eat("true") {
+                    Ok(Value::Bool(true))
+                } else if self.eat("false") {
+                    Ok(Value::Bool(false))
+                } else if self.eat("null") {
+                    Ok(Value::Null)
+                } else {
+                    self.number()
+                }
+            }
+        }
+    }
+    fn number(&mut self) -> Result<Value, ValueError> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.' | '_'))
+        {
+            self.bump();
+        }
+        let text = self.text[start..self.pos].replace('_', "");
+        // the standard parser also accepts "inf" and "NaN"
+        if !text.chars().all(|c| c.is_ascii_digit() || matches!(c, '+' | '-' | '.' | 'e' | 'E')) {
+            return Err(self.error("invalid value"));
+        }
+        text.parse()
+            .map(Value::Number)
+            .map_err(|_| self.error("invalid value"))
+    }
+    /// a double quoted string with JSON/TOML escape sequences
+    fn quoted_string(&mut self) -> Result<String, ValueError> {
+        self.expect('"')?;
+        let mut value = String::new();
+        loop {
+            match self.bump() {
+                None | Some('\n') => return Err(self.error("unterminated string")),
+                Some('"') => return Ok(value),
+                Some('\\') => match self.bump() {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some('r') => value.push('\r'),
+                    Some('b') => value.push('\u{8}'),
+                    Some('f') => value.push('\u{c}'),
+                    Some('u') => {
+                        let mut code = self.hex_code(4)?;
+                        if (0xd800..0xdc00).contains(&code) && self.eat("\\u") {
+                            let low = self.hex_code(4)?;
+                            code = 0x10000 + ((code - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff);
+                        }
+                        value.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                    }
+                    Some('U') => {
+                        let code = self.hex_code(8)?;
+                        value.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                    }
+                    Some(c) => value.push(c),
+                    None => return Err(self.error("unterminated string")),
+                },
+                Some(c) => value.push(c),
+            }
+        }
+    }
+    fn hex_code(&mut self, digits: usize) -> Result<u32, ValueError> {
+        let mut code = 0;
+        for _ in 0..digits {
+            let digit = self
+                .bump()
+                .and_then(|c| c.to_digit(16))
+                .ok_or_else(|| self.error("invalid unicode escape"))?;
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+    fn literal_string(&mut self) -> Result<String, ValueError> {
+        self.expect('\'')?;
+        let start = self.pos;
+        loop {
+            match self.bump() {
+                None | Some('\n') => return Err(self.error("unterminated string")),
+                Some('\'') => return Ok(self.text[start..self.pos - 1].to_owned()),
+                _ => (),
+            }
+        }
+    }
+    fn toml_document(&mut self) -> Result<Value, ValueError> {
+        let mut root = Vec::new();
+        let mut table: Vec<String> = Vec::new();
+        loop {
+            self.skip_space(true);
+            match self.peek() {
+                None => return Ok(Value::Object(root)),
+                Some('[') => {
+                    self.bump();
+                    table = self.toml_key()?;
+                    self.expect(']')?;
+                    insert(&mut root, &table, Value::Object(Vec::new()), self.line)?;
+                }
+                Some(_) => {
+                    let mut path = table.clone();
+                    path.extend(self.toml_key()?);
+                    self.expect('=')?;
+                    let value = self.toml_value()?;
+                    insert(&mut root, &path, value, self.line)?;
+                }
+            }
+            self.skip_line_space();
+            if !matches!(self.peek(), None | Some('\n')) {
+                return Err(self.error("expected a new line"));
+            }
+        }
+    }
+    /// a dotted key, with surrounding spaces
+    fn toml_key(&mut self) -> Result<Vec<String>, ValueError> {
+        let mut path = Vec::new();
+        loop {
+            self.skip_line_space();
+            let part = match self.peek() {
+                Some('"') => self.quoted_string()?,
+                Some('\'') => self.literal_string()?,
+                _ => {
+                    let start = self.pos;
+                    while self
+                        .peek()
+                        .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+                    {
+                        self.bump();
+                    }
+                    if start == self.pos {
+                        return Err(self.error("expected a key"));
+                    }
+                    self.text[start..self.pos].to_owned()
+                }
+            };
+            path.push(part);
+            self.skip_line_space();
+            if !self.eat(".") {
+                return Ok(path);
+            }
+        }
+    }
+    fn toml_value(&mut self) -> Result<Value, ValueError> {
+        self.nested(Self::toml_content)
+    }
+    fn toml_content(&mut self) -> Result<Value, ValueError> {
+        self.skip_line_space();
+        match self.peek() {
+            Some('"') => Ok(Value::String(self.quoted_string()?)),
+            Some('\'') => Ok(Value::String(self.literal_string()?)),
+            Some('[') => {
+                self.bump();
+                let mut items = Vec::new();
+                loop {
+                    self.skip_space(true);
+                    if self.peek() == Some(']') {
+                        self.bump();
+                        return Ok(Value::Array(items));
+                    }
+                    items.push(self.toml_value()?);
+                    self.skip_space(true);
+                    match self.peek() {
+                        Some(',') => {
+                            self.bump();
+                        }
+                        Some(']') => (),
+                        _ => return Err(self.error("expected ',' or ']'")),
+                    }
+                }
+            }
+            Some('{') => {
+                self.bump();
+                let mut fields = Vec::new();
+                loop {
+                    self.skip_line_space();
+                    if self.peek() == Some('}') {
+                        self.bump();
+                        return Ok(Value::Object(fields));
+                    }
+                    let path = self.toml_key()?;
+                    self.expect('=')?;
+                    let value = self.toml_value()?;
+                    insert(&mut fields, &path, value, self.line)?;
+                    self.skip_line_space();
+                    if !self.eat(",") && self.peek() != Some('}') {
+                        return Err(self.error("expected ',' or '}'"));
+                    }
+                }
+            }
+            _ => {
+                if self.eat("true") {
+                    Ok(Value::Bool(true))
+                } else if self.eat("false") {
+                    Ok(Value::Bool(false))
+                } else {
+                    self.number()
+                }
+            }
+        }
+    }
+}
+
+/// insert a value in a tree of objects, creating the intermediate tables
+fn insert(
+    fields: &mut Vec<(String, Value)>,
+    path: &[String],
+    value: Value,
+    line: usize,
+) -> Result<(), ValueError> {
+    let error = |message: &str| ValueError {
+        line,
+        message: format!("{} '{}'", message, path.join(".")),
+    };
+    let (key, rest) = path.split_first().ok_or_else(|| error("empty key"))?;
+    let pos = fields.iter().position(|(k, _)| k == key);
+    if rest.is_empty() {
+        return match (pos, value) {
+            // a table header for an already defined table
+            (Some(i), Value::Object(new)) if new.is_empty() && matches!(fields[i].1, Value::Object(_)) => {
+                Ok(())
+            }
+            (Some(_), _) => Err(error("duplicate key")),
+            (None, value) => {
+                fields.push((key.clone(), value));
+                Ok(())
+            }
+        };
+    }
+    let i = match pos {
+        Some(i) => i,
+        None => {
+            fields.push((key.clone(), Value::Object(Vec::new())));
+            fields.len() - 1
+        }
+    };
+    match &mut fields[i].1 {
+        Value::Object(sub) => insert(sub, rest, value, line),
+        _ => Err(error("not a table")),
+    }
+}
@@ -0,0 +1,83 @@
+use crate::{ScannerData, Span, TokenKind, TokenType};
+
+/// A token given to a TokenVisitor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VisitedToken<'a> {
+    /// index of the token in the ScannerData
+    pub index: usize,
+    pub kind: TokenKind,
+    /// token position in the source code in bytes
+    pub span: Span,
+    /// token start line in the source code
+    pub line: usize,
+    /// token text in the source code
+    pub text: &'a str,
+    /// token value, None if the scanner was configured with `with_values(false)`
+    pub value: Option<&'a TokenType>,
+}
+
+/// Callbacks called for each token by ScannerData::accept, to write analysis passes
+/// without matching the token kinds. The callbacks of the specific kinds call visit_token
+/// by default, so a visitor only implements the callbacks it needs
+pub trait TokenVisitor {
+    /// called for the tokens without a specific callback, and by the callbacks not implemented
+    fn visit_token(&mut self, _token: &VisitedToken<'_>) {}
+    fn visit_keyword(&mut self, token: &VisitedToken<'_>) {
+        self.visit_token(token)
+    }
+    fn visit_identifier(&mut self, token: &VisitedToken<'_>) {
+        self.visit_token(token)
+    }
+    /// symbols and punctuation
+    fn visit_symbol(&mut self, token: &VisitedToken<'_>) {
+        self.visit_token(token)
+    }
+    /// string literals and the string parts of interpolated strings
+    fn visit_string(&mut self, token: &VisitedToken<'_>) {
+        self.visit_token(token)
+    }
+    fn visit_char(&mut self, token: &VisitedToken<'_>) {
+        self.visit_token(token)
+    }
+    fn visit_number(&mut self, token: &VisitedToken<'_>) {
+        self.visit_token(token)
+    }
+    fn visit_comment(&mut self, token: &VisitedToken<'_>) {
+        self.visit_token(token)
+    }
+    fn visit_directive(&mut self, token: &VisitedToken<'_>) {
+        self.visit_token(token)
+    }
+    fn visit_attribute(&mut self, token: &VisitedToken<'_>) {
+        self.visit_token(token)
+    }
+}
+
+impl ScannerData<'_> {
+    /// call the callback of the visitor matching the kind of each token, in the order of the tokens
+    pub fn accept<V: TokenVisitor + ?Sized>(&self, visitor: &mut V) {
+        for index in 0..self.len() {
+            let kind = self.token_kinds[index];
+            let token = VisitedToken {
+                index,
+                kind,
+                span: self.token_spans[index],
+                line: self.token_line(index),
+                text: self.token_text(index),
+                value: self.token_types.get(index),
+            };
+            match kind {
+                TokenKind::Keyword => visitor.visit_keyword(&token),
+                TokenKind::Identifier => visitor.visit_identifier(&token),
+                TokenKind::Symbol | TokenKind::Punctuation => visitor.visit_symbol(&token),
+                TokenKind::StringLiteral | TokenKind::StringPart => visitor.visit_string(&token),
+                TokenKind::CharLiteral => visitor.visit_char(&token),
+                TokenKind::NumberLiteral => visitor.visit_number(&token),
+                TokenKind::Comment => visitor.visit_comment(&token),
+                TokenKind::Directive => visitor.visit_directive(&token),
+                TokenKind::Attribute => visitor.visit_attribute(&token),
+                _ => visitor.visit_token(&token),
+            }
+        }
+    }
+}